@@ -0,0 +1,58 @@
+#ifndef FIND_PROJECT_H
+#define FIND_PROJECT_H
+
+/* Generated by cbindgen from src/ffi.rs, don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Search options. Passing NULL instead uses the defaults, which are all
+ * zeros.
+ */
+typedef struct FpOptions {
+  bool include_hidden;
+  bool include_vendor;
+  /**
+   * Only match git repositories.
+   */
+  bool projects_only;
+  /**
+   * Don't search deeper than this many folders below the root, 0 means
+   * no limit.
+   */
+  size_t max_depth;
+} FpOptions;
+
+/**
+ * Returns the path of the first folder named `query` inside `root`, or
+ * NULL when there's none or the search failed, in which case
+ * `fp_last_error` says why. The path must be released with `fp_free`.
+ *
+ * # Safety
+ *
+ * `root` and `query` must be valid NUL-terminated strings, and `options`
+ * either NULL or a valid pointer.
+ */
+char *fp_find(const char *root, const char *query, const struct FpOptions *options);
+
+/**
+ * Returns why the last `fp_find` on this thread failed, or NULL when it
+ * didn't. The message stays valid until the next `fp_find`.
+ */
+const char *fp_last_error(void);
+
+/**
+ * Releases a path returned by `fp_find`.
+ *
+ * # Safety
+ *
+ * `path` must be NULL or a pointer returned by `fp_find`, not released
+ * before.
+ */
+void fp_free(char *path);
+
+#endif /* FIND_PROJECT_H */
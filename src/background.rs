@@ -0,0 +1,189 @@
+use crate::error::{FindError, Result};
+use crate::{Finder, FinderOptions};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+/// A search running on its own thread, which resolves once it's done.
+/// It works with any async runtime, since it doesn't block the thread
+/// polling it.
+///
+/// Dropping it stops the search, unless the finder was given its own
+/// [`FinderOptions::cancel`] flag, which is then the only way to stop it.
+/// A search that panics resolves to [`FindError::Panicked`].
+pub struct Search<T> {
+    state: Arc<Mutex<State<T>>>,
+    stop: Option<Arc<AtomicBool>>,
+}
+
+struct State<T> {
+    result: Option<Result<T>>,
+    waker: Option<Waker>,
+}
+
+impl<T> Future for Search<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().expect("the search thread doesn't panic");
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for Search<T> {
+    fn drop(&mut self) {
+        if let Some(stop) = &self.stop {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Finder {
+    /// Like [`Finder::find`], without blocking the caller.
+    pub fn find_async(&self, name: &str) -> Search<Option<PathBuf>> {
+        let (finder, stop) = self.stoppable();
+        let name = name.to_string();
+        spawn(finder.options().root.clone(), stop, move || {
+            finder.find(&name)
+        })
+    }
+
+    /// Like [`Finder::find_all`], without blocking the caller.
+    pub fn find_all_async(&self, name: &str) -> Search<Vec<PathBuf>> {
+        let (finder, stop) = self.stoppable();
+        let name = name.to_string();
+        spawn(finder.options().root.clone(), stop, move || {
+            finder.find_all(&name)
+        })
+    }
+
+    // Returns a copy of the finder to search in the background, with a
+    // flag to stop it when the search is dropped, unless it already has
+    // one.
+    fn stoppable(&self) -> (Finder, Option<Arc<AtomicBool>>) {
+        if self.options().cancel.is_some() {
+            return (self.clone(), None);
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        let finder = Finder::new(FinderOptions {
+            cancel: Some(Arc::clone(&stop)),
+            ..self.options().clone()
+        });
+        (finder, Some(stop))
+    }
+}
+
+// Runs the search in root on a new thread, waking up whoever is waiting
+// for it once it's done.
+fn spawn<T: Send + 'static>(
+    root: PathBuf,
+    stop: Option<Arc<AtomicBool>>,
+    search: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Search<T> {
+    let state = Arc::new(Mutex::new(State {
+        result: None,
+        waker: None,
+    }));
+    let shared = Arc::clone(&state);
+    thread::spawn(move || {
+        // Whoever is waiting would wait forever for a search that
+        // panicked.
+        let result = panic::catch_unwind(AssertUnwindSafe(search))
+            .unwrap_or(Err(FindError::Panicked { path: root }));
+        let mut state = shared.lock().expect("the polling thread doesn't panic");
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    });
+    Search { state, stop }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FinderOptions;
+    use std::fs;
+    use std::task::Wake;
+    use std::thread::Thread;
+    use tempfile::tempdir;
+
+    // Polls a future on the current thread until it's done.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        struct Unpark(Thread);
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_async() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("acme").join("api"))?;
+        fs::create_dir_all(path.join("tools").join("api"))?;
+
+        let finder = Finder::new(FinderOptions::new(path).sort_alphabetically(true));
+        assert_eq!(
+            block_on(finder.find_async("api"))?,
+            Some(path.join("acme").join("api"))
+        );
+        assert_eq!(
+            block_on(finder.find_all_async("api"))?,
+            vec![
+                path.join("acme").join("api"),
+                path.join("tools").join("api")
+            ]
+        );
+        assert!(block_on(Finder::new(FinderOptions::default()).find_async("api")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_search() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let finder = Finder::new(FinderOptions::new(dir.path()));
+        let search = finder.find_all_async("api");
+        let stop = search.stop.clone().expect("the search can be stopped");
+        drop(search);
+        assert!(stop.load(Ordering::Relaxed));
+
+        // A finder with its own flag is only stopped by it.
+        let cancel = Arc::new(AtomicBool::new(false));
+        let finder = Finder::new(FinderOptions::new(dir.path()).cancel(Arc::clone(&cancel)));
+        assert!(finder.find_all_async("api").stop.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_panicking_search() {
+        let search = spawn(PathBuf::from("root"), None, || -> Result<()> {
+            panic!("the search has a bug")
+        });
+        assert!(matches!(block_on(search), Err(FindError::Panicked { .. })));
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{bail, Context, Result};
+use find_project::{Finder, FinderOptions, Matcher};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::time::{Duration, Instant};
+
+/// Name of the only folder matched by the searches for a single folder,
+/// created last so finding it means walking the whole tree.
+const NEEDLE: &str = "needle";
+
+/// Shape of the synthetic tree searched by the benchmark.
+pub struct Tree {
+    /// Folders inside every folder that isn't a project.
+    pub breadth: usize,
+    /// Levels of folders above the projects.
+    pub depth: usize,
+}
+
+impl Tree {
+    /// Creates the tree inside dir: `depth` levels of `breadth` folders,
+    /// with a project at the bottom of each one, holding a `.git` marker
+    /// and a `src` folder. Returns how many folders were created.
+    pub fn generate(&self, dir: &Path) -> Result<usize> {
+        let mut created = 0;
+        let mut level = vec![dir.to_path_buf()];
+        for depth in 0..self.depth {
+            let mut next = Vec::with_capacity(level.len() * self.breadth);
+            for parent in &level {
+                for i in 0..self.breadth {
+                    next.push(parent.join(format!("group-{}-{}", depth, i)));
+                }
+            }
+            level = next;
+        }
+
+        for (i, parent) in level.iter().enumerate() {
+            let project = parent.join(format!("project-{}", i));
+            for folder in [".git", "src"] {
+                fs::create_dir_all(project.join(folder))
+                    .with_context(|| format!("Unable to create {:?}", project))?;
+            }
+            created += 3;
+        }
+        let needle = level.last().expect("at least the root").join(NEEDLE);
+        fs::create_dir_all(&needle).with_context(|| format!("Unable to create {:?}", needle))?;
+
+        // Every level above the projects, plus the needle.
+        created += (1..=self.depth)
+            .map(|d| self.breadth.pow(d as u32))
+            .sum::<usize>()
+            + 1;
+        Ok(created)
+    }
+}
+
+// A search timed by the benchmark.
+struct Scenario {
+    name: &'static str,
+    options: fn(FinderOptions) -> FinderOptions,
+    query: &'static str,
+    all: bool,
+}
+
+const SCENARIOS: &[Scenario] = &[
+    Scenario {
+        name: "first match",
+        options: |options| options,
+        query: NEEDLE,
+        all: false,
+    },
+    Scenario {
+        name: "first match, sorted",
+        options: |options| options.sort_alphabetically(true),
+        query: NEEDLE,
+        all: false,
+    },
+    Scenario {
+        name: "first match, fuzzy",
+        options: |options| options.matcher(Matcher::Fuzzy),
+        query: "ndl",
+        all: false,
+    },
+    Scenario {
+        name: "every match",
+        options: |options| options,
+        query: "src",
+        all: true,
+    },
+    Scenario {
+        name: "every project",
+        options: |options| options.stop_at_projects(true),
+        query: "",
+        all: true,
+    },
+];
+
+/// Generates a synthetic tree, or reuses the one in `dir`, and times the
+/// same searches against it `runs` times each, printing how long the
+/// first run and the median of all of them took.
+pub fn run(tree: &Tree, runs: usize, dir: Option<&Path>) -> Result<()> {
+    if tree.breadth == 0 || runs == 0 {
+        bail!("--breadth and --runs must be at least 1");
+    }
+
+    // Without a folder, the tree is generated in a temporary one, removed
+    // at the end.
+    let temporary = dir.is_none();
+    let dir = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => env::temp_dir().join(format!("find-project-bench-{}", process::id())),
+    };
+    if fs::read_dir(&dir).is_ok_and(|mut entries| entries.next().is_some()) {
+        eprintln!("Reusing the tree in {}", dir.display());
+    } else {
+        let started = Instant::now();
+        let created = tree.generate(&dir)?;
+        eprintln!(
+            "Created {} folders in {} in {}",
+            created,
+            dir.display(),
+            format_duration(started.elapsed())
+        );
+    }
+
+    let result = time_scenarios(&dir, runs);
+    if temporary {
+        fs::remove_dir_all(&dir).with_context(|| format!("Unable to remove {:?}", dir))?;
+    }
+    result
+}
+
+fn time_scenarios(dir: &Path, runs: usize) -> Result<()> {
+    println!(
+        "{:<22}  {:>8}  {:>10}  {:>10}",
+        "search", "matches", "first", "median"
+    );
+    for scenario in SCENARIOS {
+        let finder = Finder::new((scenario.options)(FinderOptions::new(dir).marker(".git")));
+        let mut times = Vec::with_capacity(runs);
+        let mut matches = 0;
+        for _ in 0..runs {
+            let started = Instant::now();
+            matches = if scenario.all {
+                finder.find_all(scenario.query)?.len()
+            } else {
+                finder.find(scenario.query)?.map_or(0, |_| 1)
+            };
+            times.push(started.elapsed());
+        }
+
+        let first = times[0];
+        times.sort();
+        println!(
+            "{:<22}  {:>8}  {:>10}  {:>10}",
+            scenario.name,
+            matches,
+            format_duration(first),
+            format_duration(times[times.len() / 2])
+        );
+    }
+    Ok(())
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_millis(1) {
+        format!("{}µs", duration.as_micros())
+    } else if duration < Duration::from_secs(1) {
+        format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_generate() -> Result<()> {
+        let dir = tempdir()?;
+        let tree = Tree {
+            breadth: 3,
+            depth: 2,
+        };
+        // 3 + 9 groups, 9 projects with 2 folders each, and the needle.
+        assert_eq!(tree.generate(dir.path())?, 40);
+
+        let finder = Finder::new(FinderOptions::new(dir.path()).marker(".git"));
+        assert_eq!(finder.find_all("src")?.len(), 9);
+        assert_eq!(
+            finder.find(NEEDLE)?,
+            Some(dir.path().join("group-0-2").join("group-1-2").join(NEEDLE))
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+use crate::error::{FindError, Result};
+use std::path::PathBuf;
+
+/// Where repositories are cloned, relative to the root, unless configured
+/// otherwise.
+pub const DEFAULT_TEMPLATE: &str = "{host}/{org}/{repo}";
+
+/// Host used for `org/repo` shorthands, unless configured otherwise.
+pub const DEFAULT_HOST: &str = "github.com";
+
+/// A repository to clone, split into the parts used to build its path.
+#[derive(Debug, PartialEq)]
+pub struct Remote {
+    /// URL given to `git clone`.
+    pub url: String,
+    pub host: String,
+    /// Owner of the repository. Nested groups, as in GitLab, are kept
+    /// together, like "group/subgroup".
+    pub org: String,
+    pub repo: String,
+}
+
+impl Remote {
+    /// Parses a clone URL (`https://`, `ssh://`, `git://` or scp-like
+    /// `git@host:org/repo`) or an `org/repo` shorthand for a repository
+    /// on `default_host`.
+    pub fn parse(spec: &str, default_host: &str) -> Result<Self> {
+        let spec = spec.trim();
+
+        let (url, host, path) = if let Some((scheme, rest)) = spec.split_once("://") {
+            if !matches!(scheme, "https" | "http" | "ssh" | "git" | "git+ssh") {
+                return Err(FindError::Invalid(format!(
+                    "Unsupported URL scheme \"{}\" in {:?}",
+                    scheme, spec
+                )));
+            }
+            let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+            (spec.to_string(), host_name(authority), path)
+        } else if let Some((authority, path)) = spec
+            .split_once(':')
+            .filter(|(authority, _)| !authority.contains('/'))
+        {
+            (spec.to_string(), host_name(authority), path)
+        } else {
+            let path = spec.trim_matches('/');
+            (
+                format!(
+                    "https://{}/{}.git",
+                    default_host,
+                    path.trim_end_matches(".git")
+                ),
+                default_host.to_string(),
+                path,
+            )
+        };
+
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        let parts: Vec<&str> = path.split('/').collect();
+        if host.is_empty() || parts.len() < 2 {
+            return Err(FindError::Invalid(format!(
+                "Unable to understand {:?}, expected a URL or \"org/repo\"",
+                spec
+            )));
+        }
+        if parts
+            .iter()
+            .any(|p| p.is_empty() || *p == "." || *p == "..")
+        {
+            return Err(FindError::Invalid(format!(
+                "Invalid repository path in {:?}",
+                spec
+            )));
+        }
+
+        let (repo, org) = parts.split_last().expect("at least two parts");
+        Ok(Remote {
+            url,
+            host,
+            org: org.join("/"),
+            repo: repo.to_string(),
+        })
+    }
+
+    /// Parses a query that names a repository rather than a folder: a
+    /// clone URL, or a path that starts with a host like
+    /// "github.com/org/repo". Plain folder names return None.
+    pub fn from_query(query: &str) -> Option<Self> {
+        if query.contains("://") {
+            return Self::parse(query, DEFAULT_HOST).ok();
+        }
+
+        let (host, path) = query.split_once('/')?;
+        if let Some((_, host)) = host.split_once(':') {
+            // Queries like git@github.com:org/repo, where the part after
+            // the colon is the start of the path.
+            return Self::parse(query, DEFAULT_HOST)
+                .ok()
+                .filter(|_| !host.is_empty());
+        }
+        if !host.contains('.') {
+            return None;
+        }
+        Self::parse(path, host).ok()
+    }
+
+    /// Returns whether both remotes point to the same repository, no
+    /// matter the protocol used to reach it.
+    pub fn same_repository(&self, other: &Remote) -> bool {
+        self.host.eq_ignore_ascii_case(&other.host)
+            && self.org.eq_ignore_ascii_case(&other.org)
+            && self.repo.eq_ignore_ascii_case(&other.repo)
+    }
+
+    /// Returns the path to clone into, relative to the root, by replacing
+    /// `{host}`, `{org}` and `{repo}` in the template.
+    pub fn path(&self, template: &str) -> Result<PathBuf> {
+        let mut path = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            path.push_str(&rest[..start]);
+            let Some(end) = rest[start..].find('}') else {
+                return Err(FindError::Invalid(format!(
+                    "Unterminated placeholder in clone template {:?}",
+                    template
+                )));
+            };
+            path.push_str(match &rest[start + 1..start + end] {
+                "host" => &self.host,
+                "org" => &self.org,
+                "repo" => &self.repo,
+                other => {
+                    return Err(FindError::Invalid(format!(
+                        "Unknown placeholder \"{{{}}}\" in clone template {:?}, \
+                        expected {{host}}, {{org}} or {{repo}}",
+                        other, template
+                    )))
+                }
+            });
+            rest = &rest[start + end + 1..];
+        }
+        path.push_str(rest);
+        Ok(PathBuf::from(path))
+    }
+}
+
+// Returns the host of an URL authority, without the user or the port.
+fn host_name(authority: &str) -> String {
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = host.split_once(':').map_or(host, |(host, _)| host);
+    host.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let remote = Remote::parse("acme/api", DEFAULT_HOST)?;
+        assert_eq!(
+            remote,
+            Remote {
+                url: "https://github.com/acme/api.git".to_string(),
+                host: "github.com".to_string(),
+                org: "acme".to_string(),
+                repo: "api".to_string(),
+            }
+        );
+
+        let remote = Remote::parse("git@GitLab.com:group/sub/web.git", DEFAULT_HOST)?;
+        assert_eq!(remote.url, "git@GitLab.com:group/sub/web.git");
+        assert_eq!(remote.host, "gitlab.com");
+        assert_eq!(remote.org, "group/sub");
+        assert_eq!(remote.repo, "web");
+
+        let remote = Remote::parse("ssh://git@example.com:2222/acme/cli/", DEFAULT_HOST)?;
+        assert_eq!(remote.host, "example.com");
+        assert_eq!((remote.org.as_str(), remote.repo.as_str()), ("acme", "cli"));
+
+        assert!(Remote::parse("api", DEFAULT_HOST).is_err());
+        assert!(Remote::parse("https://github.com/acme", DEFAULT_HOST).is_err());
+        assert!(Remote::parse("https://github.com/../etc/api", DEFAULT_HOST).is_err());
+        assert!(Remote::parse("file:///tmp/acme/api", DEFAULT_HOST).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_query() -> Result<()> {
+        let remote = Remote::parse("git@github.com:Kubernetes/autoscaler.git", DEFAULT_HOST)?;
+        for query in [
+            "github.com/kubernetes/autoscaler",
+            "https://github.com/kubernetes/autoscaler",
+            "git@github.com:kubernetes/autoscaler",
+        ] {
+            let parsed = Remote::from_query(query).expect(query);
+            assert!(parsed.same_repository(&remote), "{}", query);
+        }
+
+        assert!(!Remote::from_query("gitlab.com/kubernetes/autoscaler")
+            .expect("valid query")
+            .same_repository(&remote));
+        assert_eq!(Remote::from_query("autoscaler"), None);
+        assert_eq!(Remote::from_query("kubernetes/autoscaler"), None);
+        assert_eq!(Remote::from_query("github.com/kubernetes"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_path() -> Result<()> {
+        let remote = Remote::parse("https://github.com/acme/api", DEFAULT_HOST)?;
+        assert_eq!(
+            remote.path(DEFAULT_TEMPLATE)?,
+            PathBuf::from("github.com/acme/api")
+        );
+        assert_eq!(remote.path("{org}-{repo}")?, PathBuf::from("acme-api"));
+        assert!(remote.path("{owner}/{repo}").is_err());
+        assert!(remote.path("{repo").is_err());
+        Ok(())
+    }
+}
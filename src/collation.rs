@@ -0,0 +1,143 @@
+use std::cmp::Ordering;
+
+/// How folder names are put in alphabetical order.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum Collation {
+    /// By their bytes, so "Zed" goes before "api" and "project-10"
+    /// before "project-2".
+    #[default]
+    Lexical,
+    /// Like lexical, but numbers are compared by their value, so
+    /// "project-2" goes before "project-10".
+    Natural,
+    /// Like natural, ignoring case and the accents of Latin letters, so
+    /// "Émile" goes between "api" and "zed". This is an approximation of
+    /// the dictionary order of most European languages, not a full
+    /// locale-aware collation.
+    Locale,
+}
+
+impl Collation {
+    /// Compares two folder names. Names that only differ in what the
+    /// collation ignores are ordered lexically, so the order is stable.
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        let ordering = match self {
+            Collation::Lexical => Ordering::Equal,
+            Collation::Natural => natural(a.chars(), b.chars()),
+            Collation::Locale => natural(folded(a), folded(b)),
+        };
+        ordering.then_with(|| a.cmp(b))
+    }
+}
+
+// Compares two names one character at a time, except for runs of digits,
+// which are compared by the number they make.
+fn natural(a: impl Iterator<Item = char>, b: impl Iterator<Item = char>) -> Ordering {
+    let (mut a, mut b) = (a.peekable(), b.peekable());
+    loop {
+        match (a.peek(), b.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = number(&mut a);
+                let y = number(&mut b);
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.cmp(y);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+// Takes a run of digits, without its leading zeros, so numbers with more
+// digits are bigger.
+fn number(chars: &mut std::iter::Peekable<impl Iterator<Item = char>>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        if !(digits.is_empty() && c == '0') {
+            digits.push(c);
+        }
+    }
+    digits
+}
+
+// Lowercases a name and removes the accents of Latin letters.
+fn folded(name: &str) -> impl Iterator<Item = char> + '_ {
+    name.chars().flat_map(char::to_lowercase).map(|c| match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        c => c,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(collation: Collation, names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        names.sort_by(|a, b| collation.compare(a, b));
+        names
+    }
+
+    #[test]
+    fn test_compare() {
+        let names = [
+            "project-10",
+            "Zed",
+            "project-2",
+            "Émile",
+            "api",
+            "project-02",
+        ];
+        assert_eq!(
+            sorted(Collation::Lexical, &names),
+            [
+                "Zed",
+                "api",
+                "project-02",
+                "project-10",
+                "project-2",
+                "Émile"
+            ]
+        );
+        assert_eq!(
+            sorted(Collation::Natural, &names),
+            [
+                "Zed",
+                "api",
+                "project-02",
+                "project-2",
+                "project-10",
+                "Émile"
+            ]
+        );
+        assert_eq!(
+            sorted(Collation::Locale, &names),
+            [
+                "api",
+                "Émile",
+                "project-02",
+                "project-2",
+                "project-10",
+                "Zed"
+            ]
+        );
+    }
+}
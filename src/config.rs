@@ -0,0 +1,795 @@
+use anyhow::{bail, Context, Result};
+use find_project::frecency::{RankBy, Ranking};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+/// Files or folders that make a folder a project root when they're found
+/// inside it, unless configured otherwise.
+pub const DEFAULT_MARKERS: &[&str] = VCS_MARKERS;
+
+/// Files or folders that make a folder the root of a repository under
+/// version control.
+pub const VCS_MARKERS: &[&str] = &[".git", ".hg", ".svn"];
+
+/// Settings read from the configuration file. Everything is optional:
+/// a missing file behaves exactly like an empty one.
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Folders to search, in order. When empty, the root comes from the
+    /// $FP_FOLDER or $GOPATH environment variables.
+    pub roots: Vec<Root>,
+    /// Where the roots to search come from.
+    pub source: RootSource,
+    pub include_vendor: bool,
+    pub include_hidden: bool,
+    pub sort_alphabetically: bool,
+    /// Folder names that are never descended into.
+    pub prune: Vec<String>,
+    /// Patterns of folders that are never descended into, see
+    /// [`find_project::glob::matches_path`].
+    pub exclude: Vec<String>,
+    /// Flags added in front of the command-line arguments of every search.
+    pub default_flags: Vec<String>,
+    /// Files or folders that make a folder a project root, such as ".git"
+    /// or "go.mod". Defaults to [`DEFAULT_MARKERS`].
+    pub markers: Option<Vec<String>>,
+    /// Never search inside project roots.
+    pub stop_at_projects: bool,
+    /// Search and print the roots as written instead of resolving their
+    /// symlinks.
+    pub no_follow_root: bool,
+    /// On macOS, also search ~/Library, the trash and app bundles when
+    /// searching the home folder.
+    pub include_system: bool,
+    /// Short names for projects, mapped to a path (absolute, or relative
+    /// to the roots) or to another folder name to search for.
+    pub aliases: BTreeMap<String, String>,
+    /// Remember searches that found nothing for this many seconds, and
+    /// fail right away when they're repeated. Zero, the default, always
+    /// searches. A search is searched again early when a folder is added
+    /// right inside a root, or after `clone` or a recorded visit, but not
+    /// when a folder is added deeper inside a root by other means.
+    pub not_found_cache_seconds: u64,
+    pub ranking: RankingConfig,
+    pub history: HistoryConfig,
+    pub clone: CloneConfig,
+    /// Named sets of settings that replace the ones above when selected
+    /// with `--profile` or `$FP_PROFILE`.
+    pub profile: BTreeMap<String, Profile>,
+}
+
+/// Where the roots to search come from.
+#[derive(clap::ValueEnum, Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RootSource {
+    /// The roots in the configuration file, then $FP_FOLDER, then $GOPATH/src
+    #[default]
+    Auto,
+    /// Only $FP_FOLDER
+    Env,
+    /// Only $GOPATH/src
+    Gopath,
+    /// Only the roots in the configuration file
+    Config,
+}
+
+/// Settings that override the global ones when the profile is selected.
+/// Aliases are added to the global ones, everything else replaces them.
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub roots: Option<Vec<Root>>,
+    pub include_vendor: Option<bool>,
+    pub include_hidden: Option<bool>,
+    pub sort_alphabetically: Option<bool>,
+    pub prune: Option<Vec<String>>,
+    pub default_flags: Option<Vec<String>>,
+    pub aliases: BTreeMap<String, String>,
+}
+
+/// A folder to search, with settings that override the global ones for
+/// this folder only. In the configuration file, a root is either a plain
+/// path or a table with a `path` key.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Root {
+    /// Name used to select this root with `--root`.
+    pub name: Option<String>,
+    pub path: PathBuf,
+    pub max_depth: Option<usize>,
+    pub include_vendor: Option<bool>,
+    pub include_hidden: Option<bool>,
+    /// Folder names never descended into, on top of the global list.
+    pub prune: Vec<String>,
+    /// Folder patterns never descended into, on top of the global list.
+    pub exclude: Vec<String>,
+    /// Whether this is a $GOPATH-style folder whose projects live in `src`.
+    pub gopath: bool,
+}
+
+impl Root {
+    /// Returns the folder to traverse for this root.
+    pub fn search_path(&self) -> PathBuf {
+        if self.gopath {
+            self.path.join("src")
+        } else {
+            self.path.clone()
+        }
+    }
+}
+
+// Roots are deserialized by hand rather than with an untagged enum, so
+// mistakes inside a root table are reported precisely instead of as "data
+// did not match any variant".
+impl<'de> Deserialize<'de> for Root {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RootVisitor;
+
+        impl<'de> Visitor<'de> for RootVisitor {
+            type Value = Root;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a path or a table with a \"path\" key")
+            }
+
+            fn visit_str<E: de::Error>(self, path: &str) -> Result<Root, E> {
+                Ok(Root {
+                    path: PathBuf::from(path),
+                    ..Default::default()
+                })
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Root, A::Error> {
+                RootTable::deserialize(de::value::MapAccessDeserializer::new(map)).map(Root::from)
+            }
+        }
+
+        deserializer.deserialize_any(RootVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RootTable {
+    name: Option<String>,
+    path: PathBuf,
+    max_depth: Option<usize>,
+    include_vendor: Option<bool>,
+    include_hidden: Option<bool>,
+    #[serde(default)]
+    prune: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    gopath: bool,
+}
+
+impl From<RootTable> for Root {
+    fn from(table: RootTable) -> Self {
+        Root {
+            name: table.name,
+            path: table.path,
+            max_depth: table.max_depth,
+            include_vendor: table.include_vendor,
+            include_hidden: table.include_hidden,
+            prune: table.prune,
+            exclude: table.exclude,
+            gopath: table.gopath,
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RankingConfig {
+    pub rank_by: Option<RankBy>,
+    pub half_life_days: Option<f64>,
+    pub visit_weight: Option<f64>,
+    pub recency_boost: Option<f64>,
+}
+
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub retention_days: Option<u64>,
+}
+
+/// Settings for `find-project clone`.
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CloneConfig {
+    /// Where to clone repositories inside the root, see
+    /// [`find_project::clone::DEFAULT_TEMPLATE`].
+    pub template: Option<String>,
+    /// Host for `org/repo` shorthands, see
+    /// [`find_project::clone::DEFAULT_HOST`].
+    pub host: Option<String>,
+    /// Name of the root to clone into, instead of the first one.
+    pub root: Option<String>,
+}
+
+impl Config {
+    /// Loads the configuration file from its default location, see
+    /// [`config_path`].
+    pub fn load() -> Result<Self> {
+        match config_path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents)
+                .with_context(|| format!("Invalid configuration file {:?}", path)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err).with_context(|| format!("Unable to read {:?}", path)),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut config: Config = toml::from_str(contents)?;
+        config.expand_paths()?;
+        Ok(config)
+    }
+
+    // Expands "~" and environment variables in root paths and aliases, so
+    // the same file works across machines.
+    fn expand_paths(&mut self) -> Result<()> {
+        let profiles = self.profile.values_mut();
+        let roots = self
+            .roots
+            .iter_mut()
+            .chain(profiles.flat_map(|p| p.roots.iter_mut().flatten()));
+        for root in roots {
+            root.path = PathBuf::from(expand(&root.path.to_string_lossy())?);
+        }
+
+        let aliases = self.aliases.values_mut().chain(
+            self.profile
+                .values_mut()
+                .flat_map(|p| p.aliases.values_mut()),
+        );
+        for target in aliases {
+            *target = expand(target)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the problems that can't be caught while parsing: roots
+    /// that don't exist, duplicated root names and invalid ranking values.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        let mut check_roots = |roots: &[Root], scope: &str| {
+            let mut names = BTreeSet::new();
+            for root in roots {
+                let path = root.search_path();
+                if !path.is_dir() {
+                    problems.push(format!("{}: root {:?} doesn't exist", scope, path));
+                }
+                if let Some(name) = &root.name {
+                    if !names.insert(name) {
+                        problems.push(format!("{}: root name \"{}\" is used twice", scope, name));
+                    }
+                }
+            }
+        };
+
+        check_roots(&self.roots, "roots");
+        for (name, profile) in &self.profile {
+            if let Some(roots) = &profile.roots {
+                check_roots(roots, &format!("profile.{}.roots", name));
+            }
+        }
+
+        if self.ranking.half_life_days.is_some_and(|days| days <= 0.0) {
+            problems.push("ranking.half_life_days: must be greater than zero".to_string());
+        }
+        if self.ranking.visit_weight.is_some_and(|weight| weight < 0.0) {
+            problems.push("ranking.visit_weight: must not be negative".to_string());
+        }
+        if self
+            .markers
+            .as_ref()
+            .is_some_and(|markers| markers.is_empty())
+        {
+            problems.push("markers: at least one marker is needed".to_string());
+        }
+
+        problems
+    }
+
+    /// Returns the configured project markers, or the default ones.
+    pub fn markers(&self) -> Vec<String> {
+        match &self.markers {
+            Some(markers) => markers.clone(),
+            None => DEFAULT_MARKERS.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    /// Replaces the global settings with the ones from a profile.
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let Some(profile) = self.profile.get(name).cloned() else {
+            bail!(
+                "There's no profile named \"{}\" in the configuration file",
+                name
+            );
+        };
+
+        if let Some(roots) = profile.roots {
+            self.roots = roots;
+        }
+        if let Some(include_vendor) = profile.include_vendor {
+            self.include_vendor = include_vendor;
+        }
+        if let Some(include_hidden) = profile.include_hidden {
+            self.include_hidden = include_hidden;
+        }
+        if let Some(sort_alphabetically) = profile.sort_alphabetically {
+            self.sort_alphabetically = sort_alphabetically;
+        }
+        if let Some(prune) = profile.prune {
+            self.prune = prune;
+        }
+        if let Some(default_flags) = profile.default_flags {
+            self.default_flags = default_flags;
+        }
+        self.aliases.extend(profile.aliases);
+        Ok(())
+    }
+
+    /// Returns the ranking parameters, using the defaults for anything
+    /// not configured.
+    pub fn ranking(&self) -> Ranking {
+        let defaults = Ranking::default();
+        Ranking {
+            rank_by: self.ranking.rank_by.unwrap_or(defaults.rank_by),
+            half_life: self
+                .ranking
+                .half_life_days
+                .map(|days| (days * 24.0 * 60.0 * 60.0) as u64)
+                .unwrap_or(defaults.half_life),
+            visit_weight: self.ranking.visit_weight.unwrap_or(defaults.visit_weight),
+            recency_boost: self.ranking.recency_boost.unwrap_or(defaults.recency_boost),
+        }
+    }
+}
+
+/// Expands a leading `~` to the home directory, and `$NAME` or `${NAME}`
+/// to the value of the environment variable. Unset variables are an error
+/// rather than silently becoming empty.
+pub fn expand(value: &str) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = value;
+
+    if rest == "~" || rest.starts_with("~/") || (cfg!(windows) && rest.starts_with("~\\")) {
+        let home = env::var("HOME")
+            .or_else(|_| env::var("USERPROFILE"))
+            .with_context(|| format!("Unable to expand {:?}: $HOME is not set", value))?;
+        expanded.push_str(&home);
+        rest = &rest[1..];
+    }
+
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        let (name, remaining) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .with_context(|| format!("Missing closing brace in {:?}", value))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+
+        // A "$" not followed by a variable name is kept as is.
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+
+        let var = env::var(name)
+            .with_context(|| format!("Unable to expand {:?}: ${} is not set", value, name))?;
+        expanded.push_str(&var);
+        rest = remaining;
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Reads the raw contents of the configuration file, treating a missing
+/// file as empty.
+pub fn read_raw(path: &Path) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(err).with_context(|| format!("Unable to read {:?}", path)),
+    }
+}
+
+/// Returns the value of a dotted key such as `ranking.rank_by`, or
+/// `roots.0.path` to index into arrays.
+pub fn get(contents: &str, key: &str) -> Result<Option<String>> {
+    let doc = parse_document(contents)?;
+    let mut item = doc.as_item();
+    for part in key.split('.') {
+        let child = match part.parse::<usize>() {
+            Ok(index) => item.get(index),
+            Err(_) => item.get(part),
+        };
+        match child {
+            Some(child) if !child.is_none() => item = child,
+            _ => return Ok(None),
+        }
+    }
+    Ok(Some(format_item(item)))
+}
+
+/// Sets a dotted key to a value and returns the updated file, keeping
+/// comments and formatting intact. The value is read as TOML when
+/// possible (`true`, `5`, `["a", "b"]`) and as a string otherwise. The
+/// change is refused if it makes the configuration invalid.
+pub fn set(contents: &str, key: &str, value: &str) -> Result<String> {
+    let mut doc = parse_document(contents)?;
+
+    let mut parsed = value
+        .parse::<Value>()
+        .unwrap_or_else(|_| Value::from(value));
+    parsed.decor_mut().clear();
+
+    let mut item = doc.as_item_mut();
+    for part in key.split('.') {
+        if item.is_none() {
+            // Tables created here go after every existing one, so a new
+            // section is appended to the file instead of pushed above it.
+            let mut table = Table::new();
+            table.set_implicit(true);
+            table.set_position(usize::MAX);
+            *item = Item::Table(table);
+        } else if !item.is_table_like() {
+            bail!("Unable to set \"{}\": \"{}\" is not a table", key, part);
+        }
+        item = &mut item[part];
+    }
+    *item = Item::Value(parsed);
+
+    let updated = doc.to_string();
+    Config::parse(&updated).with_context(|| format!("Refusing to set \"{}\" to {}", key, value))?;
+    Ok(updated)
+}
+
+/// Returns every value in the configuration file with its dotted key.
+pub fn list(contents: &str) -> Result<Vec<(String, String)>> {
+    let doc = parse_document(contents)?;
+    let mut entries = Vec::new();
+    flatten(doc.as_item(), "", &mut entries);
+    Ok(entries)
+}
+
+fn flatten(item: &Item, prefix: &str, entries: &mut Vec<(String, String)>) {
+    let key = |name: &str| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        }
+    };
+
+    if let Some(table) = item.as_table_like() {
+        for (name, child) in table.iter() {
+            flatten(child, &key(name), entries);
+        }
+    } else if let Some(tables) = item.as_array_of_tables() {
+        for (index, table) in tables.iter().enumerate() {
+            for (name, child) in table.iter() {
+                flatten(
+                    child,
+                    &format!("{}.{}", key(&index.to_string()), name),
+                    entries,
+                );
+            }
+        }
+    } else if !item.is_none() {
+        entries.push((prefix.to_string(), format_item(item)));
+    }
+}
+
+fn format_item(item: &Item) -> String {
+    match item.as_str() {
+        Some(s) => s.to_string(),
+        None => item.to_string().trim().to_string(),
+    }
+}
+
+fn parse_document(contents: &str) -> Result<DocumentMut> {
+    contents
+        .parse::<DocumentMut>()
+        .context("Unable to parse the configuration file")
+}
+
+/// Returns the location of the configuration file: `$FP_CONFIG` if set,
+/// otherwise `config.toml` inside `$XDG_CONFIG_HOME/find-project` (or
+/// `~/.config/find-project`, or `%APPDATA%\find-project` on Windows).
+pub fn config_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("FP_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    let dir = if let Ok(dir) = env::var("XDG_CONFIG_HOME") {
+        PathBuf::from(dir)
+    } else if cfg!(windows) {
+        PathBuf::from(env::var("APPDATA").ok()?)
+    } else {
+        PathBuf::from(env::var("HOME").ok()?).join(".config")
+    };
+    Some(dir.join("find-project").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_config() -> Result<()> {
+        let config = Config::parse(
+            r#"
+            roots = ["/home/user/Projects", "/home/user/go/src"]
+            include_hidden = true
+            prune = ["node_modules", "target"]
+            default_flags = ["--max-depth", "5"]
+            markers = [".git", "go.mod"]
+
+            [aliases]
+            k8s = "github.com/kubernetes/kubernetes"
+
+            [ranking]
+            rank_by = "recency"
+            half_life_days = 1.5
+
+            [history]
+            retention_days = 30
+
+            [clone]
+            template = "{org}/{repo}"
+            "#,
+        )?;
+
+        let roots: Vec<_> = config.roots.iter().map(Root::search_path).collect();
+        assert_eq!(
+            roots,
+            vec![
+                PathBuf::from("/home/user/Projects"),
+                PathBuf::from("/home/user/go/src")
+            ]
+        );
+        assert!(config.include_hidden);
+        assert!(!config.include_vendor);
+        assert_eq!(config.prune, vec!["node_modules", "target"]);
+        assert_eq!(config.default_flags, vec!["--max-depth", "5"]);
+        assert_eq!(config.markers(), vec![".git", "go.mod"]);
+        assert_eq!(config.history.retention_days, Some(30));
+        assert_eq!(config.clone.template.as_deref(), Some("{org}/{repo}"));
+        assert_eq!(config.clone.host, None);
+        assert_eq!(
+            config.aliases.get("k8s").map(String::as_str),
+            Some("github.com/kubernetes/kubernetes")
+        );
+
+        let ranking = config.ranking();
+        assert_eq!(ranking.rank_by, RankBy::Recency);
+        assert_eq!(ranking.half_life, 36 * 60 * 60);
+        assert_eq!(ranking.visit_weight, Ranking::default().visit_weight);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_named_roots() -> Result<()> {
+        let config = Config::parse(
+            r#"
+            [[roots]]
+            name = "work"
+            path = "/home/user/work"
+            max_depth = 3
+            prune = ["archive"]
+            exclude = ["legacy-*", "clients/*/old"]
+
+            [[roots]]
+            name = "go"
+            path = "/home/user/go"
+            include_hidden = true
+            gopath = true
+            "#,
+        )?;
+
+        assert_eq!(
+            config.roots,
+            vec![
+                Root {
+                    name: Some("work".to_string()),
+                    path: PathBuf::from("/home/user/work"),
+                    max_depth: Some(3),
+                    prune: vec!["archive".to_string()],
+                    exclude: vec!["legacy-*".to_string(), "clients/*/old".to_string()],
+                    ..Default::default()
+                },
+                Root {
+                    name: Some("go".to_string()),
+                    path: PathBuf::from("/home/user/go"),
+                    include_hidden: Some(true),
+                    gopath: true,
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(
+            config.roots[1].search_path(),
+            PathBuf::from("/home/user/go/src")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_profile() -> Result<()> {
+        let mut config = Config::parse(
+            r#"
+            roots = ["/home/user/Projects"]
+            prune = ["node_modules"]
+
+            [aliases]
+            notes = "/home/user/notes"
+
+            [profile.client]
+            roots = ["/mnt/client/src"]
+            include_hidden = true
+
+            [profile.client.aliases]
+            api = "client/api"
+            "#,
+        )?;
+
+        assert!(config.apply_profile("missing").is_err());
+        config.apply_profile("client")?;
+
+        let roots: Vec<_> = config.roots.iter().map(|r| r.path.clone()).collect();
+        assert_eq!(roots, vec![PathBuf::from("/mnt/client/src")]);
+        assert!(config.include_hidden);
+        assert_eq!(config.prune, vec!["node_modules"]);
+        assert_eq!(config.aliases.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_set_list() -> Result<()> {
+        let contents = "# my settings\nroots = [\"/src\"]\n\n[ranking]\nrank_by = \"recency\"\n";
+
+        assert_eq!(get(contents, "roots.0")?, Some("/src".to_string()));
+        assert_eq!(
+            get(contents, "ranking.rank_by")?,
+            Some("recency".to_string())
+        );
+        assert_eq!(get(contents, "ranking.visit_weight")?, None);
+
+        let updated = set(contents, "include_hidden", "true")?;
+        let updated = set(&updated, "ranking.half_life_days", "3")?;
+        let updated = set(&updated, "aliases.k8s", "github.com/kubernetes/kubernetes")?;
+        assert!(updated.starts_with("# my settings\n"));
+        assert_eq!(get(&updated, "include_hidden")?, Some("true".to_string()));
+
+        let config = Config::parse(&updated)?;
+        assert!(config.include_hidden);
+        assert_eq!(config.ranking.half_life_days, Some(3.0));
+
+        assert!(set(&updated, "include_hidden", "yes").is_err());
+        assert!(set(&updated, "include_hidden.nested", "true").is_err());
+
+        let keys: Vec<_> = list(&updated)?.into_iter().map(|(k, _)| k).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "roots",
+                "include_hidden",
+                "ranking.rank_by",
+                "ranking.half_life_days",
+                "aliases.k8s"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand() -> Result<()> {
+        env::set_var("FP_TEST_EXPAND_BASE", "/mnt/code");
+
+        assert_eq!(expand("$FP_TEST_EXPAND_BASE/oss")?, "/mnt/code/oss");
+        assert_eq!(expand("${FP_TEST_EXPAND_BASE}2/oss")?, "/mnt/code2/oss");
+        assert_eq!(expand("/cost/$5")?, "/cost/$5");
+        assert_eq!(expand("/plain/path")?, "/plain/path");
+        assert!(expand("$FP_TEST_EXPAND_UNSET/oss").is_err());
+        assert!(expand("${FP_TEST_EXPAND_BASE").is_err());
+
+        let home = env::var("HOME")?;
+        assert_eq!(expand("~/Projects")?, format!("{}/Projects", home));
+        assert_eq!(expand("~user/Projects")?, "~user/Projects");
+
+        let config = Config::parse(
+            r#"
+            roots = ["${FP_TEST_EXPAND_BASE}/oss"]
+
+            [profile.work]
+            roots = [{ name = "work", path = "$FP_TEST_EXPAND_BASE/work" }]
+            "#,
+        )?;
+        assert_eq!(config.roots[0].path, PathBuf::from("/mnt/code/oss"));
+        let work = config.profile["work"].roots.as_ref().unwrap();
+        assert_eq!(work[0].path, PathBuf::from("/mnt/code/work"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_config_file() -> Result<()> {
+        let dir = tempdir()?;
+        let config = Config::load_from(&dir.path().join("config.toml"))?;
+        assert_eq!(config, Config::default());
+        assert_eq!(config.markers(), vec![".git", ".hg", ".svn"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config_file() {
+        let err = Config::parse("include_hidden = \"yes\"").unwrap_err();
+        assert!(format!("{:#}", err).contains("line 1"));
+
+        let err = Config::parse("roots = []\ninclude_hiden = true").unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("line 2"));
+        assert!(message.contains("unknown field `include_hiden`"));
+
+        let err = Config::parse("[[roots]]\npath = \"/src\"\nmax_dept = 3").unwrap_err();
+        assert!(format!("{:#}", err).contains("unknown field `max_dept`"));
+
+        assert!(Config::parse("[ranking]\nrank_by = \"alphabetical\"").is_err());
+    }
+
+    #[test]
+    fn test_problems() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().to_string_lossy();
+
+        let config = Config::parse(&format!(
+            r#"
+            roots = [
+                {{ name = "a", path = "{path}" }},
+                {{ name = "a", path = "{path}/missing" }},
+            ]
+
+            [ranking]
+            half_life_days = 0
+            "#
+        ))?;
+
+        assert_eq!(config.problems().len(), 3);
+        assert!(Config::default().problems().is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+use crate::config::{self, Config, RootSource};
+use anyhow::{bail, Result};
+use find_project::frecency::Database;
+use find_project::store;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Environment variables that change what find-project does.
+const VARIABLES: &[&str] = &[
+    "FP_FOLDER",
+    "GOPATH",
+    "FP_CONFIG",
+    "FP_PROFILE",
+    "FP_DATA_DIR",
+    "FP_DEBUG",
+];
+
+/// Shell startup files, relative to the home folder, where the function
+/// wrapping find-project is usually defined.
+const SHELL_FILES: &[&str] = &[
+    ".bashrc",
+    ".bash_profile",
+    ".profile",
+    ".zshrc",
+    ".config/fish/config.fish",
+    ".config/fish/functions/fp.fish",
+];
+
+/// Checks everything that commonly makes searches find nothing, printing
+/// what was found and how to fix every problem. Fails when there's any.
+pub fn run(source: Option<RootSource>) -> Result<()> {
+    let mut report = Report::default();
+
+    println!("Environment");
+    for name in VARIABLES {
+        match env::var(name) {
+            Ok(value) => report.ok(&format!("${}", name), &value),
+            Err(_) => report.ok(&format!("${}", name), "not set"),
+        }
+    }
+
+    println!("Configuration");
+    let mut config = match config::config_path() {
+        None => {
+            report.ok("file", "none, neither $HOME nor $XDG_CONFIG_HOME are set");
+            Config::default()
+        }
+        Some(path) => match Config::load_from(&path) {
+            Ok(config) if path.exists() => {
+                report.ok("file", &path.display().to_string());
+                config
+            }
+            Ok(config) => {
+                report.ok("file", &format!("{} (not created)", path.display()));
+                config
+            }
+            Err(err) => {
+                report.problem(
+                    "file",
+                    &format!("{:#}", err),
+                    "run \"find-project config check\" to see every problem in it",
+                );
+                Config::default()
+            }
+        },
+    };
+    if let Some(source) = source {
+        config.source = source;
+    }
+
+    println!("Roots");
+    match crate::root_source(&config) {
+        Ok(source) => {
+            report.ok("source", &crate::describe_source(source, &config));
+            for path in root_paths(source, &config) {
+                match fs::read_dir(&path) {
+                    Ok(entries) => {
+                        let folders = entries
+                            .flatten()
+                            .filter(|entry| entry.path().is_dir())
+                            .count();
+                        let detail = format!("{} folders inside", folders);
+                        report.ok(&path.display().to_string(), &detail);
+                    }
+                    Err(err) => report.problem(
+                        &path.display().to_string(),
+                        &format!("can't be read: {}", err),
+                        "create the folder, or fix the path in the variable or configuration file it comes from",
+                    ),
+                }
+            }
+        }
+        Err(err) => report.problem(
+            "source",
+            &err.to_string(),
+            "set $FP_FOLDER to the folder with your projects, or add \"roots\" to the configuration file",
+        ),
+    }
+
+    println!("Data");
+    match store::data_dir() {
+        Ok(dir) if dir.is_dir() => report.ok("folder", &dir.display().to_string()),
+        Ok(dir) => report.ok(
+            "folder",
+            &format!("{} (created the first time it's written to)", dir.display()),
+        ),
+        Err(err) => report.problem(
+            "folder",
+            &format!("{:#}", err),
+            "set $FP_DATA_DIR to a folder find-project can write to",
+        ),
+    }
+    match Database::load() {
+        Ok(db) => {
+            let missing = db
+                .entries()
+                .iter()
+                .filter(|entry| !entry.path.is_dir())
+                .count();
+            let detail = format!(
+                "{} visited projects, {} no longer exist",
+                db.entries().len(),
+                missing
+            );
+            report.ok("visits", &detail);
+        }
+        Err(err) => report.problem(
+            "visits",
+            &format!("{:#}", err),
+            "move the file away so it's recreated, previous visits will be forgotten",
+        ),
+    }
+
+    println!("Shell");
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from);
+    match home.as_deref().and_then(shell_integration) {
+        Some(file) => report.ok("function", &format!("defined in {}", file.display())),
+        None => report.problem(
+            "function",
+            "find-project isn't mentioned in any shell startup file",
+            "add a function like fp() { cd \"$(find-project \"$@\")\"; } to your shell startup file",
+        ),
+    }
+
+    match report.problems {
+        0 => {
+            println!("\nEverything looks fine");
+            Ok(())
+        }
+        1 => bail!("Found 1 problem"),
+        problems => bail!("Found {} problems", problems),
+    }
+}
+
+#[derive(Default)]
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn ok(&self, label: &str, detail: &str) {
+        println!("  {:<12}  {}", label, detail);
+    }
+
+    fn problem(&mut self, label: &str, detail: &str, fix: &str) {
+        self.problems += 1;
+        println!("  {:<12}  problem: {}", label, detail);
+        println!("  {:<12}  fix: {}", "", fix);
+    }
+}
+
+// Returns the folders searched for the source, before they're resolved,
+// so the ones that don't exist can be reported.
+fn root_paths(source: RootSource, config: &Config) -> Vec<PathBuf> {
+    match source {
+        RootSource::Config => config.roots.iter().map(|root| root.search_path()).collect(),
+        RootSource::Env => env::var_os("FP_FOLDER")
+            .map(|location| env::split_paths(&location).collect())
+            .unwrap_or_default(),
+        RootSource::Gopath | RootSource::Auto => env::var_os("GOPATH")
+            .map(|location| vec![Path::new(&location).join("src")])
+            .unwrap_or_default(),
+    }
+}
+
+// Returns the first shell startup file in the home folder that mentions
+// find-project, which is where the function wrapping it lives.
+fn shell_integration(home: &Path) -> Option<PathBuf> {
+    SHELL_FILES.iter().map(|file| home.join(file)).find(|file| {
+        fs::read_to_string(file).is_ok_and(|contents| contents.contains("find-project"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_shell_integration() -> Result<()> {
+        let home = tempdir()?;
+        assert_eq!(shell_integration(home.path()), None);
+
+        fs::write(home.path().join(".bashrc"), "export EDITOR=vim\n")?;
+        assert_eq!(shell_integration(home.path()), None);
+
+        let fish = home.path().join(".config").join("fish");
+        fs::create_dir_all(&fish)?;
+        fs::write(
+            fish.join("config.fish"),
+            "function fp\n  cd (find-project $argv)\nend\n",
+        )?;
+        assert_eq!(
+            shell_integration(home.path()),
+            Some(home.path().join(".config/fish/config.fish"))
+        );
+
+        Ok(())
+    }
+}
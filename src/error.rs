@@ -0,0 +1,98 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Errors returned by the library, so callers can tell a missing root
+/// apart from a folder that couldn't be read along the way, or a missing
+/// program apart from one that failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FindError {
+    /// The options don't say where to search.
+    NoRootConfigured,
+    /// The root doesn't exist or can't be read.
+    RootNotAccessible { path: PathBuf, source: io::Error },
+    /// A folder inside the root couldn't be read.
+    Io { path: PathBuf, source: io::Error },
+    /// The search was stopped with [`FinderOptions::cancel`].
+    ///
+    /// [`FinderOptions::cancel`]: crate::FinderOptions::cancel
+    Cancelled,
+    /// The search in a root stopped halfway because of a bug, instead of
+    /// taking the program down with it.
+    Panicked { path: PathBuf },
+    /// There's nowhere to keep state between runs because the variable
+    /// locating the home or data folder isn't set.
+    NoDataDir { variable: &'static str },
+    /// A file couldn't be read.
+    Read { path: PathBuf, source: io::Error },
+    /// A file couldn't be written or replaced.
+    Write { path: PathBuf, source: io::Error },
+    /// A program like `git` couldn't be started, usually because it isn't
+    /// installed.
+    Command { program: String, source: io::Error },
+    /// A command ran inside a folder but failed, with what it printed.
+    CommandFailed {
+        command: String,
+        dir: PathBuf,
+        message: String,
+    },
+    /// A command was killed after running longer than allowed.
+    Timeout { command: String, seconds: u64 },
+    /// Something read or given couldn't be understood, like a repository
+    /// URL, a clone template or a damaged database.
+    Invalid(String),
+}
+
+/// Result of the search functions.
+pub type Result<T, E = FindError> = std::result::Result<T, E>;
+
+impl fmt::Display for FindError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FindError::NoRootConfigured => write!(f, "No folder to search in was configured"),
+            FindError::RootNotAccessible { path, .. } => {
+                write!(f, "Unable to read the folder to search in {:?}", path)
+            }
+            FindError::Io { path, .. } => write!(f, "Unable to read directory {:?}", path),
+            FindError::Cancelled => write!(f, "The search was cancelled"),
+            FindError::Panicked { path } => {
+                write!(f, "The search in {:?} stopped unexpectedly", path)
+            }
+            FindError::NoDataDir { variable } => {
+                write!(
+                    f,
+                    "Unable to find a data directory: ${} is not set",
+                    variable
+                )
+            }
+            FindError::Read { path, .. } => write!(f, "Unable to read {:?}", path),
+            FindError::Write { path, .. } => write!(f, "Unable to write {:?}", path),
+            FindError::Command { program, .. } => {
+                write!(f, "Unable to run {}, is it installed?", program)
+            }
+            FindError::CommandFailed {
+                command,
+                dir,
+                message,
+            } => write!(f, "Unable to run \"{}\" in {:?}: {}", command, dir, message),
+            FindError::Timeout { command, seconds } => {
+                write!(f, "{} timed out after {} seconds", command, seconds)
+            }
+            FindError::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for FindError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FindError::RootNotAccessible { source, .. }
+            | FindError::Io { source, .. }
+            | FindError::Read { source, .. }
+            | FindError::Write { source, .. }
+            | FindError::Command { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crate::opts;
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+
+/// Runs a program inside a folder, printing everything it writes to
+/// stdout and stderr as it comes, with every line starting with the label.
+pub fn run_labeled(dir: &Path, label: &str, program: &str, args: &[String]) -> Result<ExitStatus> {
+    let mut child = Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Unable to run {:?} in {:?}", program, dir))?;
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    thread::scope(|scope| {
+        let errors = scope.spawn(|| prefix_lines(stderr, label, io::stderr()));
+        prefix_lines(stdout, label, io::stdout())?;
+        errors.join().expect("the stderr thread doesn't panic")
+    })
+    .with_context(|| format!("Unable to read the output of {:?}", program))?;
+
+    child
+        .wait()
+        .with_context(|| format!("Unable to run {:?} in {:?}", program, dir))
+}
+
+/// Returns the `fp-<name>` executable on $PATH that implements a
+/// command find-project doesn't have, like `fp-sync` for `fp run sync`.
+pub fn find_plugin(name: &str) -> Option<PathBuf> {
+    find_in(&env::var_os("PATH")?, name)
+}
+
+fn find_in(search_path: &OsStr, name: &str) -> Option<PathBuf> {
+    // Anything that could reach outside the folders in $PATH isn't a
+    // subcommand name.
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return None;
+    }
+    let file = format!("fp-{}{}", name, env::consts::EXE_SUFFIX);
+    env::split_paths(search_path)
+        .map(|dir| dir.join(&file))
+        .find(|path| is_executable(path))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Runs a plugin with the rest of the arguments and the variables given,
+/// exiting with its exit code. On Unix, the plugin replaces this process,
+/// so signals and the terminal go straight to it.
+pub fn run_plugin(plugin: &Path, args: &[OsString], vars: &[(&str, OsString)]) -> Result<()> {
+    let mut command = Command::new(plugin);
+    command
+        .args(args)
+        .envs(vars.iter().map(|(name, value)| (name, value)));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let err = command.exec();
+        Err(err).with_context(|| format!("Unable to run {:?}", plugin))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let status = command
+            .status()
+            .with_context(|| format!("Unable to run {:?}", plugin))?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
+
+/// Runs a command with the paths given on its stdin, one per line, and
+/// returns the score it prints for each of them, one per line in the same
+/// order, with higher being better.
+pub fn scores(command: &str, paths: &[&Path]) -> Result<Vec<f64>> {
+    let args = opts::split(command)?;
+    let (program, args) = args.split_first().context("The scorer command is empty")?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Unable to run the scorer {:?}", program))?;
+
+    // The paths are written while the scores are read, so a scorer that
+    // answers as it reads doesn't fill the pipe and wait forever.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input: Vec<u8> = paths
+        .iter()
+        .flat_map(|path| format!("{}\n", path.display()).into_bytes())
+        .collect();
+    let writer = thread::spawn(move || stdin.write_all(&input));
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Unable to run the scorer {:?}", program))?;
+    // A scorer may exit without reading everything, which isn't an error
+    // when it printed every score.
+    let _ = writer.join().expect("the stdin thread doesn't panic");
+    if !output.status.success() {
+        bail!("The scorer {:?} failed with {}", program, output.status);
+    }
+    parse_scores(&String::from_utf8_lossy(&output.stdout), paths.len())
+        .with_context(|| format!("Invalid output from the scorer {:?}", program))
+}
+
+// Reads one score per line, which there must be count of.
+fn parse_scores(output: &str, count: usize) -> Result<Vec<f64>> {
+    let scores = output
+        .lines()
+        .map(|line| {
+            line.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|score| !score.is_nan())
+                .with_context(|| format!("{:?} isn't a score", line))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if scores.len() != count {
+        bail!("Expected {} scores, got {}", count, scores.len());
+    }
+    Ok(scores)
+}
+
+// Copies every line from one stream to another with the label in front.
+// Each line is written at once, so lines from commands running at the
+// same time don't get mixed up.
+fn prefix_lines(from: impl Read, label: &str, mut to: impl Write) -> io::Result<()> {
+    for line in BufReader::new(from).split(b'\n') {
+        let mut labeled = format!("{}: ", label).into_bytes();
+        labeled.extend(line?);
+        labeled.push(b'\n');
+        to.write_all(&labeled)?;
+    }
+    to.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_in() -> Result<()> {
+        let first = tempdir()?;
+        let second = tempdir()?;
+        let search_path = env::join_paths([first.path(), second.path()])?;
+        assert_eq!(find_in(&search_path, "sync"), None);
+
+        let plugin = second
+            .path()
+            .join(format!("fp-sync{}", env::consts::EXE_SUFFIX));
+        fs::write(&plugin, "#!/bin/sh\n")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(find_in(&search_path, "sync"), None);
+            fs::set_permissions(&plugin, fs::Permissions::from_mode(0o755))?;
+        }
+        assert_eq!(find_in(&search_path, "sync"), Some(plugin));
+        assert_eq!(find_in(&search_path, "../fp-sync"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_scores() {
+        assert_eq!(
+            parse_scores("1\n-2.5\n 3 \n", 3).ok(),
+            Some(vec![1.0, -2.5, 3.0])
+        );
+        assert!(parse_scores("1\n2\n", 3).is_err());
+        assert!(parse_scores("1\nhigh\n", 2).is_err());
+        assert!(parse_scores("NaN\n", 1).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_scores() -> Result<()> {
+        let paths = [Path::new("/work/api"), Path::new("/home/me/api")];
+        assert_eq!(
+            scores("sh -c 'while read -r path; do echo ${#path}; done'", &paths)?,
+            [9.0, 12.0]
+        );
+        assert!(scores("sh -c 'exit 3'", &paths).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prefix_lines() -> io::Result<()> {
+        let mut output = Vec::new();
+        prefix_lines(&b"Already up to date.\nDone\n"[..], "acme/api", &mut output)?;
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "acme/api: Already up to date.\nacme/api: Done\n"
+        );
+        Ok(())
+    }
+}
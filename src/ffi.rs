@@ -0,0 +1,149 @@
+//! C bindings, so tools written in other languages can search without
+//! running the command. `include/find_project.h` declares them.
+
+use crate::{Finder, FinderOptions};
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+/// Search options. Passing NULL instead uses the defaults, which are all
+/// zeros.
+#[repr(C)]
+#[derive(Default)]
+pub struct FpOptions {
+    pub include_hidden: bool,
+    pub include_vendor: bool,
+    /// Only match git repositories.
+    pub projects_only: bool,
+    /// Don't search deeper than this many folders below the root, 0 means
+    /// no limit.
+    pub max_depth: usize,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Returns the path of the first folder named `query` inside `root`, or
+/// NULL when there's none or the search failed, in which case
+/// `fp_last_error` says why. The path must be released with `fp_free`.
+///
+/// # Safety
+///
+/// `root` and `query` must be valid NUL-terminated strings, and `options`
+/// either NULL or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn fp_find(
+    root: *const c_char,
+    query: *const c_char,
+    options: *const FpOptions,
+) -> *mut c_char {
+    LAST_ERROR.with(|last| last.borrow_mut().take());
+    let result = (|| {
+        if root.is_null() || query.is_null() {
+            return Err("root and query can't be NULL".to_string());
+        }
+        let root = CStr::from_ptr(root)
+            .to_str()
+            .map_err(|err| err.to_string())?;
+        let query = CStr::from_ptr(query)
+            .to_str()
+            .map_err(|err| err.to_string())?;
+        let default = FpOptions::default();
+        let options = options.as_ref().unwrap_or(&default);
+
+        let mut finder_options = FinderOptions::new(root)
+            .include_hidden(options.include_hidden)
+            .include_vendor(options.include_vendor)
+            .projects_only(options.projects_only);
+        if options.max_depth > 0 {
+            finder_options = finder_options.max_depth(options.max_depth);
+        }
+        if options.projects_only {
+            finder_options = finder_options.marker(".git");
+        }
+
+        match Finder::new(finder_options).find(query) {
+            Ok(Some(dir)) => dir
+                .into_os_string()
+                .into_string()
+                .map_err(|dir| format!("{:?} isn't valid UTF-8", dir))
+                .and_then(|dir| CString::new(dir).map_err(|err| err.to_string()))
+                .map(Some),
+            Ok(None) => Ok(None),
+            Err(err) => Err(err.to_string()),
+        }
+    })();
+
+    match result {
+        Ok(Some(dir)) => dir.into_raw(),
+        Ok(None) => ptr::null_mut(),
+        Err(err) => {
+            let err = CString::new(err).unwrap_or_default();
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some(err));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Returns why the last `fp_find` on this thread failed, or NULL when it
+/// didn't. The message stays valid until the next `fp_find`.
+#[no_mangle]
+pub extern "C" fn fp_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |err| err.as_ptr())
+    })
+}
+
+/// Releases a path returned by `fp_find`.
+///
+/// # Safety
+///
+/// `path` must be NULL or a pointer returned by `fp_find`, not released
+/// before.
+#[no_mangle]
+pub unsafe extern "C" fn fp_free(path: *mut c_char) {
+    if !path.is_null() {
+        drop(CString::from_raw(path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_fp_find() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+        fs::create_dir_all(path.join("acme").join("api"))?;
+
+        let root = CString::new(path.to_str().expect("UTF-8 temp dir"))?;
+        let query = CString::new("api")?;
+        unsafe {
+            let found = fp_find(root.as_ptr(), query.as_ptr(), ptr::null());
+            assert!(!found.is_null());
+            assert_eq!(
+                CStr::from_ptr(found).to_str()?,
+                path.join("acme").join("api").to_str().expect("UTF-8 path")
+            );
+            fp_free(found);
+
+            let options = FpOptions {
+                max_depth: 1,
+                ..Default::default()
+            };
+            assert!(fp_find(root.as_ptr(), query.as_ptr(), &options).is_null());
+            assert!(fp_last_error().is_null());
+
+            let missing = CString::new(path.join("missing").to_str().expect("UTF-8 path"))?;
+            assert!(fp_find(missing.as_ptr(), query.as_ptr(), ptr::null()).is_null());
+            assert!(!fp_last_error().is_null());
+        }
+        Ok(())
+    }
+}
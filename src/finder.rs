@@ -0,0 +1,2509 @@
+use crate::collation::Collation;
+use crate::error::{FindError, Result};
+use crate::manifest::Manifest;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+use crate::uring;
+use crate::{clone, git, glob, vcs};
+use nucleo_matcher::pattern::{Atom, AtomKind, CaseMatching, Normalization};
+use nucleo_matcher::Utf32Str;
+use regex::bytes::Regex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{self, Read as _};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "parallel")]
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "parallel")]
+use std::sync::Mutex;
+#[cfg(feature = "parallel")]
+use std::thread;
+use std::{mem, vec};
+
+/// What to search and how. The defaults skip hidden and "vendor" folders
+/// and search as deep as needed.
+#[derive(Default, Debug, Clone)]
+pub struct FinderOptions {
+    /// Folder to search inside.
+    pub root: PathBuf,
+    /// Also search in "vendor" folders.
+    pub include_vendor: bool,
+    /// Also search in hidden folders: those starting with a dot, and on
+    /// Windows, those with the hidden attribute.
+    pub include_hidden: bool,
+    /// Search folders in alphabetical order, rather than the order the
+    /// file system returns them in.
+    pub sort_alphabetically: bool,
+    /// What alphabetical order means.
+    pub collation: Collation,
+    /// Don't search deeper than this many folders below the root.
+    pub max_depth: Option<usize>,
+    /// Folder names that are never searched.
+    pub prune: Vec<String>,
+    /// Patterns of folders that are never searched, see
+    /// [`glob::matches_path`].
+    pub exclude: Vec<String>,
+    /// Files or folders that make a folder a project root.
+    pub markers: Vec<String>,
+    /// Also search inside symlinks to folders, and junctions on Windows.
+    /// Links back to a folder being searched are skipped, and every
+    /// folder reached through links is searched once.
+    pub follow_links: bool,
+    /// Only match project roots.
+    pub projects_only: bool,
+    /// Never search inside project roots.
+    pub stop_at_projects: bool,
+    /// Don't match linked git worktrees.
+    pub skip_worktrees: bool,
+    /// Only match repositories on a branch matching this pattern.
+    pub branch: Option<String>,
+    /// Print every folder searched to stderr.
+    pub log_enabled: bool,
+    /// How folder names are compared with the name searched for.
+    pub matcher: Matcher,
+    /// Whether to match folders, files, or both.
+    pub kind: Kind,
+    /// Match the name searched for against the name this manifest in a
+    /// folder declares, rather than against the folder name.
+    pub manifest: Option<Manifest>,
+    /// Only match folders with a file containing this text or matching
+    /// this pattern, or files that do, see [`contains_text`] for which
+    /// files are read.
+    pub contains: Option<Content>,
+    /// Only match folders with at least this many files, in them or in
+    /// the folders inside them, leaving hidden folders out.
+    pub min_files: Option<usize>,
+    /// Only match folders with at most this many files, counted the same
+    /// way.
+    pub max_files: Option<usize>,
+    /// Stops the search once set to true, from another thread or a signal
+    /// handler, making it fail with [`FindError::Cancelled`].
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Compare folder names ignoring case, like the default file systems
+    /// of macOS and Windows do, see [`crate::paths::FOLDS_CASE`].
+    pub ignore_case: bool,
+    /// Read this many folders at the same time in [`Finder::find`]. With
+    /// 0 or 1, when following links, with a memory limit or without the
+    /// `parallel` feature, they're read one at a time.
+    pub threads: usize,
+    /// Open and look up folders in batches with io_uring in
+    /// [`Finder::find`], instead of threads. Only on Linux with the
+    /// `io-uring` feature, and without a memory limit. Kernels without
+    /// io_uring, or where it's disabled, search the usual way.
+    pub io_uring: bool,
+    /// Roughly how many bytes the folders waiting to be searched can take.
+    /// Past it, the search stops keeping them, and searches the ones it
+    /// has depth-first instead, returning matches as soon as they're
+    /// found. That's a different order than breadth-first, so a deeper
+    /// folder can be found first.
+    pub max_memory: Option<usize>,
+}
+
+impl FinderOptions {
+    /// Returns the default options to search inside a root.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        FinderOptions {
+            root: root.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn include_vendor(mut self, include_vendor: bool) -> Self {
+        self.include_vendor = include_vendor;
+        self
+    }
+
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    pub fn sort_alphabetically(mut self, sort_alphabetically: bool) -> Self {
+        self.sort_alphabetically = sort_alphabetically;
+        self
+    }
+
+    pub fn collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn prune(mut self, name: impl Into<String>) -> Self {
+        self.prune.push(name.into());
+        self
+    }
+
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    pub fn marker(mut self, marker: impl Into<String>) -> Self {
+        self.markers.push(marker.into());
+        self
+    }
+
+    pub fn follow_links(mut self, follow_links: bool) -> Self {
+        self.follow_links = follow_links;
+        self
+    }
+
+    pub fn projects_only(mut self, projects_only: bool) -> Self {
+        self.projects_only = projects_only;
+        self
+    }
+
+    pub fn stop_at_projects(mut self, stop_at_projects: bool) -> Self {
+        self.stop_at_projects = stop_at_projects;
+        self
+    }
+
+    pub fn skip_worktrees(mut self, skip_worktrees: bool) -> Self {
+        self.skip_worktrees = skip_worktrees;
+        self
+    }
+
+    pub fn branch(mut self, pattern: impl Into<String>) -> Self {
+        self.branch = Some(pattern.into());
+        self
+    }
+
+    pub fn log_enabled(mut self, log_enabled: bool) -> Self {
+        self.log_enabled = log_enabled;
+        self
+    }
+
+    pub fn matcher(mut self, matcher: Matcher) -> Self {
+        self.matcher = matcher;
+        self
+    }
+
+    pub fn kind(mut self, kind: Kind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn manifest(mut self, manifest: Manifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    pub fn contains(mut self, text: impl Into<String>) -> Self {
+        self.contains = Some(Content::Text(text.into()));
+        self
+    }
+
+    pub fn contains_regex(mut self, regex: Regex) -> Self {
+        self.contains = Some(Content::Regex(regex));
+        self
+    }
+
+    pub fn min_files(mut self, min_files: usize) -> Self {
+        self.min_files = Some(min_files);
+        self
+    }
+
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.max_files = Some(max_files);
+        self
+    }
+
+    pub fn cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn io_uring(mut self, io_uring: bool) -> Self {
+        self.io_uring = io_uring;
+        self
+    }
+
+    pub fn max_memory(mut self, max_memory: usize) -> Self {
+        self.max_memory = Some(max_memory);
+        self
+    }
+}
+
+/// How folder names are compared with the name searched for.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum Matcher {
+    /// The folder name is the name searched for.
+    #[default]
+    Exact,
+    /// The name searched for is a pattern, see [`glob::matches`].
+    Glob,
+    /// The name searched for appears in the folder name in order, but not
+    /// necessarily together, ignoring case: "k8sctl" matches "k8s-control".
+    /// Matches are scored with the matcher of the nucleo fuzzy finder, see
+    /// [`FuzzyScorer`].
+    Fuzzy,
+}
+
+impl Matcher {
+    /// Returns whether a folder name matches the name searched for.
+    pub fn matches(self, name: &str, folder: &str) -> bool {
+        self.score(name, folder).is_some()
+    }
+
+    /// Returns how well a folder name matches the name searched for, or
+    /// None when it doesn't. Fuzzy matches score higher the more the
+    /// characters found are together and at the start of words, so
+    /// "k8s" scores "k8s-control" above "kube-system-8s". Exact and glob
+    /// matches all score 0.
+    ///
+    /// This is for scoring a single folder: a fuzzy match sets up a new
+    /// scorer every time. To score many folders against the same name,
+    /// like when ranking the matches found, use [`FuzzyScorer`].
+    pub fn score(self, name: &str, folder: &str) -> Option<u16> {
+        match self {
+            Matcher::Exact => (folder == name).then_some(0),
+            Matcher::Glob => glob::matches(name, folder).then_some(0),
+            Matcher::Fuzzy => FuzzyScorer::new(name).score(folder),
+        }
+    }
+}
+
+/// Scores folder names against a name like [`Matcher::Fuzzy`] does,
+/// keeping its buffers between folders so scoring one doesn't allocate.
+pub struct FuzzyScorer {
+    matcher: nucleo_matcher::Matcher,
+    atom: Atom,
+    chars: Vec<char>,
+}
+
+impl FuzzyScorer {
+    pub fn new(name: &str) -> Self {
+        // The matcher only lowercases ASCII letters of the name itself.
+        let name: String = name.chars().flat_map(char::to_lowercase).collect();
+        FuzzyScorer {
+            matcher: nucleo_matcher::Matcher::new(nucleo_matcher::Config::DEFAULT),
+            // Accented letters only match themselves, like in the other
+            // matchers.
+            atom: Atom::new(
+                &name,
+                CaseMatching::Ignore,
+                Normalization::Never,
+                AtomKind::Fuzzy,
+                false,
+            ),
+            chars: Vec::new(),
+        }
+    }
+
+    /// Returns how well a folder name matches, or None when it doesn't.
+    pub fn score(&mut self, folder: &str) -> Option<u16> {
+        let folder = Utf32Str::new(folder, &mut self.chars);
+        self.atom.score(folder, &mut self.matcher)
+    }
+}
+
+/// What a search matches. Folders are searched the same way for all of
+/// them, so files are found in the same order as folders would be, and
+/// hidden files and files matching [`FinderOptions::exclude`] are left
+/// out like folders are.
+///
+/// Files only match by name: queries for a remote or for every project
+/// root, and the options about projects and repositories, only apply to
+/// folders.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Only folders.
+    #[default]
+    Dir,
+    /// Only files, and links to them.
+    File,
+    /// Both folders and files.
+    Any,
+}
+
+/// What [`Finder::walk`] does after visiting a folder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visit {
+    /// Visit the folders inside it too.
+    Descend,
+    /// Leave the folders inside it out.
+    Skip,
+}
+
+/// Searches a root for folders by name, breadth-first.
+///
+/// A name like "github.com/org/repo", or a clone URL, matches the
+/// repositories whose origin remote points there instead, and an empty
+/// name matches every project root.
+#[derive(Debug, Clone)]
+pub struct Finder {
+    options: FinderOptions,
+}
+
+impl Finder {
+    pub fn new(options: FinderOptions) -> Self {
+        Finder { options }
+    }
+
+    pub fn options(&self) -> &FinderOptions {
+        &self.options
+    }
+
+    /// Returns the first folder matching the name. With more than one
+    /// thread, folders are read at the same time, but the folder returned
+    /// is the same one reading them one at a time would return.
+    pub fn find(&self, name: &str) -> Result<Option<PathBuf>> {
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if self.options.io_uring && self.options.max_memory.is_none() {
+            if let Ok(mut reader) = uring::Reader::new() {
+                return self.find_uring(name, &mut reader);
+            }
+        }
+        // Which folders a link leads to depends on the links followed
+        // before it, so following links needs a single thread. Reading a
+        // depth at a time keeps all of it, so a memory limit does too.
+        #[cfg(feature = "parallel")]
+        {
+            let options = &self.options;
+            if options.threads > 1 && !options.follow_links && options.max_memory.is_none() {
+                return self.find_parallel(name);
+            }
+        }
+        self.iter(name).next().transpose()
+    }
+
+    /// Returns every folder matching the name, in the order they're found.
+    pub fn find_all(&self, name: &str) -> Result<Vec<PathBuf>> {
+        self.iter(name).collect()
+    }
+
+    /// Returns an iterator over the folders matching the name, which
+    /// traverses the root as it goes, so stopping early skips the rest
+    /// of the search.
+    pub fn iter(&self, name: &str) -> Matches<'_> {
+        Matches {
+            finder: self,
+            // Module paths look like remotes, but aren't matched as one.
+            remote: match self.options.manifest {
+                Some(_) => None,
+                None => clone::Remote::from_query(name),
+            },
+            name: name.into(),
+            lowercase_name: name.chars().flat_map(char::to_lowercase).collect(),
+            fuzzy: (self.options.matcher == Matcher::Fuzzy).then(|| FuzzyScorer::new(name)),
+            dirs: Vec::new(),
+            names: Names::default(),
+            path: PathBuf::new(),
+            lowercase_folder: String::new(),
+            next: 0,
+            expand: None,
+            found: VecDeque::new(),
+            depth_first: false,
+            stack: Vec::new(),
+            followed: HashSet::new(),
+            started: false,
+            done: false,
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!("search", root = %self.options.root.display(), query = name),
+        }
+    }
+
+    /// Visits every folder under the root breadth-first, skipping the
+    /// ones the options leave out, and lets the callback decide whether
+    /// to look inside each of them. Returning `ControlFlow::Break` stops
+    /// the walk and returns its value.
+    pub fn walk<B>(
+        &self,
+        mut visit: impl FnMut(&Path) -> ControlFlow<B, Visit>,
+    ) -> Result<Option<B>> {
+        let options = &self.options;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("walk", root = %options.root.display()).entered();
+        if options.max_depth == Some(0) {
+            return Ok(None);
+        }
+
+        let mut followed = HashSet::new();
+        let mut queue: VecDeque<(PathBuf, usize)> = self
+            .root_dirs(&mut followed, None)?
+            .into_iter()
+            .map(|name| (options.root.join(name), 1))
+            .collect();
+        while let Some((dir, depth)) = queue.pop_front() {
+            self.check_cancelled()?;
+            if options.log_enabled {
+                eprintln!("Searching in: {}", dir.display());
+            }
+            match visit(&dir) {
+                ControlFlow::Break(value) => return Ok(Some(value)),
+                ControlFlow::Continue(Visit::Skip) => {}
+                ControlFlow::Continue(Visit::Descend) => {
+                    if options.max_depth.is_some_and(|max| depth >= max) {
+                        continue;
+                    }
+                    queue.extend(
+                        self.dirs(&dir, &mut followed)?
+                            .into_iter()
+                            .map(|name| (dir.join(name), depth + 1)),
+                    );
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // Searches one depth at a time, with the folders of each depth shared
+    // between the threads. Reading a folder either finds a match among its
+    // children, fails, or yields the children to search next. The earliest
+    // folder that found a match or failed, in the order Matches reads
+    // them, is kept in first: every thread checks it before reading its
+    // next folder and drops the folders after it, so the search stops as
+    // soon as the folders before the match are read.
+    #[cfg(feature = "parallel")]
+    fn find_parallel(&self, name: &str) -> Result<Option<PathBuf>> {
+        let options = &self.options;
+        let mut level = match self.first_level(&mut self.iter(name))? {
+            ControlFlow::Continue(level) => level,
+            ControlFlow::Break(found) => return Ok(found),
+        };
+
+        let mut depth = 1;
+        while !level.is_empty() {
+            let threads = options.threads.min(level.len());
+            let deques = Deques::new(level.len(), threads);
+            let first = AtomicUsize::new(usize::MAX);
+            let mut read: Vec<Option<Read>> = level.iter().map(|_| None).collect();
+            thread::scope(|scope| {
+                let searches: Vec<_> = (0..threads)
+                    .map(|thread| {
+                        let (level, deques, first) = (&level, &deques, &first);
+                        scope.spawn(move || {
+                            let mut matcher = self.iter(name);
+                            let mut results = Vec::new();
+                            while let Some(i) = deques.next(thread) {
+                                if i > first.load(Ordering::Relaxed) {
+                                    continue;
+                                }
+                                let result =
+                                    matcher.read(&level[i], depth, |finder, followed, files| {
+                                        finder.entries(&level[i], followed, files)
+                                    });
+                                if !matches!(result, Read::Children(_)) {
+                                    first.fetch_min(i, Ordering::Relaxed);
+                                }
+                                results.push((i, result));
+                            }
+                            results
+                        })
+                    })
+                    .collect();
+                for search in searches {
+                    for (i, result) in search.join().expect("the search thread doesn't panic") {
+                        read[i] = Some(result);
+                    }
+                }
+            });
+
+            let first = first.into_inner();
+            if first != usize::MAX {
+                return match read.swap_remove(first) {
+                    Some(Read::Found(dir)) => Ok(Some(dir)),
+                    Some(Read::Failed(err)) => Err(err),
+                    _ => unreachable!("the first folder found a match or failed"),
+                };
+            }
+            level = read
+                .into_iter()
+                .flat_map(|read| match read {
+                    Some(Read::Children(children)) => children,
+                    _ => Vec::new(),
+                })
+                .collect();
+            depth += 1;
+        }
+        Ok(None)
+    }
+
+    // Starts a search one depth at a time, returning the folders right
+    // inside the root to search first. Files right inside the root match
+    // before any folder, like in Matches::step, so a matching one ends the
+    // search, like a max depth of 0 does.
+    #[cfg(any(feature = "parallel", all(target_os = "linux", feature = "io-uring")))]
+    fn first_level(
+        &self,
+        matcher: &mut Matches,
+    ) -> Result<ControlFlow<Option<PathBuf>, Vec<PathBuf>>> {
+        let options = &self.options;
+        if options.max_depth == Some(0) {
+            return Ok(ControlFlow::Break(None));
+        }
+        let mut files = Vec::new();
+        let wants_files = matcher.wants_files();
+        let level = self
+            .root_dirs(&mut matcher.followed, wants_files.then_some(&mut files))?
+            .into_iter()
+            .map(|name| options.root.join(name))
+            .collect();
+        matcher.path.push(&options.root);
+        matcher.push_matching_files(files);
+        Ok(match matcher.found.pop_front() {
+            Some(file) => ControlFlow::Break(Some(file)),
+            None => ControlFlow::Continue(level),
+        })
+    }
+
+    // Searches one depth at a time like find_parallel, on a single thread,
+    // with the folders of each depth read ahead in batches by the reader.
+    // Folders are still searched in order, so links can be followed.
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn find_uring(&self, name: &str, reader: &mut uring::Reader) -> Result<Option<PathBuf>> {
+        let options = &self.options;
+        let mut matcher = self.iter(name);
+        let mut level = match self.first_level(&mut matcher)? {
+            ControlFlow::Continue(level) => level,
+            ControlFlow::Break(found) => return Ok(found),
+        };
+
+        // Once the ring fails, the rest of the folders are read the usual
+        // way.
+        let mut reader = Some(reader);
+        let mut depth = 1;
+        while !level.is_empty() {
+            let mut next = Vec::new();
+            // Folders at the maximum depth are never read.
+            let reads = options.max_depth.is_none_or(|max| depth < max);
+            for batch in level.chunks(uring::BATCH) {
+                let read = match reader.as_mut().filter(|_| reads) {
+                    Some(ring) => ring.read_dirs(batch).ok(),
+                    None => None,
+                };
+                if read.is_none() && reads {
+                    reader = None;
+                }
+                let mut read = read.into_iter().flatten();
+                for dir in batch {
+                    let entries = read.next();
+                    let result =
+                        matcher.read(dir, depth, |finder, followed, files| match entries {
+                            Some(Ok(entries)) => {
+                                finder.list(dir, entries.into_iter().map(Ok), followed, files)
+                            }
+                            Some(Err(source)) => Err(FindError::Io {
+                                path: dir.clone(),
+                                source,
+                            }),
+                            None => finder.entries(dir, followed, files),
+                        });
+                    match result {
+                        Read::Found(dir) => return Ok(Some(dir)),
+                        Read::Failed(err) => return Err(err),
+                        Read::Children(children) => next.extend(children),
+                    }
+                }
+            }
+            level = next;
+            depth += 1;
+        }
+        Ok(None)
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.options.cancel {
+            Some(cancel) if cancel.load(Ordering::Relaxed) => Err(FindError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    // Returns the names of the folders right inside the root that should
+    // be searched, and puts the files in files, if given.
+    fn root_dirs(
+        &self,
+        followed: &mut HashSet<PathBuf>,
+        files: Option<&mut Vec<OsString>>,
+    ) -> Result<Vec<OsString>> {
+        let root = &self.options.root;
+        if root.as_os_str().is_empty() {
+            return Err(FindError::NoRootConfigured);
+        }
+        self.entries(root, followed, files)
+            .map_err(|err| match err {
+                FindError::Io { path, source } if path == *root => {
+                    FindError::RootNotAccessible { path, source }
+                }
+                err => err,
+            })
+    }
+
+    // Returns the names of the folders right inside p that should be
+    // searched. Only names are returned, so folders skipped or never
+    // searched don't cost a path each. Links followed are added to
+    // followed, so they're never searched twice.
+    fn dirs(&self, p: &Path, followed: &mut HashSet<PathBuf>) -> Result<Vec<OsString>> {
+        self.entries(p, followed, None)
+    }
+
+    // Like dirs, also putting the names of the files inside p in files,
+    // if given, leaving out the same hidden and excluded ones.
+    fn entries(
+        &self,
+        p: &Path,
+        followed: &mut HashSet<PathBuf>,
+        files: Option<&mut Vec<OsString>>,
+    ) -> Result<Vec<OsString>> {
+        let entries = fs::read_dir(p).map_err(|source| FindError::Io {
+            path: p.to_path_buf(),
+            source,
+        })?;
+        self.list(p, entries, followed, files)
+    }
+
+    // Like entries, for the entries of p read some other way.
+    fn list<E: Entry>(
+        &self,
+        p: &Path,
+        entries: impl IntoIterator<Item = io::Result<E>>,
+        followed: &mut HashSet<PathBuf>,
+        mut files: Option<&mut Vec<OsString>>,
+    ) -> Result<Vec<OsString>> {
+        let options = &self.options;
+        let io_error = |source| FindError::Io {
+            path: p.to_path_buf(),
+            source,
+        };
+        let mut dirs = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(io_error)?;
+            let entry_type = entry.entry_type().map_err(io_error)?;
+            let name = entry.name();
+            let is_dir = if entry_type == EntryType::Symlink {
+                options.follow_links && follow_link(p, &p.join(&name), followed)
+            } else {
+                entry_type == EntryType::Dir
+            };
+            if !is_dir {
+                let Some(files) = files.as_deref_mut() else {
+                    continue;
+                };
+                let is_file = entry_type == EntryType::File
+                    || entry_type == EntryType::Symlink
+                        && fs::metadata(p.join(&name)).is_ok_and(|meta| meta.is_file());
+                if is_file
+                    && (options.include_hidden || !is_hidden(&entry, &name.to_string_lossy()))
+                    && !options
+                        .exclude
+                        .iter()
+                        .any(|pattern| glob::matches_path(pattern, &p.join(&name)))
+                {
+                    files.push(name);
+                }
+                continue;
+            }
+
+            // Borrowed rather than copied, unless the name isn't UTF-8.
+            let name_str = name.to_string_lossy();
+            let reason = if !options.include_hidden && is_hidden(&entry, &name_str) {
+                Some("hidden")
+            } else if !options.include_vendor && name_str == "vendor" {
+                Some("vendor")
+            } else if options.prune.iter().any(|prune| *prune == name_str) {
+                Some("pruned")
+            } else if !options.exclude.is_empty() && {
+                let path = p.join(&name);
+                options
+                    .exclude
+                    .iter()
+                    .any(|pattern| glob::matches_path(pattern, &path))
+            } {
+                Some("excluded")
+            } else {
+                None
+            };
+            match reason {
+                Some(reason) => skipped_entry(p, &name, reason),
+                None => dirs.push(name),
+            }
+        }
+
+        if options.sort_alphabetically {
+            match options.collation {
+                Collation::Lexical => dirs.sort(),
+                collation => dirs
+                    .sort_by(|a, b| collation.compare(&a.to_string_lossy(), &b.to_string_lossy())),
+            }
+            if let Some(files) = files {
+                match options.collation {
+                    Collation::Lexical => files.sort(),
+                    collation => files.sort_by(|a, b| {
+                        collation.compare(&a.to_string_lossy(), &b.to_string_lossy())
+                    }),
+                }
+            }
+        }
+
+        Ok(dirs)
+    }
+}
+
+/// Iterator over the folders matching a name, see [`Finder::iter`].
+pub struct Matches<'a> {
+    finder: &'a Finder,
+    // Queries like "github.com/org/repo" match repositories by their
+    // origin remote, since the folder might be named differently.
+    remote: Option<clone::Remote>,
+    name: PathBuf,
+    // The name lowercased once, for matches ignoring case.
+    lowercase_name: String,
+    // Scores folder names for Matcher::Fuzzy.
+    fuzzy: Option<FuzzyScorer>,
+    // Every folder found so far, in the order they're searched. Each one
+    // only keeps its own name, so millions of them don't each hold a
+    // copy of the path above them.
+    dirs: Vec<Node>,
+    // The names of the folders in dirs, each kept once.
+    names: Names,
+    // The path of the folder being looked at and its name lowercased,
+    // built in place so looking at a folder doesn't allocate.
+    path: PathBuf,
+    lowercase_folder: String,
+    // The next folder in dirs to search.
+    next: usize,
+    // A folder in dirs whose children haven't been read yet.
+    expand: Option<usize>,
+    // Matches found while reading children, not returned yet.
+    found: VecDeque<PathBuf>,
+    // Whether the folders left in dirs are searched depth-first, once
+    // dirs takes more than FinderOptions::max_memory.
+    depth_first: bool,
+    // In a depth-first search, the children left to search in every
+    // folder above the one in path, with their depth.
+    stack: Vec<(vec::IntoIter<OsString>, u32)>,
+    // Where the links followed so far point to.
+    followed: HashSet<PathBuf>,
+    started: bool,
+    done: bool,
+    // Every step runs inside it, to tell searches in different roots
+    // apart.
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+// What reading a folder in Finder::find_parallel or Finder::find_uring
+// gave.
+#[cfg(any(feature = "parallel", all(target_os = "linux", feature = "io-uring")))]
+enum Read {
+    Found(PathBuf),
+    Failed(FindError),
+    Children(Vec<PathBuf>),
+}
+
+// The folders of one depth in Finder::find_parallel, as positions in it,
+// split between the threads in runs of consecutive folders. Each thread
+// takes the folders of its own run in order, and once it runs out, takes
+// them from the end of another thread's run.
+#[cfg(feature = "parallel")]
+struct Deques(Vec<Mutex<VecDeque<usize>>>);
+
+#[cfg(feature = "parallel")]
+impl Deques {
+    fn new(len: usize, threads: usize) -> Self {
+        let run = len.div_ceil(threads);
+        Deques(
+            (0..threads)
+                .map(|thread| Mutex::new((thread * run..len.min((thread + 1) * run)).collect()))
+                .collect(),
+        )
+    }
+
+    fn next(&self, thread: usize) -> Option<usize> {
+        let deque = |i: usize| self.0[i].lock().expect("no thread panics");
+        // Its own deque is unlocked before taking from another, so two
+        // threads taking from each other don't wait for each other.
+        let own = deque(thread).pop_front();
+        own.or_else(|| {
+            (1..self.0.len())
+                .map(|other| (thread + other) % self.0.len())
+                .find_map(|other| deque(other).pop_back())
+        })
+    }
+}
+
+// A folder found by Matches, in 16 bytes, since there can be millions of
+// them. Positions are u32s, which fit more folders than fit in memory.
+struct Node {
+    // Where the folder it's in is in Matches::dirs, or None for the
+    // folders right inside the root.
+    parent: Option<u32>,
+    // Where its name is in Matches::names.
+    name: u32,
+    // How deep it is, where the children of the root are 1.
+    depth: u32,
+}
+
+// Folder names found by Matches, each kept once however many folders
+// share it, like the "src" and "node_modules" folders of every project.
+#[derive(Default)]
+struct Names {
+    names: Vec<Arc<OsStr>>,
+    ids: HashMap<Arc<OsStr>, u32>,
+    // Roughly how many bytes they take.
+    bytes: usize,
+}
+
+impl Names {
+    // What each name takes besides its bytes: its Arc, and its entries
+    // in names and ids.
+    const OVERHEAD: usize = 64;
+
+    // Returns where the name is, adding it if it's new.
+    fn intern(&mut self, name: OsString) -> u32 {
+        if let Some(&id) = self.ids.get(name.as_os_str()) {
+            return id;
+        }
+        self.bytes += name.len() + Self::OVERHEAD;
+        let id = self.names.len() as u32;
+        let name: Arc<OsStr> = name.into();
+        self.names.push(name.clone());
+        self.ids.insert(name, id);
+        id
+    }
+
+    fn get(&self, id: u32) -> &OsStr {
+        &self.names[id as usize]
+    }
+}
+
+// Puts the path of dirs[i] in path, reusing its buffer.
+fn fill_path(dirs: &[Node], names: &Names, i: usize, root: &Path, path: &mut PathBuf) {
+    let node = &dirs[i];
+    match node.parent {
+        Some(parent) => fill_path(dirs, names, parent as usize, root, path),
+        None => {
+            path.as_mut_os_string().clear();
+            path.push(root);
+        }
+    }
+    path.push(names.get(node.name));
+}
+
+impl Matches<'_> {
+    // Returns whether the folder in self.path matches. Without a name,
+    // every project root does.
+    fn matches(&mut self) -> bool {
+        let options = &self.finder.options;
+        if options.kind == Kind::File {
+            return false;
+        }
+        let dir = self.path.as_path();
+        let found = match &self.remote {
+            Some(remote) => git::origin_url(dir)
+                .and_then(|url| clone::Remote::parse(&url, clone::DEFAULT_HOST).ok())
+                .is_some_and(|origin| origin.same_repository(remote)),
+            None if self.name.as_os_str().is_empty() => is_project(dir, &options.markers),
+            None => match options.manifest {
+                Some(manifest) => manifest.declares(dir, &self.name.to_string_lossy()),
+                None => self.name_matches(),
+            },
+        };
+        let dir = self.path.as_path();
+        found
+            && (!options.projects_only || is_project(dir, &options.markers))
+            && (!options.skip_worktrees || git::main_worktree(dir).is_none())
+            && on_branch(dir, options.branch.as_deref())
+            && options
+                .contains
+                .as_ref()
+                .is_none_or(|content| contains_text(dir, content, &options.prune))
+            && files_within(dir, options.min_files, options.max_files)
+    }
+
+    // Returns whether the name of the folder or file in self.path is the
+    // name searched for.
+    fn name_matches(&mut self) -> bool {
+        let options = &self.finder.options;
+        self.path.file_name().is_some_and(|folder| {
+            match (options.matcher, options.ignore_case) {
+                (Matcher::Exact, false) => folder == self.name.as_os_str(),
+                (Matcher::Exact, true) => folder
+                    .to_string_lossy()
+                    .chars()
+                    .flat_map(char::to_lowercase)
+                    .eq(self.lowercase_name.chars()),
+                (Matcher::Glob, true) => {
+                    self.lowercase_folder.clear();
+                    self.lowercase_folder.extend(
+                        folder
+                            .to_string_lossy()
+                            .chars()
+                            .flat_map(char::to_lowercase),
+                    );
+                    Matcher::Glob.matches(&self.lowercase_name, &self.lowercase_folder)
+                }
+                // Fuzzy matches ignore case on their own.
+                (Matcher::Fuzzy, _) => self
+                    .fuzzy
+                    .as_mut()
+                    .expect("fuzzy searches have a scorer")
+                    .score(&folder.to_string_lossy())
+                    .is_some(),
+                (Matcher::Glob, false) => {
+                    Matcher::Glob.matches(&self.name.to_string_lossy(), &folder.to_string_lossy())
+                }
+            }
+        })
+    }
+
+    // Returns whether files can match: only a name matches them.
+    fn wants_files(&self) -> bool {
+        self.finder.options.kind != Kind::Dir
+            && self.finder.options.manifest.is_none()
+            && self.remote.is_none()
+            && !self.name.as_os_str().is_empty()
+    }
+
+    // Adds the files read from the folder in self.path whose name
+    // matches to the matches found.
+    fn push_matching_files(&mut self, files: Vec<OsString>) {
+        for name in files {
+            self.path.push(&name);
+            let options = &self.finder.options;
+            if self.name_matches()
+                && options
+                    .contains
+                    .as_ref()
+                    .is_none_or(|content| file_contains(&self.path, content))
+            {
+                if self.finder.options.log_enabled {
+                    eprintln!("Found: {}", self.path.display());
+                }
+                #[cfg(feature = "tracing")]
+                tracing::debug!(path = %self.path.display(), "matched");
+                self.found.push_back(self.path.clone());
+            }
+            self.path.pop();
+        }
+    }
+
+    // Reads a folder for Finder::find_parallel and Finder::find_uring, the
+    // way step and expand would: the folders right inside the root are
+    // matches themselves, and the others only have their children, listed
+    // by entries, checked.
+    #[cfg(any(feature = "parallel", all(target_os = "linux", feature = "io-uring")))]
+    fn read(
+        &mut self,
+        dir: &Path,
+        depth: usize,
+        entries: impl FnOnce(
+            &Finder,
+            &mut HashSet<PathBuf>,
+            Option<&mut Vec<OsString>>,
+        ) -> Result<Vec<OsString>>,
+    ) -> Read {
+        let finder = self.finder;
+        let options = &finder.options;
+        if let Err(err) = finder.check_cancelled() {
+            return Read::Failed(err);
+        }
+        if options.log_enabled {
+            eprintln!("Searching in: {}", dir.display());
+        }
+        self.path.as_mut_os_string().clear();
+        self.path.push(dir);
+        if depth == 1 && self.matches() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(path = %self.path.display(), "matched");
+            return Read::Found(self.path.clone());
+        }
+        if options.max_depth.is_some_and(|max| depth >= max) {
+            skipped(dir, "max depth");
+            return Read::Children(Vec::new());
+        }
+        if options.stop_at_projects && is_project(dir, &options.markers) {
+            skipped(dir, "project");
+            return Read::Children(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        let wants_files = self.wants_files();
+        let names = match entries(
+            finder,
+            &mut self.followed,
+            wants_files.then_some(&mut files),
+        ) {
+            Ok(names) => names,
+            Err(err) => return Read::Failed(err),
+        };
+        let mut children = Vec::with_capacity(names.len());
+        for name in names {
+            self.path.push(&name);
+            if self.matches() {
+                if options.log_enabled {
+                    eprintln!("Found: {}", self.path.display());
+                }
+                #[cfg(feature = "tracing")]
+                tracing::debug!(path = %self.path.display(), "matched");
+                return Read::Found(self.path.clone());
+            }
+            children.push(self.path.clone());
+            self.path.pop();
+        }
+        self.push_matching_files(files);
+        match self.found.pop_front() {
+            Some(file) => Read::Found(file),
+            None => Read::Children(children),
+        }
+    }
+
+    // Moves the search one folder forward, returning it if it matches.
+    fn step(&mut self) -> Result<Option<PathBuf>> {
+        let finder = self.finder;
+        let options = &finder.options;
+        finder.check_cancelled()?;
+        if !self.started {
+            self.started = true;
+            if options.max_depth == Some(0) {
+                self.done = true;
+                return Ok(None);
+            }
+            let mut files = Vec::new();
+            let wants_files = self.wants_files();
+            for name in finder.root_dirs(&mut self.followed, wants_files.then_some(&mut files))? {
+                let name = self.names.intern(name);
+                self.dirs.push(Node {
+                    parent: None,
+                    name,
+                    depth: 1,
+                });
+            }
+            self.path.push(&options.root);
+            self.push_matching_files(files);
+            return Ok(None);
+        }
+
+        if self.depth_first {
+            return self.step_depth_first();
+        }
+        if let Some(i) = self.expand.take() {
+            self.expand(i)?;
+            if let Some(max) = options.max_memory {
+                let bytes = self.dirs.capacity() * mem::size_of::<Node>() + self.names.bytes;
+                if bytes > max {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(bytes, max, "searching depth-first");
+                    self.depth_first = true;
+                }
+            }
+            return Ok(None);
+        }
+
+        let i = self.next;
+        if i >= self.dirs.len() {
+            self.done = true;
+            return Ok(None);
+        }
+        self.next += 1;
+        fill_path(&self.dirs, &self.names, i, &options.root, &mut self.path);
+        if options.log_enabled {
+            eprintln!("Searching in: {}", self.path.display());
+        }
+
+        // Deeper folders are checked as soon as they're found, when
+        // reading the children of their parent.
+        self.expand = Some(i);
+        if self.dirs[i].depth == 1 && self.matches() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(path = %self.path.display(), "matched");
+            return Ok(Some(self.path.clone()));
+        }
+        Ok(None)
+    }
+
+    // Moves a depth-first search one folder forward, returning it if it
+    // matches. The folders left in dirs are searched one after the other,
+    // each of them down to the bottom.
+    fn step_depth_first(&mut self) -> Result<Option<PathBuf>> {
+        let options = &self.finder.options;
+        let Some((children, depth)) = self.stack.last_mut() else {
+            let i = self.next;
+            if i >= self.dirs.len() {
+                self.done = true;
+                return Ok(None);
+            }
+            self.next += 1;
+            fill_path(&self.dirs, &self.names, i, &options.root, &mut self.path);
+            if options.log_enabled {
+                eprintln!("Searching in: {}", self.path.display());
+            }
+            // Like in step, deeper folders were checked with their parent.
+            let depth = self.dirs[i].depth;
+            let found = (depth == 1 && self.matches()).then(|| self.path.clone());
+            self.descend(depth)?;
+            return Ok(found);
+        };
+
+        let depth = *depth;
+        let Some(name) = children.next() else {
+            self.stack.pop();
+            // The folder in dirs the search started at stays in path
+            // until it's done.
+            if !self.stack.is_empty() {
+                self.path.pop();
+            }
+            return Ok(None);
+        };
+        self.path.push(&name);
+        if options.log_enabled {
+            eprintln!("Searching in: {}", self.path.display());
+        }
+        let found = self.matches().then(|| self.path.clone());
+        if let Some(found) = &found {
+            if options.log_enabled {
+                eprintln!("Found: {}", found.display());
+            }
+            #[cfg(feature = "tracing")]
+            tracing::debug!(path = %found.display(), "matched");
+        }
+        if !self.descend(depth)? {
+            self.path.pop();
+        }
+        Ok(found)
+    }
+
+    // Reads the children of the folder in path, depth deep, to search
+    // them next in a depth-first search. Returns whether there are any.
+    fn descend(&mut self, depth: u32) -> Result<bool> {
+        let finder = self.finder;
+        let options = &finder.options;
+        if options.max_depth.is_some_and(|max| depth as usize >= max) {
+            skipped(&self.path, "max depth");
+            return Ok(false);
+        }
+        if options.stop_at_projects && is_project(&self.path, &options.markers) {
+            skipped(&self.path, "project");
+            return Ok(false);
+        }
+        let mut files = Vec::new();
+        let wants_files = self.wants_files();
+        let children = finder.entries(
+            &self.path,
+            &mut self.followed,
+            wants_files.then_some(&mut files),
+        )?;
+        self.push_matching_files(files);
+        if children.is_empty() {
+            return Ok(false);
+        }
+        self.stack.push((children.into_iter(), depth + 1));
+        Ok(true)
+    }
+
+    // Reads the children of a folder to search them later, keeping the
+    // ones that match.
+    fn expand(&mut self, i: usize) -> Result<()> {
+        let finder = self.finder;
+        let options = &finder.options;
+        let depth = self.dirs[i].depth;
+        fill_path(&self.dirs, &self.names, i, &options.root, &mut self.path);
+        if options.max_depth.is_some_and(|max| depth as usize >= max) {
+            skipped(&self.path, "max depth");
+            return Ok(());
+        }
+        if options.stop_at_projects && is_project(&self.path, &options.markers) {
+            skipped(&self.path, "project");
+            return Ok(());
+        }
+
+        let mut files = Vec::new();
+        let wants_files = self.wants_files();
+        for name in finder.entries(
+            &self.path,
+            &mut self.followed,
+            wants_files.then_some(&mut files),
+        )? {
+            self.path.push(&name);
+            if self.matches() {
+                if options.log_enabled {
+                    eprintln!("Found: {}", self.path.display());
+                }
+                #[cfg(feature = "tracing")]
+                tracing::debug!(path = %self.path.display(), "matched");
+                self.found.push_back(self.path.clone());
+            }
+            self.path.pop();
+            let name = self.names.intern(name);
+            self.dirs.push(Node {
+                parent: Some(i as u32),
+                name,
+                depth: depth + 1,
+            });
+        }
+        self.push_matching_files(files);
+        Ok(())
+    }
+}
+
+impl Iterator for Matches<'_> {
+    type Item = Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        #[cfg(feature = "tracing")]
+        let _span = self.span.clone().entered();
+        loop {
+            if let Some(dir) = self.found.pop_front() {
+                return Some(Ok(dir));
+            }
+            if self.done {
+                return None;
+            }
+
+            match self.step() {
+                Ok(Some(dir)) => return Some(Ok(dir)),
+                Ok(None) => {}
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+// Returns whether to search inside a link found in parent. Junctions on
+// Windows count as links too, like the legacy "Application Data" ones
+// that point back to the folder they're in: links to the folder they're
+// in, or to any folder above it, would be searched forever.
+fn follow_link(parent: &Path, link: &Path, followed: &mut HashSet<PathBuf>) -> bool {
+    let Ok(target) = link.canonicalize() else {
+        skipped(link, "broken link");
+        return false;
+    };
+    if !target.is_dir() {
+        return false;
+    }
+    if parent
+        .canonicalize()
+        .is_ok_and(|parent| parent.starts_with(&target))
+    {
+        skipped(link, "link cycle");
+        return false;
+    }
+    if !followed.insert(target) {
+        skipped(link, "link already followed");
+        return false;
+    }
+    true
+}
+
+// The kind of an entry of a folder, without following links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum EntryType {
+    Dir,
+    File,
+    Symlink,
+    Other,
+}
+
+// An entry of a folder, read with fs::read_dir or, with the io-uring
+// feature, by uring::Reader.
+pub(crate) trait Entry {
+    fn name(&self) -> OsString;
+    fn entry_type(&self) -> io::Result<EntryType>;
+    // Whether the file system marks it as hidden, whatever its name.
+    fn has_hidden_attribute(&self) -> bool;
+}
+
+impl Entry for fs::DirEntry {
+    fn name(&self) -> OsString {
+        self.file_name()
+    }
+
+    fn entry_type(&self) -> io::Result<EntryType> {
+        let file_type = self.file_type()?;
+        Ok(if file_type.is_symlink() {
+            EntryType::Symlink
+        } else if file_type.is_dir() {
+            EntryType::Dir
+        } else if file_type.is_file() {
+            EntryType::File
+        } else {
+            EntryType::Other
+        })
+    }
+
+    fn has_hidden_attribute(&self) -> bool {
+        has_hidden_attribute(self)
+    }
+}
+
+// Folders are hidden by starting with a dot or, on Windows, by having the
+// hidden attribute, which is how Explorer hides them.
+fn is_hidden(entry: &impl Entry, name: &str) -> bool {
+    name.starts_with('.') || entry.has_hidden_attribute()
+}
+
+#[cfg(windows)]
+fn has_hidden_attribute(entry: &fs::DirEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    entry
+        .metadata()
+        .is_ok_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+#[cfg(not(windows))]
+fn has_hidden_attribute(_: &fs::DirEntry) -> bool {
+    false
+}
+
+// Reports a folder whose children won't be searched, and why.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn skipped(path: &Path, reason: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(path = %path.display(), reason, "skipped");
+}
+
+// Like skipped, only building the path of the entry when it's reported.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn skipped_entry(parent: &Path, name: &OsStr, reason: &str) {
+    #[cfg(feature = "tracing")]
+    skipped(&parent.join(name), reason);
+}
+
+/// Returns whether a folder is a project root, that is, whether it
+/// contains any of the given marker files or folders.
+pub fn is_project(dir: &Path, markers: &[String]) -> bool {
+    markers.iter().any(|marker| dir.join(marker).exists())
+}
+
+// Returns whether a folder has between min and max files. Counting stops
+// as soon as the answer is known, so a huge folder only costs reading
+// max + 1 files, or min files without a maximum.
+fn files_within(dir: &Path, min: Option<usize>, max: Option<usize>) -> bool {
+    let limit = match (min, max) {
+        (_, Some(max)) => max + 1,
+        (Some(min), None) => min,
+        (None, None) => return true,
+    };
+    let files = count_files(dir, limit);
+    min.is_none_or(|min| files >= min) && max.is_none_or(|max| files <= max)
+}
+
+/// Counts the files in a folder and the folders inside it, leaving hidden
+/// folders like `.git` out, and stopping once there are limit of them.
+pub fn count_files(dir: &Path, limit: usize) -> usize {
+    let mut files = 0;
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if files >= limit {
+                return files;
+            }
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => {
+                    if !entry.file_name().to_string_lossy().starts_with('.') {
+                        stack.push(entry.path());
+                    }
+                }
+                Ok(_) => files += 1,
+                Err(_) => {}
+            }
+        }
+    }
+    files
+}
+
+// How deep, how many and how much of the files in a folder contains_text
+// reads, so a folder with a large build output or data set doesn't take
+// long to rule out.
+const CONTENT_DEPTH: usize = 4;
+const CONTENT_FILES: usize = 5000;
+const CONTENT_BYTES: u64 = 1024 * 1024;
+
+/// What [`FinderOptions::contains`] looks for in files.
+#[derive(Debug, Clone)]
+pub enum Content {
+    /// Text, compared byte by byte.
+    Text(String),
+    /// A regular expression, with the syntax of the regex crate. Files
+    /// aren't split in lines, so `^` and `$` match at the start and end
+    /// of the part read, unless the pattern starts with `(?m)`.
+    Regex(Regex),
+}
+
+impl Content {
+    /// Returns whether the contents of a file have the text or match the
+    /// pattern.
+    pub fn is_in(&self, contents: &[u8]) -> bool {
+        match self {
+            Content::Text(text) => {
+                let text = text.as_bytes();
+                text.is_empty() || contents.windows(text.len()).any(|window| window == text)
+            }
+            Content::Regex(regex) => regex.is_match(contents),
+        }
+    }
+}
+
+/// Returns whether a file in a folder has the content. Only the first
+/// megabyte of the first few thousand files, up to 4 folders deep, is
+/// read, skipping hidden folders, the pruned ones and files that look
+/// binary, so it's quick even in a large project.
+pub fn contains_text(dir: &Path, content: &Content, prune: &[String]) -> bool {
+    let mut files = 0;
+    let mut queue = VecDeque::from([(dir.to_path_buf(), 1)]);
+    while let Some((dir, depth)) = queue.pop_front() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if file_type.is_dir() {
+                if depth < CONTENT_DEPTH
+                    && !name.starts_with('.')
+                    && !prune.iter().any(|prune| *prune == name)
+                {
+                    queue.push_back((entry.path(), depth + 1));
+                }
+            } else if file_type.is_file() {
+                files += 1;
+                if files > CONTENT_FILES {
+                    return false;
+                }
+                if file_contains(&entry.path(), content) {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+// Returns whether the start of a file has the content, leaving out files
+// with a NUL byte, which aren't text.
+fn file_contains(path: &Path, content: &Content) -> bool {
+    let mut contents = Vec::new();
+    let read =
+        fs::File::open(path).and_then(|file| file.take(CONTENT_BYTES).read_to_end(&mut contents));
+    read.is_ok() && !contents.contains(&0) && content.is_in(&contents)
+}
+
+// Returns whether a folder is a repository on a branch matching the
+// pattern. Without a pattern, every folder is.
+fn on_branch(dir: &Path, pattern: Option<&str>) -> bool {
+    match pattern {
+        Some(pattern) => vcs::branch(dir).is_some_and(|branch| glob::matches(pattern, &branch)),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_dirs() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            include_vendor: false,
+            include_hidden: false,
+            ..Default::default()
+        });
+
+        // Create some directories
+        fs::create_dir(path.join("dir1"))?;
+        fs::create_dir(path.join("dir2"))?;
+        fs::create_dir(path.join("vendor"))?;
+        fs::create_dir(path.join(".hidden"))?;
+        fs::create_dir(path.join("dir1").join("subdir1"))?;
+
+        let dirs = finder.dirs(path, &mut HashSet::new())?;
+
+        assert_eq!(dirs.len(), 2);
+        let mut names = dirs
+            .iter()
+            .map(|name| name.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["dir1", "dir2"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            include_vendor: false,
+            include_hidden: false,
+            ..Default::default()
+        });
+
+        // Create directories
+        fs::create_dir(path.join("dir1"))?;
+        fs::create_dir(path.join("dir2"))?;
+        fs::create_dir(path.join("dir1").join("target"))?;
+        fs::create_dir(path.join("dir2").join("vendor"))?;
+        fs::create_dir(path.join("dir2").join("vendor").join("target"))?;
+        fs::create_dir(path.join(".hidden"))?;
+
+        let found = finder.find("target")?;
+        assert!(found.is_some());
+        let found_path = found.unwrap();
+        assert_eq!(found_path, path.join("dir1").join("target"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_not_found() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            include_vendor: false,
+            include_hidden: false,
+            ..Default::default()
+        });
+
+        // Create directories
+        fs::create_dir(path.join("dir1"))?;
+        fs::create_dir(path.join("dir2"))?;
+        fs::create_dir(path.join("dir1").join("subdir1"))?;
+        fs::create_dir(path.join("dir2").join("vendor"))?;
+        fs::create_dir(path.join(".hidden"))?;
+
+        let found = finder.find("target")?;
+        assert!(found.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_inside_vendor() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            include_vendor: true,
+            include_hidden: false,
+            ..Default::default()
+        });
+
+        // Create directories
+        fs::create_dir(path.join("dir1"))?;
+        fs::create_dir(path.join("dir2"))?;
+        fs::create_dir(path.join("dir1").join("extra"))?;
+        fs::create_dir(path.join("dir2").join("vendor"))?;
+        fs::create_dir(path.join("dir2").join("vendor").join("target"))?;
+        fs::create_dir(path.join(".hidden"))?;
+
+        let found = finder.find("target")?;
+        assert!(found.is_some());
+        let found_path = found.unwrap();
+        assert_eq!(found_path, path.join("dir2").join("vendor").join("target"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_inside_vendor_disabled() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            include_vendor: false,
+            include_hidden: false,
+            ..Default::default()
+        });
+
+        // Create directories
+        fs::create_dir(path.join("dir1"))?;
+        fs::create_dir(path.join("dir2"))?;
+        fs::create_dir(path.join("dir1").join("extra"))?;
+        fs::create_dir(path.join("dir2").join("vendor"))?;
+        fs::create_dir(path.join("dir2").join("vendor").join("target"))?;
+        fs::create_dir(path.join(".hidden"))?;
+
+        let found = finder.find("target")?;
+        assert!(found.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_inside_hidden() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            include_vendor: false,
+            include_hidden: true,
+            ..Default::default()
+        });
+
+        // Create directories
+        fs::create_dir(path.join("dir1"))?;
+        fs::create_dir(path.join("dir2"))?;
+        fs::create_dir(path.join("dir1").join("extra"))?;
+        fs::create_dir(path.join("dir2").join("vendor"))?;
+        fs::create_dir(path.join("dir2").join("vendor").join("target"))?;
+        fs::create_dir(path.join(".hidden"))?;
+        fs::create_dir(path.join(".hidden").join("target"))?;
+
+        let found = finder.find("target")?;
+        assert!(found.is_some());
+        let found_path = found.unwrap();
+        assert_eq!(found_path, path.join(".hidden").join("target"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_max_depth() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("dir1").join("dir2").join("target"))?;
+
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            max_depth: Some(2),
+            ..Default::default()
+        });
+        assert!(finder.find("target")?.is_none());
+
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            max_depth: Some(3),
+            ..Default::default()
+        });
+        let found = finder.find("target")?;
+        assert_eq!(found, Some(path.join("dir1").join("dir2").join("target")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_projects_only() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("a").join("api"))?;
+        fs::create_dir_all(path.join("b").join("api").join("api"))?;
+        fs::write(path.join("b").join("api").join("go.mod"), "module api")?;
+        fs::write(path.join("b").join("api").join("api").join("go.mod"), "")?;
+
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            markers: vec!["go.mod".to_string()],
+            projects_only: true,
+            ..Default::default()
+        });
+        let found = finder.find("api")?;
+        assert_eq!(found, Some(path.join("b").join("api")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_stop_at_projects() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("project").join(".git"))?;
+        fs::create_dir_all(path.join("project").join("src").join("target"))?;
+
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            include_hidden: true,
+            markers: vec![".git".to_string()],
+            stop_at_projects: true,
+            ..Default::default()
+        });
+        assert!(finder.find("target")?.is_none());
+        assert!(finder.find("project")?.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_all() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("api").join("nested").join("api"))?;
+        fs::create_dir_all(path.join("work").join("api"))?;
+        fs::create_dir_all(path.join("web"))?;
+
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            sort_alphabetically: true,
+            ..Default::default()
+        });
+        let found = finder.find_all("api")?;
+        assert_eq!(
+            found,
+            vec![
+                path.join("api"),
+                path.join("work").join("api"),
+                path.join("api").join("nested").join("api"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_all_projects() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("api").join(".git"))?;
+        fs::create_dir_all(path.join("notes"))?;
+        fs::create_dir_all(path.join("work").join("web").join(".git"))?;
+
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            sort_alphabetically: true,
+            markers: vec![".git".to_string()],
+            ..Default::default()
+        });
+        let found = finder.find_all("")?;
+        assert_eq!(found, vec![path.join("api"), path.join("work").join("web")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_skip_worktrees() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        let worktree = path.join("hotfix").join("api");
+        fs::create_dir_all(path.join("main").join("api").join(".git/worktrees/api"))?;
+        fs::write(
+            path.join("main/api/.git/worktrees/api/commondir"),
+            "../..\n",
+        )?;
+        fs::create_dir_all(&worktree)?;
+        fs::write(
+            worktree.join(".git"),
+            "gitdir: ../../main/api/.git/worktrees/api\n",
+        )?;
+
+        let mut options = FinderOptions {
+            root: path.to_path_buf(),
+            sort_alphabetically: true,
+            markers: vec![".git".to_string()],
+            projects_only: true,
+            ..Default::default()
+        };
+        assert_eq!(Finder::new(options.clone()).find("api")?, Some(worktree));
+
+        options.skip_worktrees = true;
+        assert_eq!(
+            Finder::new(options).find("api")?,
+            Some(path.join("main").join("api"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_branch() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        for (parent, branch) in [("a", "main"), ("b", "master"), ("c", "release-2")] {
+            let git_dir = path.join(parent).join("api").join(".git");
+            fs::create_dir_all(&git_dir)?;
+            fs::write(
+                git_dir.join("HEAD"),
+                format!("ref: refs/heads/{}\n", branch),
+            )?;
+        }
+
+        let options = FinderOptions {
+            root: path.to_path_buf(),
+            sort_alphabetically: true,
+            branch: Some("master".to_string()),
+            ..Default::default()
+        };
+        let found = Finder::new(options.clone()).find_all("api")?;
+        assert_eq!(found, vec![path.join("b").join("api")]);
+
+        let finder = Finder::new(FinderOptions {
+            branch: Some("release-*".to_string()),
+            ..options
+        });
+        let found = finder.find_all("api")?;
+        assert_eq!(found, vec![path.join("c").join("api")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_by_remote() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        let repo = path.join("work").join("autoscaler-fork");
+        fs::create_dir_all(repo.join(".git"))?;
+        fs::write(
+            repo.join(".git").join("config"),
+            "[remote \"origin\"]\n\turl = git@github.com:kubernetes/autoscaler.git\n",
+        )?;
+        fs::create_dir_all(path.join("autoscaler"))?;
+
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            ..Default::default()
+        });
+        let found = finder.find("github.com/kubernetes/autoscaler")?;
+        assert_eq!(found, Some(repo));
+
+        let found = finder.find("gitlab.com/kubernetes/autoscaler")?;
+        assert_eq!(found, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dirs_exclude() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("legacy-billing"))?;
+        fs::create_dir_all(path.join("billing"))?;
+
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            exclude: vec!["legacy-*".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            finder.dirs(path, &mut HashSet::new())?,
+            vec![OsString::from("billing")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_inside_hidden_disabled() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            include_vendor: false,
+            include_hidden: false,
+            ..Default::default()
+        });
+
+        // Create directories
+        fs::create_dir(path.join("dir1"))?;
+        fs::create_dir(path.join("dir2"))?;
+        fs::create_dir(path.join("dir1").join("extra"))?;
+        fs::create_dir(path.join("dir2").join("vendor"))?;
+        fs::create_dir(path.join("dir2").join("vendor").join("target"))?;
+        fs::create_dir(path.join(".hidden"))?;
+        fs::create_dir(path.join(".hidden").join("target"))?;
+
+        let found = finder.find("target")?;
+        assert!(found.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("a").join("api"))?;
+        fs::create_dir_all(path.join("b").join("api"))?;
+        fs::create_dir_all(path.join("c").join("api"))?;
+
+        let finder = Finder::new(FinderOptions {
+            root: path.to_path_buf(),
+            sort_alphabetically: true,
+            ..Default::default()
+        });
+        let mut matches = finder.iter("api");
+        assert_eq!(
+            matches.next().transpose()?,
+            Some(path.join("a").join("api"))
+        );
+
+        // Folders are only read when the iterator gets to them, so
+        // removing one not searched yet doesn't fail.
+        fs::remove_dir_all(path.join("c"))?;
+        let rest = matches.take(1).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(rest, vec![path.join("b").join("api")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_matcher() {
+        assert!(Matcher::Exact.matches("api", "api"));
+        assert!(!Matcher::Exact.matches("api", "API"));
+        assert!(Matcher::Glob.matches("api-*", "api-gateway"));
+        assert!(!Matcher::Glob.matches("api-*", "web-api"));
+        assert!(Matcher::Fuzzy.matches("k8sctl", "K8s-Control"));
+        assert!(!Matcher::Fuzzy.matches("ctlk8s", "k8s-control"));
+        assert!(Matcher::Fuzzy.matches("", "api"));
+        assert!(!Matcher::Fuzzy.matches("apii", "api"));
+        assert!(Matcher::Fuzzy.matches("ÜBER", "über-app"));
+        assert!(Matcher::Fuzzy.matches("kelvin", "\u{212a}elvin"));
+        assert!(!Matcher::Fuzzy.matches("café", "cafe"));
+
+        assert_eq!(Matcher::Exact.score("api", "api"), Some(0));
+        assert_eq!(Matcher::Fuzzy.score("k8sctl", "k8s-api"), None);
+        assert!(
+            Matcher::Fuzzy.score("k8s", "k8s-control")
+                > Matcher::Fuzzy.score("k8s", "kube-system-8s")
+        );
+
+        // A scorer gives the same scores for every folder it scores.
+        let mut scorer = FuzzyScorer::new("k8s");
+        for folder in ["k8s-control", "api", "kube-system-8s"] {
+            assert_eq!(scorer.score(folder), Matcher::Fuzzy.score("k8s", folder));
+        }
+    }
+
+    #[test]
+    fn test_find_fuzzy() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("acme").join("autoscaler"))?;
+        fs::create_dir_all(path.join(".cache").join("autoscaler-old"))?;
+
+        let options = FinderOptions::new(path).matcher(Matcher::Fuzzy);
+        let finder = Finder::new(options.clone());
+        assert_eq!(
+            finder.find_all("autosc")?,
+            vec![path.join("acme").join("autoscaler")]
+        );
+
+        let finder = Finder::new(options.include_hidden(true).max_depth(1));
+        assert!(finder.find("autosc")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_walk() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("acme").join("api").join("src"))?;
+        fs::create_dir_all(path.join("acme").join("web"))?;
+        fs::create_dir_all(path.join("tools").join("cli"))?;
+        fs::write(path.join("acme").join("api").join("LICENSE"), "MIT")?;
+        fs::write(path.join("tools").join("cli").join("LICENSE"), "GPL")?;
+
+        // Collects the licensed folders, without looking inside them.
+        let finder = Finder::new(FinderOptions::new(path).sort_alphabetically(true));
+        let mut licensed = Vec::new();
+        let stopped = finder.walk(|dir| {
+            if dir.join("LICENSE").is_file() {
+                licensed.push(dir.to_path_buf());
+                return ControlFlow::<(), _>::Continue(Visit::Skip);
+            }
+            ControlFlow::Continue(Visit::Descend)
+        })?;
+        assert_eq!(stopped, None);
+        assert_eq!(
+            licensed,
+            vec![
+                path.join("acme").join("api"),
+                path.join("tools").join("cli")
+            ]
+        );
+
+        // Stops at the first GPL licensed folder.
+        let gpl = finder.walk(|dir| match fs::read_to_string(dir.join("LICENSE")) {
+            Ok(license) if license == "GPL" => ControlFlow::Break(dir.to_path_buf()),
+            _ => ControlFlow::Continue(Visit::Descend),
+        })?;
+        assert_eq!(gpl, Some(path.join("tools").join("cli")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_errors() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        let finder = Finder::new(FinderOptions::default());
+        assert!(matches!(
+            finder.find("api"),
+            Err(FindError::NoRootConfigured)
+        ));
+
+        let finder = Finder::new(FinderOptions::new(path.join("missing")));
+        match finder.find("api") {
+            Err(FindError::RootNotAccessible { path: root, .. }) => {
+                assert_eq!(root, path.join("missing"))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_follow_links() -> Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("acme").join("api"))?;
+        fs::create_dir_all(path.join("links"))?;
+        symlink(path.join("acme"), path.join("links").join("acme"))?;
+        // Like the legacy junctions on Windows, pointing to their parent.
+        symlink(path.join("acme"), path.join("acme").join("loop"))?;
+
+        let options = FinderOptions::new(path).sort_alphabetically(true);
+        let finder = Finder::new(options.clone());
+        assert_eq!(finder.find_all("api")?, vec![path.join("acme").join("api")]);
+
+        let finder = Finder::new(options.follow_links(true));
+        assert_eq!(
+            finder.find_all("api")?,
+            vec![
+                path.join("acme").join("api"),
+                path.join("links").join("acme").join("api")
+            ]
+        );
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_find_hidden_attribute() -> Result<()> {
+        use std::process::Command;
+
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("AppData").join("api"))?;
+        let status = Command::new("attrib")
+            .arg("+h")
+            .arg(path.join("AppData"))
+            .status()?;
+        assert!(status.success());
+
+        let options = FinderOptions::new(path);
+        assert!(Finder::new(options.clone()).find("api")?.is_none());
+        assert_eq!(
+            Finder::new(options.include_hidden(true)).find("api")?,
+            Some(path.join("AppData").join("api"))
+        );
+        Ok(())
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_find_hidden_attribute_ignored() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        // Only the name hides folders outside of Windows.
+        fs::create_dir_all(path.join("AppData").join("api"))?;
+        assert_eq!(
+            Finder::new(FinderOptions::new(path)).find("api")?,
+            Some(path.join("AppData").join("api"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_ignore_case() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("acme").join("MyProj"))?;
+
+        let options = FinderOptions::new(path);
+        assert!(Finder::new(options.clone()).find("myproj")?.is_none());
+
+        // The folder is returned with the case it has on disk.
+        let finder = Finder::new(options.ignore_case(true));
+        for name in ["myproj", "MYPROJ", "MyProj"] {
+            assert_eq!(
+                finder.find(name)?,
+                Some(path.join("acme").join("MyProj")),
+                "{}",
+                name
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_cancel() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("a").join("api"))?;
+        fs::create_dir_all(path.join("b").join("api"))?;
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let finder = Finder::new(
+            FinderOptions::new(path)
+                .sort_alphabetically(true)
+                .cancel(cancel.clone()),
+        );
+        let mut matches = finder.iter("api");
+        assert_eq!(
+            matches.next().transpose()?,
+            Some(path.join("a").join("api"))
+        );
+
+        cancel.store(true, Ordering::Relaxed);
+        assert!(matches!(matches.next(), Some(Err(FindError::Cancelled))));
+        assert!(matches.next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_names() {
+        let mut names = Names::default();
+        let src = names.intern(OsString::from("src"));
+        let docs = names.intern(OsString::from("docs"));
+        assert_eq!(names.intern(OsString::from("src")), src);
+        assert_ne!(src, docs);
+        assert_eq!(names.get(src), "src");
+        assert_eq!(names.get(docs), "docs");
+        assert_eq!(std::mem::size_of::<Node>(), 16);
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    fn test_find_io_uring() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("a").join("web"))?;
+        fs::create_dir_all(path.join("b").join("c").join("api"))?;
+        fs::create_dir_all(path.join("d").join("api"))?;
+        fs::create_dir_all(path.join("project").join("cli"))?;
+        fs::create_dir(path.join("project").join(".git"))?;
+        fs::create_dir_all(path.join("e").join("f").join("docker-compose.yml"))?;
+        fs::write(path.join("d").join("docker-compose.yml"), "")?;
+        std::os::unix::fs::symlink(path.join("b").join("c"), path.join("link"))?;
+        for i in 0..300 {
+            fs::create_dir_all(path.join(format!("x{}", i)).join("y").join("z"))?;
+        }
+
+        let options = FinderOptions::new(path)
+            .sort_alphabetically(true)
+            .marker(".git");
+        for options in [
+            options.clone(),
+            options.clone().max_depth(2),
+            options.clone().stop_at_projects(true),
+            options.clone().follow_links(true),
+            options.clone().kind(Kind::File),
+        ] {
+            let finder = Finder::new(options.clone());
+            let uring = Finder::new(options.io_uring(true));
+            for name in ["web", "api", "cli", "z", "docker-compose.yml", "missing"] {
+                assert_eq!(uring.find(name)?, finder.find(name)?, "{}", name);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_max_memory() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("api"))?;
+        fs::create_dir_all(path.join("a").join("b").join("c").join("api"))?;
+        fs::create_dir_all(path.join("d").join("api").join("api"))?;
+        fs::create_dir_all(path.join("project").join("api"))?;
+        fs::create_dir(path.join("project").join(".git"))?;
+
+        let options = FinderOptions::new(path)
+            .sort_alphabetically(true)
+            .marker(".git");
+        for options in [
+            options.clone(),
+            options.clone().max_depth(3),
+            options.clone().stop_at_projects(true),
+        ] {
+            let mut found = Finder::new(options.clone()).find_all("api")?;
+            // Past the limit right away, so every folder but the ones
+            // right inside the root is searched depth-first.
+            let mut depth_first = Finder::new(options.max_memory(1)).find_all("api")?;
+            found.sort();
+            depth_first.sort();
+            assert_eq!(depth_first, found);
+        }
+
+        // "a" is read before the search goes depth-first, so its children
+        // are searched after the folders right inside the root, and each
+        // of those down to the bottom.
+        let finder = Finder::new(
+            FinderOptions::new(path)
+                .sort_alphabetically(true)
+                .max_memory(1),
+        );
+        assert_eq!(
+            finder.find_all("api")?,
+            [
+                path.join("api"),
+                path.join("d").join("api"),
+                path.join("d").join("api").join("api"),
+                path.join("project").join("api"),
+                path.join("a").join("b").join("c").join("api"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_find_parallel() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("a").join("web"))?;
+        fs::create_dir_all(path.join("web"))?;
+        fs::create_dir_all(path.join("b").join("c").join("api"))?;
+        fs::create_dir_all(path.join("d").join("api"))?;
+        fs::create_dir_all(path.join("e").join("f").join("g").join("docs"))?;
+        fs::create_dir_all(path.join("project").join("cli"))?;
+        fs::create_dir(path.join("project").join(".git"))?;
+        for i in 0..20 {
+            fs::create_dir_all(path.join(format!("x{}", i)).join("y").join("z"))?;
+        }
+
+        let options = FinderOptions::new(path)
+            .sort_alphabetically(true)
+            .marker(".git");
+        for options in [
+            options.clone(),
+            options.clone().max_depth(2),
+            options.clone().stop_at_projects(true),
+        ] {
+            let finder = Finder::new(options.clone());
+            let parallel = Finder::new(options.threads(4));
+            for name in ["web", "api", "docs", "cli", "z", "missing"] {
+                assert_eq!(parallel.find(name)?, finder.find(name)?, "{}", name);
+            }
+        }
+
+        let cancel = Arc::new(AtomicBool::new(true));
+        let finder = Finder::new(FinderOptions::new(path).threads(4).cancel(cancel));
+        assert!(matches!(finder.find("api"), Err(FindError::Cancelled)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_files() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("app").join("deploy"))?;
+        fs::create_dir_all(path.join("api").join("docker-compose.yml"))?;
+        fs::create_dir_all(path.join("web").join(".hidden"))?;
+        fs::write(
+            path.join("app").join("deploy").join("docker-compose.yml"),
+            "",
+        )?;
+        fs::write(path.join("web").join("docker-compose.yml"), "")?;
+        fs::write(
+            path.join("web").join(".hidden").join("docker-compose.yml"),
+            "",
+        )?;
+
+        let options = FinderOptions::new(path).sort_alphabetically(true);
+        let dirs = Finder::new(options.clone());
+        let files = Finder::new(options.clone().kind(Kind::File));
+        let any = Finder::new(options.clone().kind(Kind::Any));
+        assert_eq!(
+            dirs.find_all("docker-compose.yml")?,
+            vec![path.join("api").join("docker-compose.yml")]
+        );
+        assert_eq!(
+            files.find_all("docker-compose.yml")?,
+            vec![
+                path.join("web").join("docker-compose.yml"),
+                path.join("app").join("deploy").join("docker-compose.yml"),
+            ]
+        );
+        assert_eq!(
+            any.find_all("docker-compose.yml")?,
+            vec![
+                path.join("api").join("docker-compose.yml"),
+                path.join("web").join("docker-compose.yml"),
+                path.join("app").join("deploy").join("docker-compose.yml"),
+            ]
+        );
+
+        // Files right inside the root match too, and searching
+        // depth-first or with threads finds them the same way.
+        fs::write(path.join("docker-compose.yml"), "")?;
+        for options in [
+            options.clone().kind(Kind::File),
+            options.clone().kind(Kind::File).threads(4),
+            options.clone().kind(Kind::File).max_memory(1),
+        ] {
+            assert_eq!(
+                Finder::new(options).find("docker-compose.yml")?,
+                Some(path.join("docker-compose.yml"))
+            );
+        }
+        fs::remove_file(path.join("docker-compose.yml"))?;
+        assert_eq!(
+            Finder::new(options.clone().kind(Kind::File).threads(4)).find("docker-compose.yml")?,
+            Some(path.join("web").join("docker-compose.yml"))
+        );
+        // Depth-first, each folder is searched down to the bottom first.
+        assert_eq!(
+            Finder::new(options.kind(Kind::File).max_memory(1)).find_all("docker-compose.yml")?,
+            vec![
+                path.join("app").join("deploy").join("docker-compose.yml"),
+                path.join("web").join("docker-compose.yml"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_manifest() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("old-name").join(".git"))?;
+        fs::create_dir_all(path.join("mod").join(".git"))?;
+        fs::write(
+            path.join("old-name").join("go.mod"),
+            "module github.com/org/mod\n",
+        )?;
+        fs::write(
+            path.join("mod").join("go.mod"),
+            "module github.com/org/other\n",
+        )?;
+
+        let finder = Finder::new(
+            FinderOptions::new(path)
+                .sort_alphabetically(true)
+                .manifest(Manifest::GoModule),
+        );
+        assert_eq!(
+            finder.find("github.com/org/mod")?,
+            Some(path.join("old-name"))
+        );
+        assert_eq!(finder.find("mod")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_contains() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        let events = path.join("one").join("app").join("src").join("events");
+        fs::create_dir_all(&events)?;
+        fs::create_dir_all(path.join("two").join("app").join("node_modules"))?;
+        fs::create_dir_all(path.join("three").join("app"))?;
+        fs::write(events.join("topics.yml"), "consume: payments.settled.v2\n")?;
+        fs::write(
+            path.join("two")
+                .join("app")
+                .join("node_modules")
+                .join("fixture.json"),
+            "payments.settled.v2",
+        )?;
+        fs::write(
+            path.join("three").join("app").join("dump.bin"),
+            b"\0payments.settled.v2",
+        )?;
+
+        let options = FinderOptions::new(path)
+            .prune("node_modules")
+            .contains("payments.settled.v2");
+        assert_eq!(
+            Finder::new(options.clone()).find_all("app")?,
+            vec![path.join("one").join("app")]
+        );
+        assert_eq!(
+            Finder::new(options.kind(Kind::File)).find_all("topics.yml")?,
+            vec![events.join("topics.yml")]
+        );
+
+        let options = FinderOptions::new(path)
+            .prune("node_modules")
+            .contains_regex(Regex::new(r"(?m)^consume: payments\.\w+\.v\d+$")?);
+        assert_eq!(
+            Finder::new(options.clone()).find_all("app")?,
+            vec![path.join("one").join("app")]
+        );
+        let options = options.contains_regex(Regex::new(r"payments\.refunded")?);
+        assert!(Finder::new(options).find_all("app")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_file_count() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("empty").join("app").join(".git"))?;
+        fs::write(path.join("empty").join("app").join(".git").join("HEAD"), "")?;
+        let nested = path.join("real").join("app").join("src");
+        fs::create_dir_all(&nested)?;
+        for i in 0..3 {
+            fs::write(nested.join(format!("{}.rs", i)), "")?;
+        }
+        let vendored = path.join("huge").join("app");
+        fs::create_dir_all(&vendored)?;
+        for i in 0..50 {
+            fs::write(vendored.join(format!("{}.js", i)), "")?;
+        }
+
+        assert_eq!(count_files(&vendored, 10), 10);
+        assert_eq!(count_files(&path.join("empty").join("app"), 10), 0);
+        let finder = Finder::new(
+            FinderOptions::new(path)
+                .sort_alphabetically(true)
+                .min_files(1)
+                .max_files(20),
+        );
+        assert_eq!(finder.find_all("app")?, vec![path.join("real").join("app")]);
+        Ok(())
+    }
+}
@@ -0,0 +1,364 @@
+use crate::error::{FindError, Result};
+use crate::{paths, store};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// What to rank previously visited directories by.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum RankBy {
+    /// Visit count weighted by how recently the directory was visited
+    #[default]
+    Frecency,
+    /// Most recently visited first
+    Recency,
+    /// Most visited first
+    Frequency,
+}
+
+/// Parameters of the frecency algorithm.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ranking {
+    pub rank_by: RankBy,
+    /// Seconds after which the weight of past visits is halved.
+    pub half_life: u64,
+    /// How much each visit is worth.
+    pub visit_weight: f64,
+    /// Multiplier applied to directories visited within the last hour.
+    pub recency_boost: f64,
+}
+
+impl Default for Ranking {
+    fn default() -> Self {
+        Ranking {
+            rank_by: RankBy::Frecency,
+            half_life: WEEK,
+            visit_weight: 1.0,
+            recency_boost: 2.0,
+        }
+    }
+}
+
+impl Ranking {
+    /// Scores an entry: higher is better.
+    pub fn score(&self, entry: &Entry, now: u64) -> f64 {
+        let age = now.saturating_sub(entry.last_visit);
+        match self.rank_by {
+            RankBy::Recency => entry.last_visit as f64,
+            RankBy::Frequency => entry.visits,
+            RankBy::Frecency => {
+                // Past visits decay exponentially so projects used a lot
+                // in the past slowly give way to the ones being worked
+                // on today.
+                let decay = 0.5f64.powf(age as f64 / self.half_life.max(1) as f64);
+                let boost = if age < HOUR { self.recency_boost } else { 1.0 };
+                entry.visits * self.visit_weight * decay * boost
+            }
+        }
+    }
+}
+
+/// A project directory that has been jumped to before.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub visits: f64,
+    pub last_visit: u64,
+}
+
+/// The frecency database: every directory find-project printed, with
+/// its visit count and the time of the last visit.
+pub struct Database {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl Database {
+    /// Loads the database stored in the data directory.
+    pub fn load() -> Result<Self> {
+        Self::load_from(store::data_dir()?.join("frecency"))
+    }
+
+    pub fn load_from(path: PathBuf) -> Result<Self> {
+        let mut entries = Vec::new();
+        for line in store::read_lines(&path)? {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(visits), Some(last_visit), Some(dir)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+
+            entries.push(Entry {
+                path: PathBuf::from(dir),
+                visits: visits.parse().map_err(|_| {
+                    FindError::Invalid(format!("Invalid visit count in {:?}", path))
+                })?,
+                last_visit: last_visit
+                    .parse()
+                    .map_err(|_| FindError::Invalid(format!("Invalid timestamp in {:?}", path)))?,
+            });
+        }
+        Ok(Database { path, entries })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::write_lines(
+            &self.path,
+            self.entries
+                .iter()
+                .map(|e| format!("{}\t{}\t{}", e.visits, e.last_visit, e.path.display())),
+        )
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the entry of a directory, if it was visited before.
+    pub fn entry(&self, dir: &Path) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.path == dir)
+    }
+
+    /// Records a visit to a directory.
+    pub fn record(&mut self, dir: &Path, now: u64) {
+        self.merge(dir, 1.0, now);
+    }
+
+    /// Adds visits to a directory, keeping the most recent visit time.
+    pub fn merge(&mut self, dir: &Path, visits: f64, last_visit: u64) {
+        match self.entries.iter_mut().find(|e| e.path == dir) {
+            Some(entry) => {
+                entry.visits += visits;
+                entry.last_visit = entry.last_visit.max(last_visit);
+            }
+            None => self.entries.push(Entry {
+                path: dir.to_path_buf(),
+                visits,
+                last_visit,
+            }),
+        }
+    }
+
+    /// Moves the entries of a directory, and of the ones inside it, to
+    /// where the directory was moved.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        for entry in &mut self.entries {
+            if let Some(path) = paths::moved(&entry.path, from, to) {
+                entry.path = path;
+            }
+        }
+    }
+
+    /// Removes the entry of a directory, returning whether there was one.
+    pub fn forget(&mut self, dir: &Path) -> bool {
+        let count = self.entries.len();
+        self.entries.retain(|e| e.path != dir);
+        self.entries.len() != count
+    }
+
+    /// Returns the highest-scoring directory named `name` under `roots`
+    /// that still exists on disk.
+    pub fn best(
+        &self,
+        name: &str,
+        ignore_case: bool,
+        roots: &[PathBuf],
+        ranking: &Ranking,
+        now: u64,
+    ) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .filter(|e| paths::same_name(&e.path, name, ignore_case))
+            .filter(|e| roots.iter().any(|root| e.path.starts_with(root)) && e.path.is_dir())
+            .max_by(|a, b| ranking.score(a, now).total_cmp(&ranking.score(b, now)))
+    }
+
+    /// Returns up to `count` directories that still exist on disk, with
+    /// their scores, highest first.
+    pub fn top(&self, ranking: &Ranking, now: u64, count: usize) -> Vec<(&Entry, f64)> {
+        let mut ranked: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|e| e.path.is_dir())
+            .map(|e| (e, ranking.score(e, now)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.truncate(count);
+        ranked
+    }
+}
+
+/// Returns the current time as seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_database_roundtrip() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        let mut db = Database::load_from(path.join("frecency"))?;
+        db.record(&path.join("one"), 100);
+        db.record(&path.join("one"), 200);
+        db.merge(&path.join("two"), 3.5, 50);
+        db.save()?;
+
+        let db = Database::load_from(path.join("frecency"))?;
+        assert_eq!(
+            db.entries(),
+            &[
+                Entry {
+                    path: path.join("one"),
+                    visits: 2.0,
+                    last_visit: 200,
+                },
+                Entry {
+                    path: path.join("two"),
+                    visits: 3.5,
+                    last_visit: 50,
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        let mut db = Database::load_from(path.join("frecency"))?;
+        db.record(&path.join("api"), 100);
+        db.record(&path.join("api").join("docs"), 100);
+        db.record(&path.join("api-old"), 100);
+        db.rename(&path.join("api"), &path.join("github.com").join("api"));
+
+        let paths: Vec<_> = db.entries().iter().map(|e| e.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                path.join("github.com").join("api"),
+                path.join("github.com").join("api").join("docs"),
+                path.join("api-old"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_forget() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        let mut db = Database::load_from(path.join("frecency"))?;
+        db.record(&path.join("api"), 100);
+        db.record(&path.join("web"), 100);
+        assert!(db.forget(&path.join("api")));
+        assert!(!db.forget(&path.join("api")));
+        assert_eq!(db.entries().len(), 1);
+        assert_eq!(db.entries()[0].path, path.join("web"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_best_prefers_recent_visits() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+        fs::create_dir_all(path.join("old").join("api"))?;
+        fs::create_dir_all(path.join("new").join("api"))?;
+
+        let now = 10 * WEEK;
+        let mut db = Database::load_from(path.join("frecency"))?;
+        db.merge(&path.join("old").join("api"), 10.0, now - 2 * WEEK);
+        db.merge(&path.join("new").join("api"), 2.0, now - HOUR / 2);
+        db.merge(&path.join("missing").join("api"), 100.0, now);
+
+        let ranking = Ranking::default();
+        let roots = [path.to_path_buf()];
+        let best = db
+            .best("api", false, &roots, &ranking, now)
+            .map(|e| e.path.clone());
+        assert_eq!(best, Some(path.join("new").join("api")));
+        assert!(db
+            .best("api", false, &[path.join("other")], &ranking, now)
+            .is_none());
+
+        let ranking = Ranking {
+            rank_by: RankBy::Frequency,
+            ..Default::default()
+        };
+        let best = db
+            .best("api", false, &roots, &ranking, now)
+            .map(|e| e.path.clone());
+        assert_eq!(best, Some(path.join("old").join("api")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_top() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+        for name in ["a", "b", "c"] {
+            fs::create_dir(path.join(name))?;
+        }
+
+        let mut db = Database::load_from(path.join("frecency"))?;
+        db.merge(&path.join("a"), 1.0, 0);
+        db.merge(&path.join("b"), 3.0, 0);
+        db.merge(&path.join("c"), 2.0, 0);
+        db.merge(&path.join("missing"), 10.0, 0);
+
+        let top: Vec<_> = db
+            .top(&Ranking::default(), 0, 2)
+            .into_iter()
+            .map(|(e, _)| e.path.clone())
+            .collect();
+        assert_eq!(top, vec![path.join("b"), path.join("c")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_frecency_decay() {
+        let entry = Entry {
+            path: PathBuf::from("api"),
+            visits: 8.0,
+            last_visit: 0,
+        };
+        let ranking = Ranking {
+            half_life: DAY,
+            visit_weight: 2.0,
+            recency_boost: 3.0,
+            ..Default::default()
+        };
+
+        assert_eq!(ranking.score(&entry, 0), 48.0);
+        assert_eq!(ranking.score(&entry, DAY), 8.0);
+        assert_eq!(ranking.score(&entry, 2 * DAY), 4.0);
+    }
+}
@@ -0,0 +1,468 @@
+use crate::error::{FindError, Result};
+use crate::paths;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The state of a repository's working copy.
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Status {
+    /// Checked out branch, or None when HEAD is detached.
+    pub branch: Option<String>,
+    /// Commits not pushed to the upstream branch.
+    pub ahead: u64,
+    /// Commits in the upstream branch not merged yet.
+    pub behind: u64,
+    /// Whether there are uncommitted or untracked changes.
+    pub dirty: bool,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.branch.as_deref().unwrap_or("(detached)"))?;
+        if self.ahead > 0 {
+            write!(f, " +{}", self.ahead)?;
+        }
+        if self.behind > 0 {
+            write!(f, " -{}", self.behind)?;
+        }
+        if self.dirty {
+            f.write_str(" dirty")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the `.git` folder of a repository root, or None when the folder
+/// isn't one. Linked worktrees and submodules have a `.git` file pointing
+/// to their folder inside the main repository instead, which is followed.
+pub fn git_dir(repo: &Path) -> Option<PathBuf> {
+    let dir = repo.join(".git");
+    if dir.is_dir() {
+        return Some(dir);
+    }
+
+    let contents = fs::read_to_string(&dir).ok()?;
+    let target = contents.trim().strip_prefix("gitdir:")?.trim();
+    Some(repo.join(target)).filter(|dir| dir.is_dir())
+}
+
+// Returns the folder holding what's shared by all the worktrees of a
+// repository, like its config, which is the git folder itself unless
+// it's the one of a linked worktree.
+fn common_dir(git_dir: &Path) -> PathBuf {
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(common) => git_dir.join(common.trim()),
+        Err(_) => git_dir.to_path_buf(),
+    }
+}
+
+/// Returns the main worktree of a repository root when it's a linked
+/// worktree created with `git worktree add`, or None otherwise.
+pub fn main_worktree(repo: &Path) -> Option<PathBuf> {
+    let git_dir = git_dir(repo)?;
+    if !git_dir.join("commondir").is_file() {
+        return None;
+    }
+    let common = paths::canonicalize(&common_dir(&git_dir)).ok()?;
+    common.parent().map(Path::to_path_buf)
+}
+
+/// Returns the branch checked out in a repository root by reading its
+/// HEAD, or None when HEAD is detached or the folder isn't one.
+pub fn head_branch(repo: &Path) -> Option<String> {
+    let head = fs::read_to_string(git_dir(repo)?.join("HEAD")).ok()?;
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(String::from)
+}
+
+/// Returns the status of a repository root by running `git status`, or
+/// None when the folder isn't one.
+pub fn status(repo: &Path) -> Result<Option<Status>> {
+    if git_dir(repo).is_none() {
+        return Ok(None);
+    }
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["status", "--porcelain=v2", "--branch"])
+        .output()
+        .map_err(not_installed)?;
+    if !output.status.success() {
+        return Err(failed("git status", repo, &output.stderr));
+    }
+    Ok(Some(parse_status(&String::from_utf8_lossy(&output.stdout))))
+}
+
+// Parses the output of "git status --porcelain=v2 --branch", where
+// headers start with "#" and every other line is a changed file.
+fn parse_status(output: &str) -> Status {
+    let mut status = Status::default();
+    for line in output.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            status.branch = Some(head.to_string()).filter(|head| head != "(detached)");
+        } else if let Some(counts) = line.strip_prefix("# branch.ab ") {
+            for count in counts.split_whitespace() {
+                if let Some(ahead) = count.strip_prefix('+') {
+                    status.ahead = ahead.parse().unwrap_or_default();
+                } else if let Some(behind) = count.strip_prefix('-') {
+                    status.behind = behind.parse().unwrap_or_default();
+                }
+            }
+        } else if !line.starts_with('#') && !line.is_empty() {
+            status.dirty = true;
+        }
+    }
+    status
+}
+
+/// Returns when the commit checked out in a repository root was made, in
+/// seconds since the Unix epoch, or None when the folder isn't one or
+/// has no commits yet.
+pub fn last_commit_time(repo: &Path) -> Result<Option<u64>> {
+    if git_dir(repo).is_none() {
+        return Ok(None);
+    }
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["log", "-1", "--format=%ct"])
+        .output()
+        .map_err(not_installed)?;
+    // Fails in repositories without commits, which are just never sorted
+    // before anything else.
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+}
+
+/// Runs `git fetch --prune` in a repository root, killing it if it takes
+/// longer than the timeout, and returns the remote branches that changed,
+/// like "origin/main".
+pub fn fetch(repo: &Path, timeout: Duration) -> Result<Vec<String>> {
+    let before = remote_refs(repo)?;
+
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["fetch", "--prune", "--quiet"])
+        // Fail instead of waiting for credentials nobody will type.
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(not_installed)?;
+
+    // Read stderr as it comes, so git never blocks on a full pipe.
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let errors = thread::spawn(move || {
+        let mut errors = String::new();
+        let _ = stderr.read_to_string(&mut errors);
+        errors
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(not_installed)? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(FindError::Timeout {
+                command: "git fetch".to_string(),
+                seconds: timeout.as_secs(),
+            });
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    let errors = errors.join().unwrap_or_default();
+    if !status.success() {
+        return Err(failed("git fetch", repo, errors.as_bytes()));
+    }
+
+    let after = remote_refs(repo)?;
+    Ok(after
+        .into_iter()
+        .filter(|(name, id)| before.get(name) != Some(id))
+        .map(|(name, _)| name)
+        .collect())
+}
+
+// Returns the commit every remote branch points to, by name.
+fn remote_refs(repo: &Path) -> Result<BTreeMap<String, String>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args([
+            "for-each-ref",
+            "--format=%(objectname) %(refname)",
+            "refs/remotes",
+        ])
+        .output()
+        .map_err(not_installed)?;
+    if !output.status.success() {
+        return Err(failed("git for-each-ref", repo, &output.stderr));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (id, name) = line.split_once(' ')?;
+            let name = name.strip_prefix("refs/remotes/").unwrap_or(name);
+            // Skips origin/HEAD, which only follows another branch.
+            if name.ends_with("/HEAD") {
+                return None;
+            }
+            Some((name.to_string(), id.to_string()))
+        })
+        .collect())
+}
+
+// Returns the error of git failing to start or to be waited for, which
+// nearly always means it isn't installed.
+fn not_installed(source: io::Error) -> FindError {
+    FindError::Command {
+        program: "git".to_string(),
+        source,
+    }
+}
+
+// Returns the error of a git command that exited with a failure, with
+// what it printed to stderr.
+fn failed(command: &str, repo: &Path, stderr: &[u8]) -> FindError {
+    FindError::CommandFailed {
+        command: command.to_string(),
+        dir: repo.to_path_buf(),
+        message: String::from_utf8_lossy(stderr).trim().to_string(),
+    }
+}
+
+/// Returns the URL of the `origin` remote of a repository root, read
+/// straight from its `.git/config` file.
+pub fn origin_url(repo: &Path) -> Option<String> {
+    let contents = fs::read_to_string(common_dir(&git_dir(repo)?).join("config")).ok()?;
+    remote_url(&contents, "origin")
+}
+
+// Finds the url of a remote in the contents of a git config file. Only
+// the subset of the format that git itself writes for remotes is needed.
+fn remote_url(contents: &str, remote: &str) -> Option<String> {
+    let section = format!("[remote \"{}\"]", remote);
+    let mut in_section = false;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') {
+            in_section = line == section;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim().eq_ignore_ascii_case("url") {
+                return Some(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_origin_url() -> Result<()> {
+        let dir = tempdir()?;
+        assert_eq!(origin_url(dir.path()), None);
+
+        fs::create_dir(dir.path().join(".git"))?;
+        fs::write(
+            dir.path().join(".git/config"),
+            "[core]\n\
+            \tbare = false\n\
+            [remote \"upstream\"]\n\
+            \turl = https://github.com/kubernetes/autoscaler.git\n\
+            [remote \"origin\"]\n\
+            \t# a comment\n\
+            \turl = git@github.com:patrickdappollonio/autoscaler.git\n\
+            \tfetch = +refs/heads/*:refs/remotes/origin/*\n",
+        )?;
+        assert_eq!(
+            origin_url(dir.path()).as_deref(),
+            Some("git@github.com:patrickdappollonio/autoscaler.git")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_status() {
+        let status = parse_status(
+            "# branch.oid 1b2c3d\n\
+            # branch.head main\n\
+            # branch.upstream origin/main\n\
+            # branch.ab +2 -1\n",
+        );
+        assert_eq!(
+            status,
+            Status {
+                branch: Some("main".to_string()),
+                ahead: 2,
+                behind: 1,
+                dirty: false,
+            }
+        );
+        assert_eq!(status.to_string(), "main +2 -1");
+
+        let status = parse_status(
+            "# branch.oid 1b2c3d\n\
+            # branch.head (detached)\n\
+            ? notes.txt\n",
+        );
+        assert_eq!(status.branch, None);
+        assert!(status.dirty);
+        assert_eq!(status.to_string(), "(detached) dirty");
+    }
+
+    #[test]
+    fn test_last_commit_time() -> Result<()> {
+        let dir = tempdir()?;
+        assert_eq!(last_commit_time(dir.path())?, None);
+
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .env("GIT_COMMITTER_DATE", "1700000000 +0000")
+                .output()
+        };
+        git(&["init", "--quiet"])?;
+        assert_eq!(last_commit_time(dir.path())?, None);
+
+        git(&[
+            "-c",
+            "user.name=find-project",
+            "-c",
+            "user.email=find-project@example.com",
+            "commit",
+            "--quiet",
+            "--allow-empty",
+            "--message",
+            "Initial commit",
+        ])?;
+        assert_eq!(last_commit_time(dir.path())?, Some(1_700_000_000));
+        Ok(())
+    }
+
+    #[test]
+    fn test_worktrees_and_submodules() -> Result<()> {
+        let dir = tempdir()?;
+        let main = dir.path().join("api");
+        let worktree = dir.path().join("api-hotfix");
+        let submodule = main.join("proto");
+
+        fs::create_dir_all(main.join(".git").join("worktrees").join("api-hotfix"))?;
+        fs::write(
+            main.join(".git").join("config"),
+            "[remote \"origin\"]\n\turl = git@github.com:acme/api.git\n",
+        )?;
+        fs::write(main.join(".git/worktrees/api-hotfix/commondir"), "../..\n")?;
+        fs::create_dir_all(&worktree)?;
+        fs::write(
+            worktree.join(".git"),
+            "gitdir: ../api/.git/worktrees/api-hotfix\n",
+        )?;
+
+        fs::create_dir_all(main.join(".git").join("modules").join("proto"))?;
+        fs::write(
+            main.join(".git/modules/proto/config"),
+            "[remote \"origin\"]\n\turl = git@github.com:acme/proto.git\n",
+        )?;
+        fs::create_dir_all(&submodule)?;
+        fs::write(submodule.join(".git"), "gitdir: ../.git/modules/proto\n")?;
+
+        assert_eq!(
+            origin_url(&worktree).as_deref(),
+            Some("git@github.com:acme/api.git")
+        );
+        assert_eq!(main_worktree(&worktree), Some(paths::canonicalize(&main)?));
+
+        fs::write(main.join(".git").join("HEAD"), "ref: refs/heads/main\n")?;
+        fs::write(
+            main.join(".git/worktrees/api-hotfix/HEAD"),
+            "ref: refs/heads/hotfix/login\n",
+        )?;
+        assert_eq!(head_branch(&main).as_deref(), Some("main"));
+        assert_eq!(head_branch(&worktree).as_deref(), Some("hotfix/login"));
+        fs::write(main.join(".git").join("HEAD"), "1b2c3d4e\n")?;
+        assert_eq!(head_branch(&main), None);
+        assert_eq!(main_worktree(&main), None);
+
+        assert_eq!(
+            origin_url(&submodule).as_deref(),
+            Some("git@github.com:acme/proto.git")
+        );
+        assert_eq!(main_worktree(&submodule), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch() -> Result<()> {
+        let dir = tempdir()?;
+        let upstream = dir.path().join("upstream");
+        let clone = dir.path().join("clone");
+
+        let git = |dir: &Path, args: &[&str]| -> Result<()> {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(["-c", "user.name=find-project"])
+                .args(["-c", "user.email=find-project@example.com"])
+                .args(args)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()?;
+            assert!(status.success(), "git {:?} failed", args);
+            Ok(())
+        };
+        let commit = |message| git(&upstream, &["commit", "--allow-empty", "-m", message]);
+
+        fs::create_dir(&upstream)?;
+        git(&upstream, &["init", "--quiet"])?;
+        commit("Initial commit")?;
+        git(
+            dir.path(),
+            &["clone", "--quiet", upstream.to_str().unwrap(), "clone"],
+        )?;
+
+        let timeout = Duration::from_secs(30);
+        assert!(fetch(&clone, timeout)?.is_empty());
+
+        commit("Second commit")?;
+        let updated = fetch(&clone, timeout)?;
+        assert_eq!(updated.len(), 1);
+        assert!(updated[0].starts_with("origin/"), "{:?}", updated);
+        Ok(())
+    }
+}
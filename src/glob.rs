@@ -0,0 +1,74 @@
+use std::path::Path;
+
+/// Returns whether `text` matches a shell-style pattern, where `*` matches
+/// any number of characters and `?` matches exactly one.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was seen in the pattern, and the position in the
+    // text it's currently matching up to, to backtrack on mismatches.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Returns whether a path matches a pattern. A pattern without slashes is
+/// matched against the folder name, otherwise each of its parts is matched
+/// against the trailing components of the path, so `github.com/*/archive`
+/// matches any `archive` folder two levels under `github.com`.
+pub fn matches_path(pattern: &str, path: &Path) -> bool {
+    let parts: Vec<&str> = pattern.split('/').filter(|p| !p.is_empty()).collect();
+    let components: Vec<_> = path.components().collect();
+    if parts.is_empty() || parts.len() > components.len() {
+        return false;
+    }
+
+    components[components.len() - parts.len()..]
+        .iter()
+        .zip(parts)
+        .all(|(component, part)| matches(part, &component.as_os_str().to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("legacy-*", "legacy-billing"));
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "aXXbYYc"));
+        assert!(matches("v?", "v2"));
+        assert!(!matches("v?", "v10"));
+        assert!(!matches("legacy-*", "billing"));
+        assert!(matches("*.photoslibrary", "Photos Library.photoslibrary"));
+    }
+
+    #[test]
+    fn test_matches_path() {
+        let path = Path::new("/src/github.com/acme/archive");
+        assert!(matches_path("archive", path));
+        assert!(matches_path("github.com/*/archive", path));
+        assert!(matches_path("acme/arch*", path));
+        assert!(!matches_path("gitlab.com/*/archive", path));
+        assert!(!matches_path("acme", path));
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use grep_regex::RegexMatcherBuilder;
+use grep_searcher::sinks::Lossy;
+use grep_searcher::{BinaryDetection, SearcherBuilder};
+use ignore::WalkBuilder;
+use std::path::Path;
+
+// Files larger than this, like data dumps or bundled builds, aren't
+// searched.
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Calls found with the path, line number and text of every line of the
+/// files in a folder matching the regular expression, in the order of
+/// their paths. Hidden folders like `.git`, the pruned ones, files left
+/// out by `.gitignore` and `.ignore` files, binary files and files over
+/// 10 MiB are skipped, and links aren't followed.
+pub fn search(
+    dir: &Path,
+    pattern: &str,
+    ignore_case: bool,
+    prune: &[String],
+    found: &mut impl FnMut(&Path, usize, &str) -> Result<()>,
+) -> Result<()> {
+    let matcher = RegexMatcherBuilder::new()
+        .case_insensitive(ignore_case)
+        .line_terminator(Some(b'\n'))
+        .build(pattern)
+        .with_context(|| format!("Invalid regular expression {:?}", pattern))?;
+    let mut searcher = SearcherBuilder::new()
+        .binary_detection(BinaryDetection::quit(0))
+        .line_number(true)
+        .build();
+
+    let prune = prune.to_vec();
+    let walker = WalkBuilder::new(dir)
+        .max_filesize(Some(MAX_FILE_SIZE))
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| {
+            !entry.file_type().is_some_and(|t| t.is_dir())
+                || !prune
+                    .iter()
+                    .any(|prune| entry.file_name() == prune.as_str())
+        })
+        .build();
+    for entry in walker.filter_map(|entry| entry.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let path = entry.path();
+        // Errors from found stop the search, and files that can't be read
+        // are skipped.
+        let mut failed = None;
+        let _ = searcher.search_path(
+            &matcher,
+            path,
+            Lossy(
+                |n, line| match found(path, n as usize, line.trim_end_matches(['\r', '\n'])) {
+                    Ok(()) => Ok(true),
+                    Err(err) => {
+                        failed = Some(err);
+                        Ok(false)
+                    }
+                },
+            ),
+        );
+        if let Some(err) = failed {
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_search() -> Result<()> {
+        let dir = tempdir()?;
+        fs::create_dir_all(dir.path().join("src"))?;
+        fs::create_dir_all(dir.path().join(".git"))?;
+        fs::create_dir_all(dir.path().join("target"))?;
+        fs::create_dir_all(dir.path().join("dist"))?;
+        fs::write(
+            dir.path().join("src/lib.rs"),
+            "fn charge() {}\r\n// Charge the card\nfn refund() {}\n",
+        )?;
+        fs::write(dir.path().join("README.md"), "Charges cards.\n")?;
+        fs::write(dir.path().join(".git/HEAD"), "charge\n")?;
+        fs::write(dir.path().join(".gitignore"), "dist/\n")?;
+        fs::write(dir.path().join("dist/app.js"), "charge()\n")?;
+        fs::write(dir.path().join("target/out"), "charge\n")?;
+        fs::write(dir.path().join("src/data.bin"), b"charge\0")?;
+        fs::write(
+            dir.path().join("src/dump.sql"),
+            "charge\n".repeat(MAX_FILE_SIZE as usize / 7 + 1),
+        )?;
+
+        let mut lines: Vec<(PathBuf, usize, String)> = Vec::new();
+        let prune = ["target".to_string()];
+        search(dir.path(), "charge", false, &prune, &mut |path, n, line| {
+            lines.push((path.strip_prefix(dir.path())?.to_path_buf(), n, line.into()));
+            Ok(())
+        })?;
+        assert_eq!(
+            lines,
+            [(PathBuf::from("src/lib.rs"), 1, "fn charge() {}".to_string())]
+        );
+
+        let mut found: Vec<(PathBuf, usize)> = Vec::new();
+        search(
+            dir.path(),
+            r"^//\s*CHARGE",
+            true,
+            &prune,
+            &mut |path, n, _| {
+                found.push((path.strip_prefix(dir.path())?.to_path_buf(), n));
+                Ok(())
+            },
+        )?;
+        assert_eq!(found, [(PathBuf::from("src/lib.rs"), 2)]);
+
+        assert!(search(dir.path(), "charge(", false, &prune, &mut |_, _, _| Ok(())).is_err());
+        Ok(())
+    }
+}
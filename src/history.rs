@@ -0,0 +1,134 @@
+use crate::error::Result;
+use crate::store;
+use std::path::{Path, PathBuf};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// How many days of history are kept by default.
+pub const DEFAULT_RETENTION_DAYS: u64 = 90;
+
+/// A query resolved by find-project and the directory it printed.
+#[derive(Debug, PartialEq)]
+pub struct Jump {
+    pub time: u64,
+    pub query: String,
+    pub path: PathBuf,
+}
+
+/// The log of every jump, oldest first.
+pub struct History {
+    path: PathBuf,
+    jumps: Vec<Jump>,
+}
+
+impl History {
+    /// Loads the history stored in the data directory.
+    pub fn load() -> Result<Self> {
+        Self::load_from(store::data_dir()?.join("history"))
+    }
+
+    pub fn load_from(path: PathBuf) -> Result<Self> {
+        let jumps = store::read_lines(&path)?
+            .into_iter()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                Some(Jump {
+                    time: fields.next()?.parse().ok()?,
+                    query: fields.next()?.to_string(),
+                    path: PathBuf::from(fields.next()?),
+                })
+            })
+            .collect();
+        Ok(History { path, jumps })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::write_lines(
+            &self.path,
+            self.jumps
+                .iter()
+                .map(|j| format!("{}\t{}\t{}", j.time, j.query, j.path.display())),
+        )
+    }
+
+    pub fn jumps(&self) -> &[Jump] {
+        &self.jumps
+    }
+
+    /// Appends a jump and forgets the ones older than the retention
+    /// period.
+    pub fn record(&mut self, query: &str, path: &Path, now: u64, retention_days: u64) {
+        let cutoff = now.saturating_sub(retention_days.saturating_mul(DAY));
+        self.jumps.retain(|j| j.time >= cutoff);
+        self.jumps.push(Jump {
+            time: now,
+            query: query.to_string(),
+            path: path.to_path_buf(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.jumps.clear();
+    }
+}
+
+/// Formats seconds since the Unix epoch as an UTC `YYYY-MM-DD HH:MM:SS`
+/// timestamp.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / DAY) as i64;
+    let rem = secs % DAY;
+
+    // Converts days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_history_retention() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("history");
+
+        let mut history = History::load_from(path.clone())?;
+        history.record("api", Path::new("/src/api"), DAY, 1);
+        history.record("web", Path::new("/src/web"), 2 * DAY, 1);
+        history.record("cli", Path::new("/src/cli"), 3 * DAY, 1);
+        history.save()?;
+
+        let history = History::load_from(path)?;
+        let queries: Vec<_> = history.jumps().iter().map(|j| j.query.as_str()).collect();
+        assert_eq!(queries, vec!["web", "cli"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13:20");
+    }
+}
@@ -0,0 +1,221 @@
+use crate::error::{FindError, Result};
+use std::env;
+use std::path::PathBuf;
+
+/// A directory read from another tool's database.
+#[derive(Debug, PartialEq)]
+pub struct Imported {
+    pub path: PathBuf,
+    pub visits: f64,
+    pub last_visit: u64,
+}
+
+/// Returns where zoxide keeps its database by default.
+pub fn zoxide_path() -> Option<PathBuf> {
+    if let Ok(dir) = env::var("_ZO_DATA_DIR") {
+        return Some(PathBuf::from(dir).join("db.zo"));
+    }
+    platform_data_dir().map(|dir| dir.join("zoxide").join("db.zo"))
+}
+
+/// Returns where autojump keeps its database by default.
+pub fn autojump_path() -> Option<PathBuf> {
+    if cfg!(target_os = "macos") {
+        return home().map(|home| home.join("Library").join("autojump").join("autojump.txt"));
+    }
+    platform_data_dir().map(|dir| dir.join("autojump").join("autojump.txt"))
+}
+
+/// Returns where z keeps its database by default.
+pub fn z_path() -> Option<PathBuf> {
+    if let Ok(file) = env::var("_Z_DATA") {
+        return Some(PathBuf::from(file));
+    }
+    home().map(|home| home.join(".z"))
+}
+
+/// Parses a zoxide database: a version number followed by a bincode
+/// encoded list of directories with their rank and last access time.
+pub fn zoxide(data: &[u8]) -> Result<Vec<Imported>> {
+    let mut reader = Reader { data, pos: 0 };
+
+    let version = reader.u32()?;
+    if version != 3 {
+        return Err(FindError::Invalid(format!(
+            "Unsupported zoxide database version {}",
+            version
+        )));
+    }
+
+    let len = reader.u64()?;
+    let mut dirs = Vec::new();
+    for _ in 0..len {
+        let path_len = reader.u64()? as usize;
+        let path = String::from_utf8(reader.bytes(path_len)?.to_vec())
+            .map_err(|_| FindError::Invalid("Invalid path in zoxide database".to_string()))?;
+        let rank = f64::from_le_bytes(reader.array()?);
+        let last_accessed = reader.u64()?;
+
+        dirs.push(Imported {
+            path: PathBuf::from(path),
+            visits: rank,
+            last_visit: last_accessed,
+        });
+    }
+
+    Ok(dirs)
+}
+
+/// Parses an autojump database: one `weight<TAB>path` per line. Autojump
+/// doesn't keep visit times, so all entries get `last_visit`.
+pub fn autojump(contents: &str, last_visit: u64) -> Vec<Imported> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (weight, path) = line.split_once('\t')?;
+            Some(Imported {
+                path: PathBuf::from(path),
+                visits: weight.trim().parse().ok()?,
+                last_visit,
+            })
+        })
+        .collect()
+}
+
+/// Parses a z database: one `path|rank|time` per line.
+pub fn z(contents: &str) -> Vec<Imported> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.rsplitn(3, '|');
+            let time = fields.next()?;
+            let rank = fields.next()?;
+            let path = fields.next()?;
+            Some(Imported {
+                path: PathBuf::from(path),
+                visits: rank.parse().ok()?,
+                last_visit: time.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+fn home() -> Option<PathBuf> {
+    env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .ok()
+        .map(PathBuf::from)
+}
+
+fn platform_data_dir() -> Option<PathBuf> {
+    if let Ok(dir) = env::var("XDG_DATA_HOME") {
+        return Some(PathBuf::from(dir));
+    }
+    if cfg!(windows) {
+        return env::var("LOCALAPPDATA").ok().map(PathBuf::from);
+    }
+    if cfg!(target_os = "macos") {
+        return home().map(|home| home.join("Library").join("Application Support"));
+    }
+    home().map(|home| home.join(".local").join("share"))
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| FindError::Invalid("Unexpected end of zoxide database".to_string()))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoxide() -> Result<()> {
+        let mut data = Vec::new();
+        data.extend(3u32.to_le_bytes());
+        data.extend(1u64.to_le_bytes());
+        data.extend(8u64.to_le_bytes());
+        data.extend(b"/src/api");
+        data.extend(12.5f64.to_le_bytes());
+        data.extend(1700000000u64.to_le_bytes());
+
+        assert_eq!(
+            zoxide(&data)?,
+            vec![Imported {
+                path: PathBuf::from("/src/api"),
+                visits: 12.5,
+                last_visit: 1700000000,
+            }]
+        );
+
+        assert!(zoxide(&data[..data.len() - 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_autojump() {
+        let contents = "10.0\t/src/api\nbroken line\n22.5\t/src/web\n";
+        assert_eq!(
+            autojump(contents, 42),
+            vec![
+                Imported {
+                    path: PathBuf::from("/src/api"),
+                    visits: 10.0,
+                    last_visit: 42,
+                },
+                Imported {
+                    path: PathBuf::from("/src/web"),
+                    visits: 22.5,
+                    last_visit: 42,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_z() {
+        let contents = "/src/a|b|7|1700000000\n/src/web|2|1700000001\n";
+        assert_eq!(
+            z(contents),
+            vec![
+                Imported {
+                    path: PathBuf::from("/src/a|b"),
+                    visits: 7.0,
+                    last_visit: 1700000000,
+                },
+                Imported {
+                    path: PathBuf::from("/src/web"),
+                    visits: 2.0,
+                    last_visit: 1700000001,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! Finds project folders by name inside one or more roots. This is the
+//! engine behind the `find-project` command, usable from other tools
+//! without shelling out to it.
+
+#[cfg(feature = "async")]
+pub mod background;
+pub mod clone;
+pub mod collation;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod finder;
+pub mod frecency;
+pub mod git;
+pub mod glob;
+pub mod history;
+pub mod import;
+pub mod manifest;
+pub mod misses;
+pub mod paths;
+pub mod pins;
+#[cfg(feature = "python")]
+mod python;
+pub mod report;
+pub mod stats;
+pub mod store;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod uring;
+pub mod vcs;
+
+pub use collation::Collation;
+pub use error::FindError;
+pub use finder::{is_project, Content, Finder, FinderOptions, FuzzyScorer, Kind, Matcher, Visit};
+pub use manifest::Manifest;
+pub use report::{Match, SearchReport};
@@ -1,332 +1,4933 @@
-use anyhow::{Context, Result};
-use clap::Parser;
+mod bench;
+mod config;
+mod doctor;
+mod exec;
+mod grep;
+mod nice;
+mod opts;
+mod serve;
+mod service;
+#[cfg(feature = "self-update")]
+mod update;
+
+use anyhow::{bail, Context, Result};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use config::{Config, Root, RootSource};
+use find_project::frecency::{self, Database, RankBy, Ranking};
+use find_project::history::{self, History};
+use find_project::misses::Misses;
+use find_project::pins::Pins;
+use find_project::{
+    clone, git, import, is_project, paths, stats, store, vcs, Collation, Content, FindError,
+    Finder, FinderOptions, FuzzyScorer, Kind, Manifest, Matcher,
+};
+use regex::bytes::Regex;
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
 use std::env;
+use std::ffi::OsString;
 use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, IsTerminal, Write};
+use std::iter;
+use std::net::{SocketAddr, TcpListener};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+#[derive(Parser, Default, Debug, Clone)]
+#[command(
+    version,
+    about = "Traverse the directory specified by $FP_FOLDER or $GOPATH to find a folder depth-first.",
+    args_conflicts_with_subcommands = true,
+    args_override_self = true,
+    subcommand_negates_reqs = true
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[clap(required_unless_present_any = ["dirty", "clean", "branch"])]
+    folder_name: Option<String>,
+
+    #[clap(long, help = "Also search in \"vendor\" folders")]
+    include_vendor: bool,
+
+    #[clap(
+        long,
+        help = "Also search in hidden folders: dot folders, and on Windows, those with the hidden attribute"
+    )]
+    include_hidden: bool,
+
+    #[clap(long, help = "Sort folders alphabetically")]
+    sort_alphabetically: bool,
+
+    #[clap(
+        long,
+        help = "Also search inside symlinks to folders, and junctions on Windows"
+    )]
+    follow_links: bool,
+
+    #[clap(
+        long,
+        help = "On macOS, also search ~/Library, the trash and app bundles when searching the home folder"
+    )]
+    include_system: bool,
+
+    #[clap(
+        long,
+        conflicts_with = "case_sensitive",
+        help = "Match folder names ignoring case [default on macOS and Windows]"
+    )]
+    ignore_case: bool,
+
+    #[clap(long, help = "Match folder names with the exact case")]
+    case_sensitive: bool,
+
+    #[clap(
+        long,
+        help = "Match folders with the letters of the name in order, ignoring case, and pick or list the best matches first"
+    )]
+    fuzzy: bool,
+
+    #[clap(
+        long = "type",
+        value_enum,
+        value_name = "TYPE",
+        default_value_t = EntryType::Dir,
+        help = "Whether to find folders, files, or both"
+    )]
+    entry_type: EntryType,
+
+    #[clap(
+        long,
+        value_name = "DEPTH",
+        help = "Don't search deeper than this many folders"
+    )]
+    max_depth: Option<usize>,
+
+    #[clap(
+        long,
+        value_name = "COUNT",
+        help = "Read this many folders of a root at the same time, stopping them all at the first match"
+    )]
+    threads: Option<usize>,
+
+    #[clap(
+        long,
+        help = "Open folders in batches with io_uring instead of threads, on Linux builds with the io-uring feature"
+    )]
+    io_uring: bool,
+
+    #[clap(
+        long,
+        value_name = "SIZE",
+        value_parser = stats::parse_size,
+        help = "Search depth-first once the folders waiting to be searched take this much memory, like \"256M\""
+    )]
+    max_memory: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Use the settings of this profile from the configuration file [env: FP_PROFILE]"
+    )]
+    profile: Option<String>,
+
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        help = "Where the roots to search come from [default: auto]"
+    )]
+    source: Option<RootSource>,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Search and print the roots as configured, without resolving their symlinks"
+    )]
+    no_follow_root: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Only read from disk when nothing else is, for long searches and commands over every repository"
+    )]
+    nice_io: bool,
+
+    #[clap(
+        long = "root",
+        value_name = "NAME",
+        help = "Only search the roots with this name from the configuration file"
+    )]
+    roots: Vec<String>,
+
+    #[clap(
+        long = "prune",
+        value_name = "NAME",
+        help = "Never search inside folders with this name"
+    )]
+    prune: Vec<String>,
+
+    #[clap(
+        long = "exclude",
+        value_name = "PATTERN",
+        help = "Never search inside folders matching this pattern, like \"legacy-*\" or \"github.com/*/archive\""
+    )]
+    exclude: Vec<String>,
+
+    #[clap(
+        long = "marker",
+        value_name = "FILE",
+        help = "File or folder that makes a folder a project root [default: .git, .hg, .svn]"
+    )]
+    markers: Vec<String>,
+
+    #[clap(
+        long,
+        help = "Only match folders that are project roots (see --marker)"
+    )]
+    projects_only: bool,
+
+    #[clap(
+        long,
+        help = "Match the repository whose go.mod declares the module path given, instead of a folder name"
+    )]
+    module: bool,
+
+    #[clap(
+        long,
+        conflicts_with = "module",
+        help = "Match the folder whose package.json declares the package name given, like @org/pkg, instead of a folder name"
+    )]
+    npm: bool,
+
+    #[clap(
+        long = "crate",
+        conflicts_with_all = ["module", "npm"],
+        help = "Match the folder whose Cargo.toml declares the crate name given, instead of a folder name"
+    )]
+    rust_crate: bool,
+
+    #[clap(
+        long,
+        conflicts_with_all = ["module", "npm", "rust_crate"],
+        help = "Match the repositories whose README title and first line have every word given, instead of a folder name"
+    )]
+    describe: bool,
+
+    #[clap(
+        long,
+        value_name = "TEXT",
+        help = "Only match folders with a file containing this text, like a topic or an endpoint, or files containing it"
+    )]
+    contains: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "PATTERN",
+        value_parser = Regex::new,
+        conflicts_with = "contains",
+        help = "Like --contains, with a regular expression instead of plain text"
+    )]
+    contains_regex: Option<Regex>,
+
+    #[clap(
+        long,
+        value_name = "COUNT",
+        help = "Only match folders with at least this many files, leaving hidden folders out"
+    )]
+    min_files: Option<usize>,
+
+    #[clap(
+        long,
+        value_name = "COUNT",
+        help = "Only match folders with at most this many files, leaving hidden folders out"
+    )]
+    max_files: Option<usize>,
+
+    #[clap(long, help = "Never search inside project roots (see --marker)")]
+    stop_at_projects: bool,
+
+    #[clap(
+        long,
+        help = "Don't match linked git worktrees, only their main repository"
+    )]
+    skip_worktrees: bool,
+
+    #[clap(
+        long,
+        value_name = "PATTERN",
+        help = "Only match repositories on a branch matching this pattern, like \"master\" or \"release-*\""
+    )]
+    branch: Option<String>,
+
+    #[clap(
+        long,
+        value_enum,
+        help = "How to rank previously visited folders sharing the same name [default: frecency]"
+    )]
+    rank_by: Option<RankBy>,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = Select::First,
+        help = "Whether to print the first folder found, or to search everything for the highest-ranked one"
+    )]
+    select: Select,
+
+    #[clap(
+        long,
+        value_name = "COMMAND",
+        help = "Search everything and rank the folders found by the scores this command prints for the paths on its stdin, one per line, highest first"
+    )]
+    scorer: Option<String>,
+
+    #[clap(long, help = "Print every matching folder instead of the first one")]
+    all: bool,
+
+    #[clap(
+        long,
+        conflicts_with = "all",
+        help = "When several folders match, list them and ask which one to print, if stderr is a terminal"
+    )]
+    choose: bool,
+
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        value_name = "STYLE",
+        help = "Print paths for a shell on the other side of WSL"
+    )]
+    translate_path: Option<PathStyle>,
+
+    #[clap(
+        short,
+        long,
+        global = true,
+        action = clap::ArgAction::Count,
+        help = "Print what the search does to stderr, repeat for more detail"
+    )]
+    verbose: u8,
+
+    #[command(flatten)]
+    list: ListOptions,
+}
+
+impl Args {
+    // Whether folder names are compared ignoring case, which is the
+    // default where the file system does it too.
+    fn ignores_case(&self) -> bool {
+        self.ignore_case || (paths::FOLDS_CASE && !self.case_sensitive)
+    }
+
+    // Returns the manifest declaring the name searched for, when it isn't
+    // a folder name.
+    fn manifest(&self) -> Option<Manifest> {
+        if self.module {
+            Some(Manifest::GoModule)
+        } else if self.npm {
+            Some(Manifest::NpmPackage)
+        } else if self.rust_crate {
+            Some(Manifest::Crate)
+        } else if self.describe {
+            Some(Manifest::Readme)
+        } else {
+            None
+        }
+    }
+
+    // Whether every match is printed, rather than jumping to the first.
+    // Without a folder name, every project is.
+    fn lists_matches(&self) -> bool {
+        let list = &self.list;
+        self.folder_name.is_none()
+            || self.all
+            || list.long
+            || list.git_status
+            || list.dirty
+            || list.clean
+            || list.sort == Some(SortOrder::LastCommit)
+            || list.group_by.is_some()
+            || list.json
+            || list.print0
+    }
+
+    // Returns these arguments searching the roots given to a subcommand
+    // instead of the ones given before it.
+    fn with_roots(&self, roots: &RootsArg) -> Args {
+        let mut args = self.clone();
+        args.roots = roots.roots.clone();
+        args
+    }
+}
+
+// Options for printing every match, used by searches with --all and by
+// the list subcommand.
+#[derive(clap::Args, Default, Debug, Clone)]
+struct ListOptions {
+    #[clap(
+        long,
+        help = "Also print when each folder was last visited (implies --all)"
+    )]
+    long: bool,
+
+    #[clap(
+        long,
+        help = "Also print the branch, ahead and behind counts and uncommitted \
+            changes of each repository (implies --long)"
+    )]
+    git_status: bool,
+
+    #[clap(
+        long,
+        conflicts_with = "clean",
+        help = "Only print repositories with uncommitted changes (implies --all)"
+    )]
+    dirty: bool,
+
+    #[clap(
+        long,
+        help = "Only print repositories without uncommitted changes (implies --all)"
+    )]
+    clean: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "ORDER",
+        help = "Search folders in this alphabetical order, or print the matches by their last commit (implies --all) [default: found]"
+    )]
+    sort: Option<SortOrder>,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "KEY",
+        help = "Print the matches under a header for each group (implies --all)"
+    )]
+    group_by: Option<GroupBy>,
+
+    #[clap(
+        long,
+        conflicts_with = "print0",
+        help = "Print the matches as a JSON array (implies --all)"
+    )]
+    json: bool,
+
+    #[clap(
+        long,
+        conflicts_with = "group_by",
+        help = "End every match with a NUL character instead of a newline, for xargs -0 (implies --all)"
+    )]
+    print0: bool,
+
+    #[clap(
+        long,
+        value_name = "COUNT",
+        help = "Only keep the first folders found in each root, this many of them, so a large root doesn't crowd out the others"
+    )]
+    max_per_root: Option<usize>,
+}
+
+// The roots searched by the subcommands going over them, which replace
+// the ones given before the subcommand.
+#[derive(clap::Args, Default, Debug, Clone)]
+struct RootsArg {
+    #[clap(
+        long = "root",
+        value_name = "NAME",
+        help = "Only search the roots with this name from the configuration file"
+    )]
+    roots: Vec<String>,
+}
+
+// A folder printed by list_matches, in the shape written by --json.
+#[derive(serde::Serialize)]
+struct Listed {
+    path: String,
+    root: PathBuf,
+    /// Seconds since the Unix epoch, or None when it was never visited.
+    last_visit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<git::Status>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    worktree_of: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+}
+
+impl Listed {
+    // Returns the line printed for the folder, with when it was last
+    // visited and its status with --long and --git-status.
+    fn line(&self, list: &ListOptions) -> String {
+        if !list.long && !list.git_status {
+            return self.path.clone();
+        }
+        let last_visit = self
+            .last_visit
+            .map_or_else(|| "never visited".to_string(), history::format_timestamp);
+        let mut line = match &self.status {
+            Some(status) => format!("{:<19}  {}  {}", last_visit, self.path, status),
+            None if list.git_status => format!("{:<19}  {}  -", last_visit, self.path),
+            None => format!("{:<19}  {}", last_visit, self.path),
+        };
+        if let Some(main) = &self.worktree_of {
+            line.push_str(&format!("  (worktree of {})", main.display()));
+        }
+        line
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+enum Select {
+    /// The previously visited folder ranked highest, or the first one found, which is fast
+    #[default]
+    First,
+    /// The highest-ranked of every folder found, then the shallowest, which is always the same
+    Best,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+enum SortOrder {
+    /// In the order they're found while traversing the roots
+    #[default]
+    Found,
+    /// Repositories with the most recent commit first
+    LastCommit,
+    /// Folders in alphabetical order, by their bytes, like --sort-alphabetically
+    Lexical,
+    /// Folders in alphabetical order, with numbers by their value: "project-2" before "project-10"
+    Natural,
+    /// Like natural, ignoring case and accents
+    Locale,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+enum EntryType {
+    /// Folders
+    #[default]
+    Dir,
+    /// Files, in the same folders folders are searched in
+    File,
+    /// Folders and files
+    Any,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum PathStyle {
+    /// Paths inside WSL, like /mnt/c/Users for C:\Users
+    Wsl,
+    /// Windows paths, like C:\Users for /mnt/c/Users
+    Windows,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Editor {
+    /// Visual Studio Code
+    Vscode,
+    /// Emacs, for project.el or Projectile
+    Emacs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ExportOutput {
+    /// A .code-workspace file with every folder, the default for VS Code
+    Workspace,
+    /// The items of a VS Code QuickPick, for an extension to show
+    VscodeQuickpick,
+    /// The project list of project.el, the default for Emacs
+    ProjectEl,
+    /// The known projects file of Projectile
+    Projectile,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum DirenvShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+enum RecentBy {
+    /// When its files or its git index last changed
+    #[default]
+    Modified,
+    /// When you last went to it
+    Visited,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+enum DuplicateKey {
+    /// The name of the folder
+    #[default]
+    Name,
+    /// The repository its origin remote points to, no matter the protocol
+    Remote,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum GroupBy {
+    /// The owner of the repository, from its origin remote or its path
+    Org,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Pin a project so it always wins for its folder name
+    Pin {
+        /// Folder name to search for, or path to the project to pin
+        #[clap(required_unless_present = "list")]
+        name_or_path: Option<String>,
+
+        #[clap(long, conflicts_with = "name_or_path", help = "List pinned projects")]
+        list: bool,
+    },
+
+    /// Remove a pinned project by folder name or path
+    Unpin { name_or_path: String },
+
+    /// Seed the frecency database from zoxide, autojump or z
+    Import {
+        #[clap(value_enum)]
+        source: ImportSource,
+
+        #[clap(
+            long,
+            help = "Read the database from this file instead of the default location"
+        )]
+        file: Option<PathBuf>,
+    },
+
+    /// Print the highest-ranked previously visited projects
+    Top {
+        #[clap(default_value_t = 10, help = "Number of projects to print")]
+        count: usize,
+
+        #[clap(long, value_enum, help = "What to rank by [default: frecency]")]
+        rank_by: Option<RankBy>,
+    },
+
+    /// Print the projects changed or visited most recently, newest first
+    Recent {
+        #[clap(default_value_t = 10, help = "Number of projects to print")]
+        count: usize,
+
+        #[clap(
+            long,
+            value_enum,
+            default_value_t,
+            help = "What makes a project recent"
+        )]
+        by: RecentBy,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Show the log of resolved queries and the folders they printed
+    History {
+        #[clap(long, help = "Forget all recorded history")]
+        clear: bool,
+    },
+
+    /// Print every project, or every version-controlled repository, in the roots
+    List {
+        #[clap(
+            long,
+            help = "Only print repositories under version control, without searching inside them"
+        )]
+        vcs: bool,
+
+        #[command(flatten)]
+        options: ListOptions,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Print how many folders match, or how many repositories there are
+    Count {
+        /// Folder name to search for, every repository if not given
+        query: Option<String>,
+
+        #[clap(long, help = "Also print how many are in each root")]
+        stats: bool,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Print the matching folders, or every repository, as a tree of each root
+    Tree {
+        /// Folder name to search for, every repository if not given
+        query: Option<String>,
+
+        #[clap(
+            long,
+            value_name = "N",
+            default_value_t = 0,
+            help = "Also print the folders inside every match, this many levels deep"
+        )]
+        depth: usize,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Print the lines matching a regular expression in the files of every matching folder
+    Grep {
+        /// Regular expression to search for
+        pattern: String,
+
+        /// Folder name to search in, every repository if not given
+        query: Option<String>,
+
+        #[clap(short, long, help = "Match the pattern ignoring case")]
+        ignore_case: bool,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Run a command in every matching folder, or in every repository
+    Foreach {
+        /// Folder name to search for, every repository if not given
+        query: Option<String>,
+
+        #[command(flatten)]
+        roots: RootsArg,
+
+        /// Command to run, after "--"
+        #[clap(last = true, required = true)]
+        command: Vec<String>,
+    },
+
+    /// Print the matching folders, or every repository, for an editor to open
+    Export {
+        /// Editor to export them for
+        #[clap(value_enum)]
+        editor: Editor,
+
+        /// Folder name to search for, every repository if not given
+        query: Option<String>,
+
+        #[clap(long, value_enum, help = "What to print them as")]
+        output: Option<ExportOutput>,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Run "git fetch --prune" in every git repository and report the ones with new commits
+    FetchAll {
+        #[clap(
+            short,
+            long,
+            default_value_t = 8,
+            help = "How many repositories to fetch at the same time"
+        )]
+        jobs: usize,
+
+        #[clap(
+            long,
+            value_name = "SECONDS",
+            default_value_t = 120,
+            help = "Give up on a repository after this many seconds"
+        )]
+        timeout: u64,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Summarize the projects in every root
+    Stats {
+        #[command(flatten)]
+        roots: RootsArg,
+
+        #[clap(
+            long,
+            help = "Don't add up how much space the projects take, which reads every folder in them"
+        )]
+        no_size: bool,
+    },
+
+    /// Print how much space every matching folder, or every project, takes
+    Du {
+        /// Folder name to search for, every project if not given
+        query: Option<String>,
+
+        #[clap(
+            short,
+            long,
+            default_value_t = 8,
+            help = "How many folders to measure at the same time"
+        )]
+        jobs: usize,
+
+        #[clap(long, help = "Print sizes in bytes instead of KiB, MiB and GiB")]
+        bytes: bool,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Print the projects nothing has changed in for a while, and optionally remove them
+    Stale {
+        #[clap(
+            long,
+            default_value_t = 180,
+            help = "How many days without changes make a project stale"
+        )]
+        days: u64,
+
+        #[clap(
+            long,
+            conflicts_with = "archive",
+            help = "Delete every stale project, asking about each one first"
+        )]
+        delete: bool,
+
+        #[clap(
+            long,
+            value_name = "DIR",
+            help = "Move every stale project into this folder, asking about each one first"
+        )]
+        archive: Option<PathBuf>,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Print empty folders and visited or pinned projects that no longer exist, and optionally remove them
+    Prune {
+        #[clap(
+            long,
+            help = "Delete the empty folders and forget the missing projects, asking first"
+        )]
+        delete: bool,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Print the projects sharing a folder name or a repository, grouped
+    Dedupe {
+        #[clap(
+            long,
+            value_enum,
+            default_value_t,
+            help = "What makes projects duplicates"
+        )]
+        by: DuplicateKey,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Move repositories to where "clone" would put them, following their origin remote
+    Organize {
+        #[clap(
+            long,
+            conflicts_with = "dry_run",
+            help = "Move the repositories, instead of only printing where they'd go"
+        )]
+        apply: bool,
+
+        #[clap(long, help = "Only print where the repositories would go [default]")]
+        dry_run: bool,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Find a project like a regular search and open it in the file manager
+    Open {
+        /// Folder name to search for
+        name: String,
+    },
+
+    /// Find a project like a regular search and print the shell code that goes to it and loads its .envrc
+    Direnv {
+        /// Folder name to search for
+        #[clap(required_unless_present = "stdlib")]
+        name: Option<String>,
+
+        #[clap(
+            long,
+            value_enum,
+            default_value_t = DirenvShell::Bash,
+            help = "Shell the printed code is for"
+        )]
+        shell: DirenvShell,
+
+        #[clap(
+            long,
+            conflicts_with = "name",
+            help = "Print a \"use find_project\" function for ~/.config/direnv/direnvrc instead"
+        )]
+        stdlib: bool,
+    },
+
+    /// Print the project the current folder is in, its root and its depth, for a shell prompt
+    PromptInfo {
+        #[clap(
+            long,
+            value_name = "PATH",
+            help = "Folder to describe instead of the current one"
+        )]
+        path: Option<PathBuf>,
+
+        #[clap(long, help = "Print it as JSON")]
+        json: bool,
+
+        #[clap(
+            long,
+            value_name = "MILLISECONDS",
+            default_value_t = 100,
+            help = "Print nothing when it takes longer than this, such as on a slow network drive"
+        )]
+        timeout: u64,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Print the settings searches run with, after applying the configuration file and environment
+    Env {
+        #[clap(long, help = "Print them as JSON, with the settings of every root")]
+        json: bool,
+    },
+
+    /// Answer searches over HTTP on a loopback address, or JSON-RPC on stdin, as JSON
+    Serve {
+        #[clap(
+            long,
+            value_name = "ADDRESS",
+            default_value = "127.0.0.1:7433",
+            help = "Listen on this loopback address and port"
+        )]
+        http: String,
+
+        #[clap(
+            long,
+            conflicts_with = "http",
+            help = "Answer JSON-RPC calls on stdin instead, one per line, for editor plugins"
+        )]
+        stdio: bool,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Replace this binary with the one from the latest release on GitHub
+    #[cfg(feature = "self-update")]
+    SelfUpdate {
+        #[clap(long, help = "Only print whether there's a newer version")]
+        check: bool,
+    },
+
+    /// Explain step by step how a search for a folder name picks its result
+    Which {
+        /// Folder name to search for
+        name: String,
+
+        #[command(flatten)]
+        roots: RootsArg,
+    },
+
+    /// Clone a repository into <root>/<host>/<org>/<repo> and print its path
+    Clone {
+        /// Repository URL, or "org/repo" for a repository on GitHub
+        repo: String,
+
+        #[clap(
+            long,
+            value_name = "NAME",
+            help = "Clone into the root with this name from the configuration file"
+        )]
+        root: Option<String>,
+    },
+
+    /// Check the environment, configuration and roots for common problems
+    Doctor,
+
+    /// Print the man page, or write one for every command into a folder
+    Man {
+        #[clap(
+            long,
+            value_name = "DIR",
+            help = "Write find-project.1 and a page for every subcommand into this folder"
+        )]
+        dir: Option<PathBuf>,
+    },
+
+    /// Time searches against a generated tree of folders
+    Bench {
+        #[clap(long, default_value_t = 10, help = "Folders inside every folder")]
+        breadth: usize,
+
+        #[clap(
+            long,
+            default_value_t = 3,
+            help = "Levels of folders above the projects"
+        )]
+        depth: usize,
+
+        #[clap(long, default_value_t = 5, help = "How many times to run every search")]
+        runs: usize,
+
+        #[clap(
+            long,
+            value_name = "DIR",
+            help = "Generate the tree here and keep it, or reuse the one already in it"
+        )]
+        dir: Option<PathBuf>,
+    },
+
+    /// Inspect and modify the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Start "serve --http" at login as a user service, or remove it
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonAction,
+    },
+
+    /// Run the fp-<plugin> executable on $PATH with the rest of the arguments
+    Run {
+        /// Name of the plugin, like "sync" for fp-sync
+        plugin: String,
+
+        /// Arguments for the plugin, passed as they are
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<OsString>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum ConfigAction {
+    /// Print the value of a setting, such as "ranking.rank_by"
+    Get { key: String },
+
+    /// Change a setting, refusing values that make the configuration invalid
+    Set { key: String, value: String },
+
+    /// Print every setting in the configuration file
+    List,
+
+    /// Open the configuration file in $VISUAL or $EDITOR and validate it
+    Edit,
+
+    /// Validate the configuration file and report every problem found
+    Check,
+
+    /// Print the location of the configuration file
+    Path,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum DaemonAction {
+    /// Write the service definition for this platform, enable it and start it
+    Install {
+        #[clap(
+            long,
+            value_name = "ADDRESS",
+            default_value = "127.0.0.1:7433",
+            help = "Loopback address and port the service listens on"
+        )]
+        http: String,
+    },
+
+    /// Stop the service and remove its definition
+    Uninstall,
+
+    /// Print whether the service is installed and running
+    Status,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ImportSource {
+    Zoxide,
+    Autojump,
+    Z,
+}
+
+// Exit code after Ctrl-C, like shells use for processes killed by
+// SIGINT.
+const EXIT_CANCELLED: i32 = 130;
+
+fn main() -> Result<()> {
+    let result = run();
+    if let Err(err) = &result {
+        if matches!(err.downcast_ref(), Some(FindError::Cancelled)) {
+            eprintln!("Search cancelled");
+            process::exit(EXIT_CANCELLED);
+        }
+    }
+    result
+}
+
+fn run() -> Result<()> {
+    // Collect command-line arguments
+    let cli: Vec<OsString> = env::args_os().collect();
+    let args = Args::parse_from(cli.iter().cloned());
+
+    // Plugins only run when asked for with "run", so a folder sharing a
+    // name with one is still searched for, and searches don't go through
+    // $PATH first. They run before anything else is set up, so Ctrl-C
+    // goes straight to them.
+    if let Some(Command::Run { plugin, args }) = &args.command {
+        let Some(path) = exec::find_plugin(plugin) else {
+            bail!(
+                "There's no plugin named {:?}: fp-{} isn't on $PATH",
+                plugin,
+                plugin
+            );
+        };
+        return exec::run_plugin(&path, args, &plugin_env());
+    }
+
+    // Ctrl-C stops the search, so the matches found so far can still be
+    // printed. Pressing it again exits right away. It's only set up once
+    // the arguments are valid, so --help and mistakes return right away.
+    #[cfg(not(target_os = "wasi"))]
+    ctrlc::set_handler(|| {
+        if interrupted().swap(true, Ordering::Relaxed) {
+            process::exit(EXIT_CANCELLED);
+        }
+    })
+    .context("Unable to handle Ctrl-C")?;
+
+    // Enable debug logging if the environment variable FP_DEBUG is set
+    // to any non-empty value, or with -v when built without tracing.
+    let log_enabled =
+        env::var("FP_DEBUG").is_ok() || (args.verbose > 0 && !cfg!(feature = "tracing"));
+    #[cfg(feature = "tracing")]
+    init_tracing(args.verbose);
+
+    // The configuration subcommands must keep working when the file is
+    // invalid, so they run before it's loaded.
+    if let Some(Command::Config { action }) = &args.command {
+        return config_command(action);
+    }
+    // Before loading the configuration, so problems in it are reported
+    // along with everything else.
+    if let Some(Command::Doctor) = &args.command {
+        return doctor::run(args.source);
+    }
+    if let Some(Command::Man { dir }) = &args.command {
+        return man(dir.as_deref());
+    }
+    if let Some(Command::Direnv { stdlib: true, .. }) = &args.command {
+        print!("{}", DIRENV_STDLIB);
+        return Ok(());
+    }
+    if let Some(Command::Daemon { action }) = &args.command {
+        return match action {
+            DaemonAction::Install { http } => {
+                loopback_address(http)?;
+                service::install(http)
+            }
+            DaemonAction::Uninstall => service::uninstall(),
+            DaemonAction::Status => service::status(),
+        };
+    }
+    #[cfg(feature = "self-update")]
+    if let Some(Command::SelfUpdate { check }) = &args.command {
+        return update::run(*check);
+    }
+    if let Some(Command::Bench {
+        breadth,
+        depth,
+        runs,
+        dir,
+    }) = &args.command
+    {
+        let tree = bench::Tree {
+            breadth: *breadth,
+            depth: *depth,
+        };
+        return bench::run(&tree, *runs, dir.as_deref());
+    }
+
+    // Settings from the configuration file apply unless overridden by
+    // the command-line flags.
+    let mut config = Config::load()?;
+    let mut args = apply_default_flags(args, cli, &mut config)?;
+    apply_config(&mut args, &config);
+    if let Some(source) = args.source {
+        config.source = source;
+    }
+    config.no_follow_root |= args.no_follow_root;
+    // Modules and descriptions are looked for in repository roots only,
+    // without searching inside them.
+    if args.module || args.describe {
+        args.markers = config::VCS_MARKERS.iter().map(|m| m.to_string()).collect();
+        args.projects_only = true;
+        args.stop_at_projects = true;
+    }
+    // Packages and crates are often inside a monorepo or a workspace, so
+    // repositories are searched, but not the packages installed in them
+    // or what's built.
+    let installed = match args.manifest() {
+        Some(Manifest::NpmPackage) => Some("node_modules"),
+        Some(Manifest::Crate) => Some("target"),
+        _ => None,
+    };
+    if let Some(installed) = installed {
+        if !args.prune.iter().any(|name| name == installed) {
+            args.prune.push(installed.to_string());
+        }
+    }
+    if args.nice_io {
+        if let Err(err) = nice::lower_io_priority() {
+            eprintln!("Unable to lower the I/O priority: {}", err);
+        }
+    }
+    if log_enabled {
+        if let Ok(source) = root_source(&config) {
+            eprintln!("{}", describe_source(source, &config));
+        }
+        if args.translate_path.is_none()
+            && paths::is_wsl()
+            && search_roots(&config, &args.roots)
+                .is_ok_and(|roots| roots.iter().any(|root| root.path.starts_with("/mnt")))
+        {
+            eprintln!(
+                "Searching a Windows drive from WSL, use --translate-path windows \
+                if a Windows shell reads the paths printed"
+            );
+        }
+    }
+
+    match &args.command {
+        Some(Command::Pin { name_or_path, list }) => {
+            return pin(name_or_path.as_deref(), *list, &args, &config, log_enabled)
+        }
+        Some(Command::Unpin { name_or_path }) => return unpin(name_or_path),
+        Some(Command::Import { source, file }) => {
+            return import_database(*source, file.as_deref(), &config)
+        }
+        Some(Command::Top { count, rank_by }) => return top(*count, *rank_by, &config),
+        Some(Command::Env { json }) => return print_env(*json, &config),
+        Some(Command::PromptInfo {
+            path,
+            json,
+            timeout,
+            roots,
+        }) => {
+            let search_args = args.with_roots(roots);
+            return prompt_info(
+                path.clone(),
+                *json,
+                Duration::from_millis(*timeout),
+                search_args,
+                config,
+            );
+        }
+        Some(Command::Serve { http, stdio, roots }) => {
+            let search_args = args.with_roots(roots);
+            if *stdio {
+                return serve_stdio(&search_args, &config);
+            }
+            return serve(http, &search_args, &config);
+        }
+        Some(Command::Which { name, roots }) => {
+            let search_args = args.with_roots(roots);
+            return which(name, &search_args, &config, log_enabled);
+        }
+        Some(Command::Recent { count, by, roots }) => {
+            let search_args = args.with_roots(roots);
+            return recent(*count, *by, &search_args, &config, log_enabled);
+        }
+        Some(Command::History { clear }) => return history(*clear),
+        Some(Command::List {
+            vcs,
+            options,
+            roots,
+        }) => {
+            let mut list_args = args.with_roots(roots);
+            list_args.list = options.clone();
+            if *vcs {
+                list_args.markers = config::VCS_MARKERS.iter().map(|m| m.to_string()).collect();
+                list_args.stop_at_projects = true;
+            }
+            let roots = search_roots(&config, &list_args.roots)?;
+            return list_matches("", &roots, &list_args, &config, log_enabled);
+        }
+        Some(Command::Count {
+            query,
+            stats,
+            roots,
+        }) => {
+            let search_args = args.with_roots(roots);
+            return count(query.as_deref(), *stats, &search_args, &config, log_enabled);
+        }
+        Some(Command::Tree {
+            query,
+            depth,
+            roots,
+        }) => {
+            let search_args = args.with_roots(roots);
+            return tree(query.as_deref(), *depth, &search_args, &config, log_enabled);
+        }
+        Some(Command::Grep {
+            pattern,
+            query,
+            ignore_case,
+            roots,
+        }) => {
+            let search_args = args.with_roots(roots);
+            return grep(
+                pattern,
+                query.as_deref(),
+                *ignore_case,
+                &search_args,
+                &config,
+                log_enabled,
+            );
+        }
+        Some(Command::Foreach {
+            query,
+            roots,
+            command,
+        }) => {
+            let search_args = args.with_roots(roots);
+            return foreach(
+                query.as_deref(),
+                command,
+                &search_args,
+                &config,
+                log_enabled,
+            );
+        }
+        Some(Command::Export {
+            editor,
+            query,
+            output,
+            roots,
+        }) => {
+            let search_args = args.with_roots(roots);
+            return export(
+                *editor,
+                *output,
+                query.as_deref(),
+                &search_args,
+                &config,
+                log_enabled,
+            );
+        }
+        Some(Command::FetchAll {
+            jobs,
+            timeout,
+            roots,
+        }) => {
+            let search_args = args.with_roots(roots);
+            return fetch_all(
+                *jobs,
+                Duration::from_secs(*timeout),
+                &search_args,
+                &config,
+                log_enabled,
+            );
+        }
+        Some(Command::Stats { roots, no_size }) => {
+            let search_args = args.with_roots(roots);
+            return root_stats(!no_size, &search_args, &config, log_enabled);
+        }
+        Some(Command::Du {
+            query,
+            jobs,
+            bytes,
+            roots,
+        }) => {
+            let search_args = args.with_roots(roots);
+            return disk_usage(
+                query.as_deref(),
+                *jobs,
+                *bytes,
+                &search_args,
+                &config,
+                log_enabled,
+            );
+        }
+        Some(Command::Stale {
+            days,
+            delete,
+            archive,
+            roots,
+        }) => {
+            let search_args = args.with_roots(roots);
+            let action = match archive {
+                Some(dir) => StaleAction::Archive(dir),
+                None if *delete => StaleAction::Delete,
+                None => StaleAction::Print,
+            };
+            return stale(*days, action, &search_args, &config, log_enabled);
+        }
+        Some(Command::Prune { delete, roots }) => {
+            let search_args = args.with_roots(roots);
+            return prune(*delete, &search_args, &config);
+        }
+        Some(Command::Dedupe { by, roots }) => {
+            let search_args = args.with_roots(roots);
+            return dedupe(*by, &search_args, &config, log_enabled);
+        }
+        Some(Command::Organize { apply, roots, .. }) => {
+            let search_args = args.with_roots(roots);
+            return organize(*apply, &search_args, &config, log_enabled);
+        }
+        Some(Command::Clone { repo, root }) => {
+            return clone_repository(repo, root.as_deref(), &config)
+        }
+        #[cfg(feature = "self-update")]
+        Some(Command::SelfUpdate { .. }) => {}
+        Some(
+            Command::Config { .. }
+            | Command::Doctor
+            | Command::Man { .. }
+            | Command::Daemon { .. }
+            | Command::Bench { .. }
+            | Command::Open { .. }
+            | Command::Direnv { .. }
+            | Command::Run { .. },
+        )
+        | None => {}
+    }
+
+    // "open" and "direnv" search like a regular query, then open the
+    // folder found, or print how to go to it, instead of printing it.
+    let mut open = false;
+    let mut direnv = None;
+    match &args.command {
+        Some(Command::Open { name }) => {
+            args.folder_name = Some(name.clone());
+            open = true;
+        }
+        Some(Command::Direnv { name, shell, .. }) => {
+            args.folder_name = name.clone();
+            direnv = Some(*shell);
+        }
+        _ => {}
+    }
+
+    let query = args.folder_name.as_deref().unwrap_or_default();
+    if args.lists_matches() {
+        let roots = search_roots(&config, &args.roots)?;
+        return list_matches(query, &roots, &args, &config, log_enabled);
+    }
+    // Resolving the roots reads every one of them, so it waits until a
+    // step needs them: a pinned project is printed without touching them.
+    let roots = OnceCell::new();
+    let roots = || lazy_roots(&roots, &config, &args.roots);
+
+    let mut db = load_state(Database::load())?;
+    let now = frecency::now();
+    let ranking = ranking(args.rank_by, &config);
+
+    // Aliases are resolved before anything else. An alias either points
+    // to a folder, or to another name to search for.
+    let mut folder_name = query;
+    let mut alias_dir = None;
+    if let Some(target) = config.aliases.get(query) {
+        alias_dir = resolve_alias(target, roots()?);
+        if alias_dir.is_none() {
+            if Path::new(target).components().count() != 1 {
+                bail!(
+                    "Alias \"{}\" points to {}, which doesn't exist inside {}",
+                    query,
+                    target,
+                    display_roots(roots()?)
+                );
+            }
+            folder_name = target.as_str();
+        }
+    }
+
+    // A pinned project wins over anything else, then the most frecent
+    // project visited before, and only then we traverse the directory.
+    // Those are folders found by their name alone, so a search for files,
+    // for the name in a manifest, or filtering folders by what's in them,
+    // goes straight to it.
+    let by_folder_name = args.entry_type != EntryType::File
+        && args.manifest().is_none()
+        && args.contains.is_none()
+        && args.contains_regex.is_none()
+        && args.min_files.is_none()
+        && args.max_files.is_none();
+    let loc = if let Some(dir) = alias_dir {
+        if log_enabled {
+            eprintln!("Using alias \"{}\": {}", query, dir.display());
+        }
+        Some(dir)
+    } else if let Some(pinned) = load_state(Pins::load())?
+        .as_ref()
+        .and_then(|pins| pins.lookup(folder_name, args.ignores_case()))
+        .filter(|_| by_folder_name)
+    {
+        if log_enabled {
+            eprintln!("Using pinned project: {}", pinned.display());
+        }
+        Some(pinned.to_path_buf())
+    } else if args.choose && io::stderr().is_terminal() {
+        choose(roots()?, folder_name, &args, &config, log_enabled)?
+    } else if args.select == Select::Best || args.scorer.is_some() || args.fuzzy {
+        let mut dirs = find_all_in_roots(roots()?, folder_name, &args, &config, log_enabled)?;
+        rank_matches(&mut dirs, db.as_ref(), &ranking, now);
+        if args.fuzzy {
+            rank_by_fuzzy_score(&mut dirs, folder_name);
+        }
+        if let Some(scorer) = args.scorer.as_deref().filter(|_| dirs.len() > 1) {
+            rank_by_scorer(&mut dirs, scorer)?;
+        }
+        if log_enabled && dirs.len() > 1 {
+            eprintln!("Picked the best of {} folders found", dirs.len());
+        }
+        dirs.into_iter().next().map(|(_, dir)| dir)
+    } else if let Some(entry) = match &db {
+        Some(db) if by_folder_name => db.best(
+            folder_name,
+            args.ignores_case(),
+            &root_paths(roots()?),
+            &ranking,
+            now,
+        ),
+        _ => None,
+    } {
+        if log_enabled {
+            eprintln!("Using previously visited project: {}", entry.path.display());
+        }
+        Some(entry.path.clone())
+    } else {
+        find_or_remember(roots()?, folder_name, &args, &config, now, log_enabled)?
+    };
+
+    if let Some(loc) = loc {
+        // Files found aren't projects to rank among the visited ones. The
+        // folder is printed even when the visit can't be recorded.
+        if let Some(db) = db.as_mut().filter(|_| loc.is_dir()) {
+            db.record(&loc, now);
+            if let Err(err) = db.save() {
+                eprintln!("Warning: unable to record the visit: {}", err);
+            }
+        }
+
+        let retention_days = history_retention(&config)?;
+        if retention_days > 0 {
+            if let Some(mut history) = load_state(History::load())? {
+                history.record(query, &loc, now, retention_days);
+                if let Err(err) = history.save() {
+                    eprintln!("Warning: unable to record the jump: {}", err);
+                }
+            }
+        }
+
+        if open {
+            open_in_file_manager(&loc)
+        } else if let Some(shell) = direnv {
+            println!("{}", direnv_script(&output_path(&loc, &args), shell));
+            Ok(())
+        } else {
+            println!("{}", output_path(&loc, &args));
+            Ok(())
+        }
+    } else {
+        eprintln!(
+            "Folder \"{}\" not found inside {}",
+            folder_name,
+            display_roots(roots()?)
+        );
+        process::exit(1);
+    }
+}
+
+// The project a folder is in, as printed by "prompt-info".
+#[derive(serde::Serialize, Debug, PartialEq)]
+struct Enclosing {
+    name: String,
+    root: String,
+    depth: usize,
+    path: PathBuf,
+}
+
+// Prints the project a folder is in for a shell prompt, or nothing, and
+// fails, when it isn't in one. Prompts run it before every command, so
+// it never searches the roots, only looks at the folders above, and
+// gives up after the timeout.
+fn prompt_info(
+    path: Option<PathBuf>,
+    json: bool,
+    timeout: Duration,
+    args: Args,
+    config: Config,
+) -> Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let found = (|| {
+            let dir = match path {
+                Some(path) => path,
+                None => env::current_dir()?,
+            };
+            let dir = root_path(&dir, &config)?;
+            let roots = search_roots(&config, &args.roots)?;
+            anyhow::Ok(enclosing_project(&dir, &roots, &args.markers))
+        })();
+        let _ = sender.send(found);
+    });
+    let enclosing = match receiver.recv_timeout(timeout) {
+        Ok(Ok(Some(enclosing))) => enclosing,
+        Ok(Err(err)) => return Err(err),
+        Ok(Ok(None)) | Err(_) => process::exit(1),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string(&enclosing)?);
+    } else {
+        println!(
+            "{} · {} · {}",
+            enclosing.name, enclosing.root, enclosing.depth
+        );
+    }
+    Ok(())
+}
+
+// Returns the closest folder above dir, or dir itself, with a project
+// marker, inside the deepest root containing it. Roots are named after
+// their name in the configuration file, or their folder.
+fn enclosing_project(dir: &Path, roots: &[Root], markers: &[String]) -> Option<Enclosing> {
+    let root = roots
+        .iter()
+        .filter(|root| dir.starts_with(&root.path))
+        .max_by_key(|root| root.path.components().count())?;
+    let project = dir
+        .ancestors()
+        .take_while(|dir| *dir != root.path)
+        .find(|dir| is_project(dir, markers))?;
+    Some(Enclosing {
+        name: project.file_name()?.to_string_lossy().into_owned(),
+        root: match &root.name {
+            Some(name) => name.clone(),
+            None => root.path.file_name()?.to_string_lossy().into_owned(),
+        },
+        depth: project.strip_prefix(&root.path).ok()?.components().count(),
+        path: project.to_path_buf(),
+    })
+}
+
+// The settings a search without flags runs with, as printed by "env".
+#[derive(serde::Serialize)]
+struct Settings {
+    config_file: Option<PathBuf>,
+    profile: Option<String>,
+    source: RootSource,
+    roots: Vec<RootSettings>,
+    markers: Vec<String>,
+    prune: Vec<String>,
+    exclude: Vec<String>,
+    include_vendor: bool,
+    include_hidden: bool,
+    max_depth: Option<usize>,
+    sort_alphabetically: bool,
+    stop_at_projects: bool,
+    follow_links: bool,
+    no_follow_root: bool,
+    default_flags: Vec<String>,
+    rank_by: RankBy,
+    history_days: u64,
+    data_dir: PathBuf,
+}
+
+// The settings of a root, once the global ones and its own are merged.
+#[derive(serde::Serialize)]
+struct RootSettings {
+    name: Option<String>,
+    path: PathBuf,
+    include_vendor: bool,
+    include_hidden: bool,
+    max_depth: Option<usize>,
+    prune: Vec<String>,
+    exclude: Vec<String>,
+}
+
+// Prints the settings a search without flags would run with, either as
+// lines a shell can source or as JSON. Default flags only apply to
+// searches, so they're parsed here as if this was one, for an empty
+// folder name.
+fn print_env(json: bool, config: &Config) -> Result<()> {
+    let default_flags = default_flags(config)?;
+    let mut args = Args::parse_from(
+        iter::once("find-project".to_string())
+            .chain(default_flags.iter().cloned())
+            .chain(["--".to_string(), String::new()]),
+    );
+    apply_config(&mut args, config);
+
+    let source = root_source(config)?;
+    let roots = search_roots(config, &[])?
+        .into_iter()
+        .map(|root| {
+            let options = root_finder(&args, config, &root, false).options().clone();
+            RootSettings {
+                name: root.name,
+                path: options.root,
+                include_vendor: options.include_vendor,
+                include_hidden: options.include_hidden,
+                max_depth: options.max_depth,
+                prune: options.prune,
+                exclude: options.exclude,
+            }
+        })
+        .collect();
+    let settings = Settings {
+        config_file: config::config_path(),
+        profile: args.profile.clone().or_else(|| env::var("FP_PROFILE").ok()),
+        source,
+        roots,
+        markers: args.markers.clone(),
+        prune: args.prune.clone(),
+        exclude: args.exclude.clone(),
+        include_vendor: args.include_vendor || config.include_vendor,
+        include_hidden: args.include_hidden || config.include_hidden,
+        max_depth: args.max_depth,
+        sort_alphabetically: args.sort_alphabetically,
+        stop_at_projects: args.stop_at_projects,
+        follow_links: args.follow_links,
+        no_follow_root: config.no_follow_root,
+        default_flags,
+        rank_by: ranking(args.rank_by, config).rank_by,
+        history_days: history_retention(config)?,
+        data_dir: store::data_dir()?,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&settings)?);
+        return Ok(());
+    }
+
+    // Variables find-project reads keep their names, so sourcing the
+    // lines changes nothing, and are left out when they aren't set. The
+    // others are only there to be read.
+    let path = |path: &Path| path.to_string_lossy().into_owned();
+    if let Some(file) = &settings.config_file {
+        println!("FP_CONFIG={}", opts::quote(&path(file)));
+    }
+    if let Some(profile) = &settings.profile {
+        println!("FP_PROFILE={}", opts::quote(profile));
+    }
+    // Roots are joined like $PATH, the way $FP_FOLDER takes them.
+    let roots = env::join_paths(settings.roots.iter().map(|root| &root.path)).unwrap_or_default();
+    let lines = [
+        ("FP_DATA_DIR", path(&settings.data_dir)),
+        ("FP_HISTORY_DAYS", settings.history_days.to_string()),
+        ("FP_SOURCE", value_name(settings.source)),
+        ("FP_ROOTS", roots.to_string_lossy().into_owned()),
+        ("FP_MARKERS", opts::join(&settings.markers)),
+        ("FP_PRUNE", opts::join(&settings.prune)),
+        ("FP_EXCLUDE", opts::join(&settings.exclude)),
+        ("FP_INCLUDE_VENDOR", settings.include_vendor.to_string()),
+        ("FP_INCLUDE_HIDDEN", settings.include_hidden.to_string()),
+        (
+            "FP_MAX_DEPTH",
+            settings
+                .max_depth
+                .map(|d| d.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "FP_SORT_ALPHABETICALLY",
+            settings.sort_alphabetically.to_string(),
+        ),
+        ("FP_STOP_AT_PROJECTS", settings.stop_at_projects.to_string()),
+        ("FP_FOLLOW_LINKS", settings.follow_links.to_string()),
+        ("FP_NO_FOLLOW_ROOT", settings.no_follow_root.to_string()),
+        ("FP_DEFAULT_FLAGS", opts::join(&settings.default_flags)),
+        ("FP_RANK_BY", value_name(settings.rank_by)),
+    ];
+    for (name, value) in lines {
+        println!("{}={}", name, opts::quote(&value));
+    }
+    Ok(())
+}
+
+// Returns what a flag takes to select a value.
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+// Goes through the same steps as a search, printing what every one of
+// them found, so it's clear why a folder was picked. Unlike a search,
+// the visit isn't recorded.
+fn which(query: &str, args: &Args, config: &Config, log_enabled: bool) -> Result<()> {
+    let step = |label: &str, detail: &str| println!("  {:<10}  {}", label, detail);
+
+    println!("Roots");
+    let source = root_source(config)?;
+    let chosen = match args.source {
+        Some(_) => "chosen with --source",
+        None if config.source != RootSource::Auto => "chosen in the configuration file",
+        None => "the first one set up",
+    };
+    step(
+        "source",
+        &format!("{} ({})", describe_source(source, config), chosen),
+    );
+    let roots = search_roots(config, &args.roots)?;
+    for root in &roots {
+        step("root", &output_path(&root.path, args));
+    }
+
+    println!("Alias");
+    let mut folder_name = query;
+    let mut found = None;
+    match config.aliases.get(query) {
+        None => step("none", &format!("no alias named \"{}\"", query)),
+        Some(target) => match resolve_alias(target, &roots) {
+            Some(dir) => {
+                step("used", &format!("\"{}\" points to {}", query, target));
+                found = Some((dir, "the alias"));
+            }
+            None if Path::new(target).components().count() != 1 => bail!(
+                "Alias \"{}\" points to {}, which doesn't exist inside {}",
+                query,
+                target,
+                display_roots(&roots)
+            ),
+            None => {
+                step("used", &format!("searching for \"{}\" instead", target));
+                folder_name = target.as_str();
+            }
+        },
+    }
+
+    println!("Pins");
+    if found.is_some() {
+        step("skipped", "the alias points to a folder");
+    } else {
+        match Pins::load()?.lookup(folder_name, args.ignores_case()) {
+            Some(pinned) => {
+                step("used", &output_path(pinned, args));
+                found = Some((pinned.to_path_buf(), "the pinned projects"));
+            }
+            None => step(
+                "none",
+                &format!("no project named \"{}\" is pinned", folder_name),
+            ),
+        }
+    }
+
+    println!("Visits");
+    if found.is_some() {
+        step("skipped", "already found");
+    } else {
+        let db = Database::load()?;
+        let ranking = ranking(args.rank_by, config);
+        match db.best(
+            folder_name,
+            args.ignores_case(),
+            &root_paths(&roots),
+            &ranking,
+            frecency::now(),
+        ) {
+            Some(entry) => {
+                step("used", &output_path(&entry.path, args));
+                found = Some((entry.path.clone(), "the projects visited before"));
+            }
+            None => step(
+                "none",
+                &format!("no project named \"{}\" visited before", folder_name),
+            ),
+        }
+    }
+
+    println!("Walk");
+    if found.is_some() {
+        step("skipped", "already found");
+    } else {
+        for root in &roots {
+            check_interrupted()?;
+            let label = output_path(&root.path, args);
+            match root_finder(args, config, root, log_enabled).find(folder_name) {
+                Ok(Some(dir)) => {
+                    step(
+                        "found",
+                        &format!("{} in {}", output_path(&dir, args), label),
+                    );
+                    found = Some((dir, "walking the roots"));
+                    break;
+                }
+                Ok(None) => step("none", &format!("nothing in {}", label)),
+                Err(err @ FindError::RootNotAccessible { .. }) => step("skipped", &err.to_string()),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    println!("Result");
+    match found {
+        Some((dir, from)) => {
+            step("path", &output_path(&dir, args));
+            step("from", from);
+            Ok(())
+        }
+        None => bail!(
+            "Folder \"{}\" not found inside {}",
+            folder_name,
+            display_roots(&roots)
+        ),
+    }
+}
+
+// Returns the variables telling plugins where the roots and the data are,
+// as far as they can be found: a broken configuration file shouldn't stop
+// a plugin from running.
+fn plugin_env() -> Vec<(&'static str, OsString)> {
+    let mut vars = Vec::new();
+    if let Ok(exe) = env::current_exe() {
+        vars.push(("FP_EXE", exe.into_os_string()));
+    }
+    if let Some(path) = config::config_path() {
+        vars.push(("FP_CONFIG", path.into_os_string()));
+    }
+    if let Ok(dir) = store::data_dir() {
+        vars.push(("FP_DATA_DIR", dir.into_os_string()));
+    }
+    let roots = Config::load()
+        .and_then(|config| search_roots(&config, &[]))
+        .ok()
+        .and_then(|roots| env::join_paths(roots.iter().map(|root| &root.path)).ok());
+    if let Some(roots) = roots {
+        vars.push(("FP_ROOTS", roots));
+    }
+    vars
+}
+
+// Prints the spans and events of the search to stderr: -v prints the
+// folders skipped and matched, -vv everything.
+#[cfg(feature = "tracing")]
+fn init_tracing(verbose: u8) {
+    let level = match verbose {
+        0 => return,
+        1 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr)
+        .init();
+}
+
+// Parses the command-line arguments again with the default flags from
+// the configuration file and $FP_OPTS in front of them, so the ones given
+// on the command line win. The selected profile, if any, is applied to
+// the configuration first.
+fn apply_default_flags(args: Args, cli: Vec<OsString>, config: &mut Config) -> Result<Args> {
+    if let Some(profile) = args.profile.clone().or_else(|| env::var("FP_PROFILE").ok()) {
+        config.apply_profile(&profile)?;
+    }
+
+    // Default flags only apply to searches, not to subcommands.
+    if args.command.is_some() {
+        return Ok(args);
+    }
+
+    let defaults = default_flags(config)?;
+    if defaults.is_empty() {
+        return Ok(args);
+    }
+
+    let mut argv = Vec::with_capacity(cli.len() + defaults.len());
+    argv.extend(cli.first().cloned());
+    argv.extend(defaults.into_iter().map(OsString::from));
+    argv.extend(cli.into_iter().skip(1));
+    Ok(Args::parse_from(argv))
+}
+
+// Returns the flags added in front of the arguments of every search:
+// the ones from the configuration file, then the ones from $FP_OPTS.
+fn default_flags(config: &Config) -> Result<Vec<String>> {
+    let mut defaults = config.default_flags.clone();
+    if let Ok(fp_opts) = env::var("FP_OPTS") {
+        defaults.extend(opts::split(&fp_opts).context("Unable to parse $FP_OPTS")?);
+    }
+    Ok(defaults)
+}
+
+fn apply_config(args: &mut Args, config: &Config) {
+    // Boolean flags can only be turned on from the command line, so the
+    // configuration file can only turn them on too. Settings that can be
+    // overridden per root are applied in root_finder.
+    args.sort_alphabetically |= config.sort_alphabetically;
+    args.stop_at_projects |= config.stop_at_projects;
+    args.include_system |= config.include_system;
+    args.prune.extend(config.prune.iter().cloned());
+    args.exclude.extend(config.exclude.iter().cloned());
+    if args.markers.is_empty() {
+        args.markers = config.markers();
+    }
+}
+
+// Returns the flag set by Ctrl-C.
+fn interrupted() -> Arc<AtomicBool> {
+    static INTERRUPTED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    INTERRUPTED.get_or_init(Arc::default).clone()
+}
+
+fn check_interrupted() -> Result<()> {
+    if interrupted().load(Ordering::Relaxed) {
+        return Err(FindError::Cancelled.into());
+    }
+    Ok(())
+}
+
+// Returns the finder for a specific root, with the command-line flags
+// first, then the settings of the root, then the global configuration.
+fn root_finder(args: &Args, config: &Config, root: &Root, log_enabled: bool) -> Finder {
+    let mut prune = args.prune.clone();
+    prune.extend(root.prune.iter().cloned());
+    let mut exclude = args.exclude.clone();
+    exclude.extend(root.exclude.iter().cloned());
+    if cfg!(target_os = "macos") && !args.include_system {
+        if let Some(home) = env::var_os("HOME") {
+            exclude.extend(macos_exclusions(&root.path, Path::new(&home)));
+        }
+    }
+
+    Finder::new(FinderOptions {
+        root: root.path.clone(),
+        include_vendor: args.include_vendor || root.include_vendor.unwrap_or(config.include_vendor),
+        include_hidden: args.include_hidden || root.include_hidden.unwrap_or(config.include_hidden),
+        sort_alphabetically: args.sort_alphabetically
+            || matches!(
+                args.list.sort,
+                Some(SortOrder::Lexical | SortOrder::Natural | SortOrder::Locale)
+            ),
+        collation: match args.list.sort {
+            Some(SortOrder::Natural) => Collation::Natural,
+            Some(SortOrder::Locale) => Collation::Locale,
+            _ => Collation::Lexical,
+        },
+        max_depth: args.max_depth.or(root.max_depth),
+        prune,
+        exclude,
+        markers: args.markers.clone(),
+        follow_links: args.follow_links,
+        ignore_case: args.ignores_case(),
+        matcher: if args.fuzzy {
+            Matcher::Fuzzy
+        } else {
+            Matcher::Exact
+        },
+        projects_only: args.projects_only,
+        stop_at_projects: args.stop_at_projects,
+        skip_worktrees: args.skip_worktrees,
+        branch: args.branch.clone(),
+        log_enabled,
+        cancel: Some(interrupted()),
+        threads: args.threads.unwrap_or(1),
+        io_uring: args.io_uring,
+        max_memory: args.max_memory.map(|bytes| bytes as usize),
+        manifest: args.manifest(),
+        contains: match (&args.contains, &args.contains_regex) {
+            (Some(text), _) => Some(Content::Text(text.clone())),
+            (None, Some(regex)) => Some(Content::Regex(regex.clone())),
+            (None, None) => None,
+        },
+        min_files: args.min_files,
+        max_files: args.max_files,
+        kind: match args.entry_type {
+            EntryType::Dir => Kind::Dir,
+            EntryType::File => Kind::File,
+            EntryType::Any => Kind::Any,
+        },
+    })
+}
+
+// Folders macOS fills with application data under the home folder, which
+// are slow to search and never hold projects. They're only skipped when
+// the root is the home folder or a folder above it.
+fn macos_exclusions(root: &Path, home: &Path) -> Vec<String> {
+    if !home.starts_with(root) {
+        return Vec::new();
+    }
+    let mut exclude = vec!["*.app".to_string(), "*.photoslibrary".to_string()];
+    if let Some(name) = home.file_name() {
+        let name = name.to_string_lossy();
+        exclude.push(format!("{}/Library", name));
+        exclude.push(format!("{}/.Trash", name));
+    }
+    exclude
+}
+
+fn ranking(rank_by: Option<RankBy>, config: &Config) -> Ranking {
+    let mut ranking = config.ranking();
+    if let Some(rank_by) = rank_by {
+        ranking.rank_by = rank_by;
+    }
+    ranking
+}
+
+// Returns the roots to search with their paths made absolute. When names
+// are given, only the configured roots with those names are returned.
+// Roots that can't be reached are skipped, see reachable_roots.
+fn search_roots(config: &Config, names: &[String]) -> Result<Vec<Root>> {
+    for name in names {
+        if !config.roots.iter().any(|r| r.name.as_ref() == Some(name)) {
+            bail!(
+                "There's no root named \"{}\" in the configuration file",
+                name
+            );
+        }
+    }
+
+    match root_source(config)? {
+        RootSource::Config => reachable_roots(
+            config
+                .roots
+                .iter()
+                .filter(|r| names.is_empty() || r.name.as_ref().is_some_and(|n| names.contains(n)))
+                .map(|root| {
+                    let path = root.search_path();
+                    Ok(Root {
+                        path: root_path(&path, config).with_context(|| {
+                            format!("Unable to get absolute path to {:?}", path)
+                        })?,
+                        gopath: false,
+                        ..root.clone()
+                    })
+                })
+                .collect(),
+        ),
+        _ if !names.is_empty() => {
+            bail!("--root only selects roots from the configuration file, see --source")
+        }
+        RootSource::Env => {
+            // Like $PATH, $FP_FOLDER holds several folders separated by
+            // ";" on Windows, as in "D:\code;E:\work", and ":" elsewhere.
+            let location = env::var_os("FP_FOLDER").context("$FP_FOLDER isn't set")?;
+            reachable_roots(
+                env::split_paths(&location)
+                    .filter(|path| !path.as_os_str().is_empty())
+                    .map(|path| {
+                        Ok(Root {
+                            path: root_path(&path, config).with_context(|| {
+                                format!("Unable to get absolute path to {:?} in $FP_FOLDER", path)
+                            })?,
+                            ..Default::default()
+                        })
+                    })
+                    .collect(),
+            )
+        }
+        RootSource::Gopath | RootSource::Auto => {
+            let location = env::var("GOPATH").context("$GOPATH isn't set")?;
+            Ok(vec![Root {
+                path: root_path(&Path::new(&location).join("src"), config)
+                    .context("Unable to get absolute path to $GOPATH/src")?,
+                ..Default::default()
+            }])
+        }
+    }
+}
+
+// Returns the roots kept in the cell, resolving them the first time.
+fn lazy_roots<'a>(
+    cell: &'a OnceCell<Vec<Root>>,
+    config: &Config,
+    names: &[String],
+) -> Result<&'a [Root]> {
+    if let Some(roots) = cell.get() {
+        return Ok(roots);
+    }
+    let roots = search_roots(config, names)?;
+    Ok(cell.get_or_init(|| roots))
+}
+
+// Returns the roots that can be searched, warning about the ones that
+// can't, so a disconnected drive or network share doesn't stop the search
+// in the others. It's only an error when none of them can be searched.
+fn reachable_roots(roots: Vec<Result<Root>>) -> Result<Vec<Root>> {
+    let (reachable, unreachable): (Vec<_>, Vec<_>) = roots.into_iter().partition(Result::is_ok);
+    let mut errors = unreachable.into_iter().filter_map(Result::err);
+    if reachable.is_empty() {
+        return match errors.next() {
+            Some(err) => Err(err),
+            None => bail!("There are no roots to search"),
+        };
+    }
+    for err in errors {
+        eprintln!("Skipping root: {:#}", err);
+    }
+    reachable.into_iter().collect()
+}
+
+// Returns where the roots come from. With "auto", that's the first one
+// set up: roots in the configuration file win over the environment
+// variables, since they have to be set up on purpose, and $FP_FOLDER
+// wins over $GOPATH, which is only kept for backwards compatibility.
+fn root_source(config: &Config) -> Result<RootSource> {
+    match config.source {
+        RootSource::Auto if !config.roots.is_empty() => Ok(RootSource::Config),
+        RootSource::Auto if env::var("FP_FOLDER").is_ok() => Ok(RootSource::Env),
+        RootSource::Auto if env::var("GOPATH").is_ok() => Ok(RootSource::Gopath),
+        RootSource::Auto => bail!(
+            "Please set the $FP_FOLDER environment variable or the $GOPATH \
+            environment variable to a location that find-project can search. \
+            Neither are set."
+        ),
+        RootSource::Config if config.roots.is_empty() => {
+            bail!("--source config needs roots in the configuration file")
+        }
+        source => Ok(source),
+    }
+}
+
+// Explains which roots are searched, mentioning when $GOPATH is set but
+// ignored, since it used to win over $FP_FOLDER.
+fn describe_source(source: RootSource, config: &Config) -> String {
+    match source {
+        RootSource::Config => "Searching the roots from the configuration file".to_string(),
+        RootSource::Env if config.source == RootSource::Auto && env::var("GOPATH").is_ok() => {
+            "Searching $FP_FOLDER, $GOPATH is set too but it's only searched \
+            with --source gopath"
+                .to_string()
+        }
+        RootSource::Env => "Searching $FP_FOLDER".to_string(),
+        RootSource::Gopath | RootSource::Auto => "Searching $GOPATH/src".to_string(),
+    }
+}
+
+// Returns the folder an alias points to: either an absolute path or a
+// path relative to one of the roots, as long as it exists.
+fn resolve_alias(target: &str, roots: &[Root]) -> Option<PathBuf> {
+    let target = Path::new(target);
+    if target.is_absolute() {
+        return Some(target.to_path_buf()).filter(|dir| dir.is_dir());
+    }
+
+    roots
+        .iter()
+        .map(|root| root.path.join(target))
+        .find(|dir| dir.is_dir())
+}
+
+fn root_paths(roots: &[Root]) -> Vec<PathBuf> {
+    roots.iter().map(|root| root.path.clone()).collect()
+}
+
+// Makes a root absolute. Its symlinks are resolved too unless it should
+// be kept as configured, so tools keyed on the path a project is reached
+// from, like direnv, see the same path in the output.
+fn root_path(path: &Path, config: &Config) -> io::Result<PathBuf> {
+    if !config.no_follow_root {
+        return absolute(path);
+    }
+    let path = std::path::absolute(path)?;
+    if !path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{:?} isn't a folder", path),
+        ));
+    }
+    Ok(path)
+}
+
+// Resolves symlinks and relative parts of a root.
+#[cfg(not(target_os = "wasi"))]
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    paths::canonicalize(path)
+}
+
+// WASI can't resolve paths, so roots are used as given. They're the
+// directories preopened by the runtime, like "/projects" after
+// `wasmtime --dir ~/Projects::/projects`.
+#[cfg(target_os = "wasi")]
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    if !path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{:?} isn't a preopened directory", path),
+        ));
+    }
+    Ok(path.to_path_buf())
+}
+
+fn display_roots(roots: &[Root]) -> String {
+    roots
+        .iter()
+        .map(|root| root.path.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn find_in_roots(
+    roots: &[Root],
+    name: &str,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<Option<PathBuf>> {
+    // Every root is searched at the same time, but the first root with a
+    // match still wins, as if they were searched one after the other. Once
+    // it's known, the searches still going on are stopped instead of
+    // waited for, so a big root after the one with the match doesn't hold
+    // it up.
+    let stop = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for (i, root) in roots.iter().enumerate() {
+            let finder = Finder::new(FinderOptions {
+                cancel: Some(stop.clone()),
+                ..root_finder(args, config, root, log_enabled)
+                    .options()
+                    .clone()
+            });
+            let sender = sender.clone();
+            scope.spawn(move || {
+                let found = panic::catch_unwind(AssertUnwindSafe(|| finder.find(name)))
+                    .unwrap_or_else(|_| {
+                        Err(FindError::Panicked {
+                            path: root.path.clone(),
+                        })
+                    });
+                // Nobody is listening anymore once a root won.
+                let _ = sender.send((i, found));
+            });
+        }
+        drop(sender);
+
+        let found = first_found(roots, &receiver);
+        stop.store(true, Ordering::Relaxed);
+        found
+    })
+}
+
+// Returns the match of the first root with one, out of the results of
+// the searches in every root, which come in the order they finish.
+fn first_found(
+    roots: &[Root],
+    receiver: &mpsc::Receiver<(usize, Result<Option<PathBuf>, FindError>)>,
+) -> Result<Option<PathBuf>> {
+    let mut results: Vec<_> = roots.iter().map(|_| None).collect();
+    for i in 0..roots.len() {
+        let found = loop {
+            if let Some(found) = results[i].take() {
+                break found;
+            }
+            // The searches stop on their own after Ctrl-C only once they
+            // know, so it's checked while waiting for them.
+            match receiver.recv_timeout(Duration::from_millis(50)) {
+                Ok((j, found)) => results[j] = Some(found),
+                Err(mpsc::RecvTimeoutError::Timeout) => check_interrupted()?,
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    unreachable!("every search sends its result")
+                }
+            }
+        };
+        match found {
+            Ok(Some(found)) => return Ok(Some(found)),
+            Ok(None) => {}
+            // A drive disconnected since the roots were listed only stops
+            // the search in that root.
+            Err(err @ FindError::RootNotAccessible { .. }) => eprintln!("Skipping root: {}", err),
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(None)
+}
+
+// Answers searches over HTTP until Ctrl-C. GET /search?q=NAME returns
+// every folder matching the name and GET /projects every project root,
+// like --all and --json do, and GET /metrics counts them for Prometheus.
+// Only loopback addresses are allowed, since the answers reveal every
+// project on the machine.
+fn serve(address: &str, args: &Args, config: &Config) -> Result<()> {
+    let address = loopback_address(address)?;
+    let listener =
+        TcpListener::bind(address).with_context(|| format!("Unable to listen on {}", address))?;
+    eprintln!("Listening on http://{}", address);
+
+    let mut metrics = serve::Metrics::default();
+    serve::http(&listener, &interrupted(), |request| {
+        let response = answer_http(request, args, config, &mut metrics);
+        if response.status >= 400 {
+            metrics.error(response.status);
+        }
+        response
+    })?;
+    Ok(())
+}
+
+// Parses the address "serve --http" listens on, refusing the ones other
+// machines can reach.
+fn loopback_address(address: &str) -> Result<SocketAddr> {
+    let address: SocketAddr = address
+        .parse()
+        .with_context(|| format!("{:?} isn't an address like 127.0.0.1:7433", address))?;
+    if !address.ip().is_loopback() {
+        bail!(
+            "{} isn't a loopback address, only 127.0.0.1 or [::1] are allowed",
+            address.ip()
+        );
+    }
+    Ok(address)
+}
+
+// Answers a request to "serve --http", counting searches in metrics.
+fn answer_http(
+    request: &serve::Request,
+    args: &Args,
+    config: &Config,
+    metrics: &mut serve::Metrics,
+) -> serve::Response {
+    let name = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/search") => match request.param("q") {
+            Some(name) if !name.is_empty() => name,
+            _ => return serve::Response::error(400, "Missing the name to search for, as ?q="),
+        },
+        ("GET", "/projects") => "",
+        ("GET", "/metrics") => return serve::Response::metrics(metrics.render()),
+        (_, "/search" | "/projects" | "/metrics") => {
+            return serve::Response::error(405, "Only GET requests are answered")
+        }
+        _ => return serve::Response::error(404, "Not found"),
+    };
+
+    let started = Instant::now();
+    match served_matches(name, args, config) {
+        Ok(listed) => {
+            metrics.search(started.elapsed().as_secs_f64(), listed.len());
+            match serde_json::to_string(&listed) {
+                Ok(body) => serve::Response::json(200, body),
+                Err(err) => serve::Response::error(500, &err.to_string()),
+            }
+        }
+        Err(err) => serve::Response::error(500, &format!("{:#}", err)),
+    }
+}
+
+// Searches every root for a request to serve, returning the folders
+// found as "list --json" prints them.
+fn served_matches(name: &str, args: &Args, config: &Config) -> Result<Vec<Listed>> {
+    let roots = search_roots(config, &args.roots)?;
+    let dirs = find_all_in_roots(&roots, name, args, config, false)?;
+    let db = Database::load()?;
+    Ok(dirs
+        .into_iter()
+        .map(|(root, dir)| served_match(root, &dir, &db, args))
+        .collect())
+}
+
+// Returns a folder found for "serve", as "list --json" prints it.
+fn served_match(root: &Path, dir: &Path, db: &Database, args: &Args) -> Listed {
+    Listed {
+        path: output_path(dir, args),
+        root: root.to_path_buf(),
+        last_visit: db
+            .entries()
+            .iter()
+            .find(|entry| entry.path == dir)
+            .map(|entry| entry.last_visit),
+        status: None,
+        worktree_of: git::main_worktree(dir),
+        group: None,
+    }
+}
+
+// Answers JSON-RPC calls from editors on stdin, one per line, until it's
+// closed. "search" and "list" send a "match" notification for every
+// folder as it's found, then answer with all of them.
+fn serve_stdio(args: &Args, config: &Config) -> Result<()> {
+    serve::stdio(io::stdin().lock(), io::stdout().lock(), |call, replies| {
+        let params = &call.params;
+        match call.method.as_str() {
+            "search" | "list" => {
+                let name = match (call.method.as_str(), params.name.as_deref()) {
+                    ("list", _) => "",
+                    (_, Some(name)) if !name.is_empty() => name,
+                    _ => return replies.error(serve::INVALID_PARAMS, "Missing the name"),
+                };
+                let mut search_args = args.clone();
+                if !params.roots.is_empty() {
+                    search_args.roots = params.roots.clone();
+                }
+                let roots = search_roots(config, &search_args.roots)?;
+                let db = Database::load()?;
+                let mut found = Vec::new();
+                stream_matches(&roots, name, &search_args, config, |root, dir| {
+                    let listed = served_match(root, &dir, &db, args);
+                    replies.notify("match", &listed)?;
+                    found.push(listed);
+                    Ok(())
+                })?;
+                replies.result(&found)
+            }
+            "record-visit" => {
+                let Some(path) = &params.path else {
+                    return replies.error(serve::INVALID_PARAMS, "Missing the path");
+                };
+                if !path.is_dir() {
+                    bail!("{:?} isn't a folder", path);
+                }
+                let mut db = Database::load()?;
+                db.record(path, frecency::now());
+                db.save()?;
+                forget_misses()?;
+                replies.result(&())
+            }
+            // There's no index to rebuild, but searches that found
+            // nothing may be remembered, so they're searched again.
+            "reindex" => {
+                forget_misses()?;
+                replies.result(&())
+            }
+            method => replies.error(
+                serve::METHOD_NOT_FOUND,
+                &format!("Unknown method {:?}", method),
+            ),
+        }
+    })
+}
+
+// Like find_in_roots, but when the same search found nothing less than
+// not_found_cache_seconds ago, it finds nothing again without searching.
+// Searches that find nothing are remembered for that long.
+fn find_or_remember(
+    roots: &[Root],
+    name: &str,
+    args: &Args,
+    config: &Config,
+    now: u64,
+    log_enabled: bool,
+) -> Result<Option<PathBuf>> {
+    let ttl = config.not_found_cache_seconds;
+    if ttl == 0 {
+        return find_in_roots(roots, name, args, config, log_enabled);
+    }
+
+    let Some(mut misses) = load_state(Misses::load())? else {
+        return find_in_roots(roots, name, args, config, log_enabled);
+    };
+    let key = miss_key(roots, name, args, config);
+    if let Some(time) = misses.get(&key, now, ttl) {
+        if log_enabled {
+            eprintln!(
+                "Not searching again, the same search found nothing {}s ago",
+                now.saturating_sub(time)
+            );
+        }
+        return Ok(None);
+    }
+    let found = find_in_roots(roots, name, args, config, log_enabled)?;
+    if found.is_none() {
+        misses.record(&key, now, ttl);
+        if let Err(err) = misses.save() {
+            eprintln!("Warning: unable to remember the search: {}", err);
+        }
+    }
+    Ok(found)
+}
+
+// Forgets every search that found nothing, so the next ones search
+// again, like after adding a project one of them may have looked for.
+fn forget_misses() -> Result<()> {
+    let mut misses = Misses::load()?;
+    misses.clear();
+    misses.save()?;
+    Ok(())
+}
+
+// Returns a key for a search, covering the name and the options it runs
+// with in every root. The last time each root changed is part of it too,
+// so a project cloned right inside a root is found right away. Folders
+// deeper inside aren't looked at, since that would mean walking the
+// roots, so a project added there, like root/github.com/org/api, isn't
+// found until the miss expires, or misses are forgotten by cloning or
+// recording a visit.
+fn miss_key(roots: &[Root], name: &str, args: &Args, config: &Config) -> String {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    for root in roots {
+        let options = FinderOptions {
+            log_enabled: false,
+            cancel: None,
+            ..root_finder(args, config, root, false).options().clone()
+        };
+        format!("{:?}", options).hash(&mut hasher);
+        fs::metadata(&root.path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+// Prints every folder matching the query in all roots, or every project
+// root when there's no query. Nothing is recorded, since none of them is
+// being visited.
+fn list_matches(
+    query: &str,
+    roots: &[Root],
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    let folder_name = match config.aliases.get(query) {
+        Some(target) if Path::new(target).components().count() == 1 => target.as_str(),
+        _ => query,
+    };
+    let list = &args.list;
+
+    // After Ctrl-C, the matches found so far are still printed.
+    let mut dirs = Vec::new();
+    let cancelled =
+        match extend_with_matches(&mut dirs, roots, folder_name, args, config, log_enabled) {
+            Ok(()) => false,
+            Err(FindError::Cancelled) => true,
+            Err(err) => return Err(err.into()),
+        };
+
+    if args.fuzzy && list.sort.is_none() {
+        rank_by_fuzzy_score(&mut dirs, folder_name);
+    }
+    if list.sort == Some(SortOrder::LastCommit) {
+        let mut times = Vec::with_capacity(dirs.len());
+        for (root, dir) in dirs {
+            times.push((vcs::last_commit_time(&dir)?, root, dir));
+        }
+        // Newest first, with folders that aren't repositories last.
+        times.sort_by_key(|(time, _, _)| Reverse(*time));
+        dirs = times
+            .into_iter()
+            .map(|(_, root, dir)| (root, dir))
+            .collect();
+    }
+
+    let db = Database::load()?;
+    let found = !dirs.is_empty();
+    let mut groups: BTreeMap<String, Vec<Listed>> = BTreeMap::new();
+    for (root, dir) in dirs {
+        let status = if list.git_status || list.dirty || list.clean {
+            vcs::status(&dir)?
+        } else {
+            None
+        };
+        if (list.dirty || list.clean)
+            && status
+                .as_ref()
+                .is_none_or(|status| status.dirty != list.dirty)
+        {
+            continue;
+        }
+
+        let group = match list.group_by {
+            Some(GroupBy::Org) => organization(root, &dir),
+            None => String::new(),
+        };
+        let listed = Listed {
+            path: output_path(&dir, args),
+            root: root.to_path_buf(),
+            last_visit: db
+                .entries()
+                .iter()
+                .find(|entry| entry.path == dir)
+                .map(|entry| entry.last_visit),
+            status: status.filter(|_| list.git_status),
+            worktree_of: (list.long || list.git_status || list.json)
+                .then(|| git::main_worktree(&dir))
+                .flatten(),
+            group: list.group_by.map(|_| group.clone()),
+        };
+        groups.entry(group).or_default().push(listed);
+    }
+
+    if groups.is_empty() {
+        if cancelled {
+            return Err(FindError::Cancelled.into());
+        }
+        let state = if list.dirty { "dirty" } else { "clean" };
+        let roots = display_roots(roots);
+        match (found, folder_name.is_empty()) {
+            (false, false) => eprintln!("Folder \"{}\" not found inside {}", folder_name, roots),
+            (false, true) => eprintln!("No projects found inside {}", roots),
+            (true, false) => eprintln!(
+                "No {} repository named \"{}\" found inside {}",
+                state, folder_name, roots
+            ),
+            (true, true) => eprintln!("No {} repositories found inside {}", state, roots),
+        }
+        process::exit(1);
+    }
+
+    if list.json {
+        let listed: Vec<_> = groups.into_values().flatten().collect();
+        println!("{}", serde_json::to_string_pretty(&listed)?);
+    } else if list.print0 {
+        let mut stdout = io::stdout().lock();
+        for listed in groups.values().flatten() {
+            write!(stdout, "{}\0", listed.line(list))?;
+        }
+        stdout.flush()?;
+    } else {
+        for (i, (group, listed)) in groups.iter().enumerate() {
+            if list.group_by.is_none() {
+                listed.iter().for_each(|l| println!("{}", l.line(list)));
+                continue;
+            }
+
+            if i > 0 {
+                println!();
+            }
+            println!("{}", group);
+            listed.iter().for_each(|l| println!("  {}", l.line(list)));
+        }
+    }
+    if cancelled {
+        return Err(FindError::Cancelled.into());
+    }
+    Ok(())
+}
+
+// Prints the man page of the command, or writes the pages of the command
+// and of every subcommand, like find-project-list.1, into a folder for
+// packages to install.
+fn man(dir: Option<&Path>) -> Result<()> {
+    let command = Args::command();
+    match dir {
+        Some(dir) => {
+            fs::create_dir_all(dir).with_context(|| format!("Unable to create {:?}", dir))?;
+            clap_mangen::generate_to(command, dir)
+                .with_context(|| format!("Unable to write the man pages into {:?}", dir))
+        }
+        None => clap_mangen::Man::new(command)
+            .render(&mut io::stdout())
+            .context("Unable to print the man page"),
+    }
+}
+
+// Defines "use find_project NAME" for .envrc files, which loads the
+// .envrc of the project found for NAME, so projects can share one.
+const DIRENV_STDLIB: &str = r#"# Added by "find-project direnv --stdlib": "use find_project NAME" in an
+# .envrc loads the .envrc of the project find-project finds for NAME.
+use_find_project() {
+  local dir
+  dir=$(find-project "$@") || return
+  source_env "$dir"
+}
+"#;
+
+// Returns the shell code "direnv" prints: going to the folder, then
+// loading its .envrc right away instead of at the next prompt, so a
+// command chained after it already sees its variables.
+fn direnv_script(dir: &str, shell: DirenvShell) -> String {
+    match shell {
+        DirenvShell::Bash | DirenvShell::Zsh => {
+            let shell = if shell == DirenvShell::Bash {
+                "bash"
+            } else {
+                "zsh"
+            };
+            format!(
+                "cd -- {} && eval \"$(direnv export {})\"",
+                opts::quote(dir),
+                shell
+            )
+        }
+        DirenvShell::Fish => format!(
+            "cd '{}'; and direnv export fish | source",
+            dir.replace('\\', "\\\\").replace('\'', "\\'")
+        ),
+    }
+}
+
+// Opens a folder in the file manager of the platform. Under WSL, that's
+// the Windows one.
+fn open_in_file_manager(dir: &Path) -> Result<()> {
+    let windows_path = paths::is_wsl()
+        .then(|| {
+            paths::wsl_to_windows(
+                &dir.to_string_lossy(),
+                env::var("WSL_DISTRO_NAME").ok().as_deref(),
+            )
+        })
+        .flatten();
+    let (program, target) = match windows_path {
+        Some(path) => ("explorer.exe", OsString::from(path)),
+        None if cfg!(target_os = "macos") => ("open", dir.into()),
+        None if cfg!(windows) => ("explorer", dir.into()),
+        None => ("xdg-open", dir.into()),
+    };
+
+    let status = process::Command::new(program)
+        .arg(&target)
+        .status()
+        .with_context(|| format!("Unable to run {}", program))?;
+    // Explorer exits with 1 even when the folder was opened.
+    if !status.success() && !program.starts_with("explorer") {
+        bail!(
+            "Unable to open {:?}: {} exited with {}",
+            dir,
+            program,
+            status
+        );
+    }
+    Ok(())
+}
+
+// Returns a path as it should be printed, translated for the shell on the
+// other side of WSL when asked to. Paths that can't be translated are
+// printed as they are.
+fn output_path(path: &Path, args: &Args) -> String {
+    let display = path.display().to_string();
+    let translated = match args.translate_path {
+        Some(PathStyle::Windows) => {
+            paths::wsl_to_windows(&display, env::var("WSL_DISTRO_NAME").ok().as_deref())
+        }
+        Some(PathStyle::Wsl) => paths::windows_to_wsl(&display),
+        None => None,
+    };
+    translated.unwrap_or(display)
+}
+
+// Returns the organization a project belongs to, like
+// "github.com/kubernetes", from its origin remote. Projects without one
+// use the folder they're in, relative to the root.
+fn organization(root: &Path, dir: &Path) -> String {
+    if let Some(remote) =
+        git::origin_url(dir).and_then(|url| clone::Remote::parse(&url, clone::DEFAULT_HOST).ok())
+    {
+        return format!("{}/{}", remote.host, remote.org);
+    }
+
+    match dir
+        .parent()
+        .and_then(|parent| parent.strip_prefix(root).ok())
+    {
+        Some(parent) if parent.as_os_str().is_empty() => "(no organization)".to_string(),
+        Some(parent) => parent
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        None => "(no organization)".to_string(),
+    }
+}
+
+// Returns every folder matching the name in all roots, along with the
+// root it was found in.
+fn find_all_in_roots<'a>(
+    roots: &'a [Root],
+    name: &str,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<Vec<(&'a Path, PathBuf)>> {
+    let mut dirs = Vec::new();
+    extend_with_matches(&mut dirs, roots, name, args, config, log_enabled)?;
+    Ok(dirs)
+}
+
+// Adds every folder matching the name in all roots to dirs, so the ones
+// found before the search fails are kept. Roots are searched at the same
+// time, and their matches added in the order of the roots. With
+// --max-per-root, a root stops being searched once it has that many.
+fn extend_with_matches<'a>(
+    dirs: &mut Vec<(&'a Path, PathBuf)>,
+    roots: &'a [Root],
+    name: &str,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<(), FindError> {
+    let limit = args.list.max_per_root.unwrap_or(usize::MAX);
+    let searches: Vec<(Vec<PathBuf>, Option<FindError>)> = thread::scope(|scope| {
+        let searches: Vec<_> = roots
+            .iter()
+            .map(|root| {
+                let finder = root_finder(args, config, root, log_enabled);
+                scope.spawn(move || {
+                    let mut found = Vec::new();
+                    for dir in finder.iter(name).take(limit) {
+                        match dir {
+                            Ok(dir) => found.push(dir),
+                            Err(err) => return (found, Some(err)),
+                        }
+                    }
+                    (found, None)
+                })
+            })
+            .collect();
+        // A search that panicked fails like one that couldn't go on.
+        roots
+            .iter()
+            .zip(searches)
+            .map(|(root, search)| {
+                search.join().unwrap_or_else(|_| {
+                    let path = root.path.clone();
+                    (Vec::new(), Some(FindError::Panicked { path }))
+                })
+            })
+            .collect()
+    });
+
+    let mut seen = HashSet::new();
+    let mut failed = None;
+    for (root, (found, err)) in roots.iter().zip(searches) {
+        match err {
+            Some(err @ FindError::RootNotAccessible { .. }) => {
+                eprintln!("Skipping root: {}", err)
+            }
+            // A failed root doesn't stop the others, but the first
+            // failure is still returned once every match is added.
+            Some(err) => {
+                failed.get_or_insert(err);
+            }
+            None => {}
+        }
+        for dir in found {
+            if seen.insert(match_key(&dir, args)) {
+                dirs.push((root.path.as_path(), dir));
+            }
+        }
+    }
+    failed.map_or(Ok(()), Err)
+}
+
+// Returns what tells a folder found apart from the others. Overlapping
+// roots, or symlinks like "current" pointing to "releases/2024", reach the
+// same folder twice. Folders that vanished since are compared by path,
+// written with a different case where the file system ignores case.
+fn match_key(dir: &Path, args: &Args) -> paths::FileId {
+    paths::file_id(dir).unwrap_or_else(|_| {
+        paths::FileId::Path(if args.ignores_case() {
+            PathBuf::from(dir.to_string_lossy().to_lowercase())
+        } else {
+            dir.to_path_buf()
+        })
+    })
+}
+
+// Calls found with every folder matching the name as soon as it's found,
+// one root after the other, skipping the folders already found like
+// extend_with_matches does.
+fn stream_matches(
+    roots: &[Root],
+    name: &str,
+    args: &Args,
+    config: &Config,
+    mut found: impl FnMut(&Path, PathBuf) -> Result<()>,
+) -> Result<()> {
+    let mut seen = HashSet::new();
+    for root in roots {
+        for dir in root_finder(args, config, root, false).iter(name) {
+            let dir = match dir {
+                Ok(dir) => dir,
+                Err(err @ FindError::RootNotAccessible { .. }) => {
+                    eprintln!("Skipping root: {}", err);
+                    break;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            if seen.insert(match_key(&dir, args)) {
+                found(&root.path, dir)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// A VS Code workspace, as a .code-workspace file holds it.
+#[derive(serde::Serialize, Debug, PartialEq)]
+struct Workspace {
+    folders: Vec<WorkspaceFolder>,
+}
+
+#[derive(serde::Serialize, Debug, PartialEq)]
+struct WorkspaceFolder {
+    name: String,
+    path: String,
+}
+
+// An item of a VS Code QuickPick: the folder name, where it is inside
+// its root, and its path.
+#[derive(serde::Serialize, Debug, PartialEq)]
+struct QuickPickItem {
+    label: String,
+    description: String,
+    detail: String,
+}
+
+// Finds the folders matching the query inside the roots, or every
+// repository when there's no query, without looking inside them.
+fn find_projects<'a>(
+    roots: &'a [Root],
+    query: Option<&str>,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<Vec<(&'a Path, PathBuf)>> {
+    let mut search_args = args.clone();
+    if query.is_none() {
+        search_args.markers = config::VCS_MARKERS.iter().map(|m| m.to_string()).collect();
+        search_args.stop_at_projects = true;
+    }
+    find_all_in_roots(
+        roots,
+        query.unwrap_or_default(),
+        &search_args,
+        config,
+        log_enabled,
+    )
+}
+
+// Fails when find_projects found nothing, naming the query if there was
+// one.
+fn ensure_found(dirs: &[(&Path, PathBuf)], query: Option<&str>, roots: &[Root]) -> Result<()> {
+    match query {
+        _ if !dirs.is_empty() => Ok(()),
+        Some(name) => bail!(
+            "Folder \"{}\" not found inside {}",
+            name,
+            display_roots(roots)
+        ),
+        None => bail!("No repositories found inside {}", display_roots(roots)),
+    }
+}
+
+// Prints the matching folders, or every repository when there's no
+// query, in a format the editor reads.
+fn export(
+    editor: Editor,
+    output: Option<ExportOutput>,
+    query: Option<&str>,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    let roots = search_roots(config, &args.roots)?;
+    let dirs = find_projects(&roots, query, args, config, log_enabled)?;
+    match (editor, output) {
+        (Editor::Vscode, None | Some(ExportOutput::Workspace)) => println!(
+            "{}",
+            serde_json::to_string_pretty(&vscode_workspace(&dirs, args))?
+        ),
+        (Editor::Vscode, Some(ExportOutput::VscodeQuickpick)) => {
+            println!("{}", serde_json::to_string(&vscode_quickpick(&dirs, args))?)
+        }
+        (Editor::Emacs, None | Some(ExportOutput::ProjectEl)) => {
+            print!("{}", project_el_list(&dirs, args))
+        }
+        (Editor::Emacs, Some(ExportOutput::Projectile)) => {
+            print!("{}", projectile_bookmarks(&dirs, args))
+        }
+        (editor, Some(output)) => bail!(
+            "--output {} can't be used when exporting for {}",
+            value_name(output),
+            value_name(editor)
+        ),
+    }
+    Ok(())
+}
+
+// Returns the list of projects project.el keeps in
+// ~/.emacs.d/projects: a list with one list per project, holding its
+// folder with a trailing slash.
+fn project_el_list(dirs: &[(&Path, PathBuf)], args: &Args) -> String {
+    let mut list = String::from(";;; -*- lisp-data -*-\n(");
+    for (i, (_, dir)) in dirs.iter().enumerate() {
+        if i > 0 {
+            list.push_str("\n ");
+        }
+        list.push_str(&format!("({})", elisp_folder(dir, args)));
+    }
+    list.push_str(")\n");
+    list
+}
+
+// Returns the list of projects Projectile keeps in
+// ~/.emacs.d/projectile-bookmarks.eld: one string per project.
+fn projectile_bookmarks(dirs: &[(&Path, PathBuf)], args: &Args) -> String {
+    let folders: Vec<String> = dirs
+        .iter()
+        .map(|(_, dir)| elisp_folder(dir, args))
+        .collect();
+    format!("({})\n", folders.join(" "))
+}
+
+// Quotes a folder as an Emacs Lisp string, with the trailing slash Emacs
+// uses to tell folders apart.
+fn elisp_folder(dir: &Path, args: &Args) -> String {
+    let mut path = output_path(dir, args);
+    if !path.ends_with(['/', '\\']) {
+        path.push('/');
+    }
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Names every folder of the workspace after where it is inside its root,
+// since VS Code shows only the name and projects often share one.
+fn vscode_workspace(dirs: &[(&Path, PathBuf)], args: &Args) -> Workspace {
+    let folders = dirs
+        .iter()
+        .map(|(root, dir)| WorkspaceFolder {
+            name: dir.strip_prefix(root).unwrap_or(dir).display().to_string(),
+            path: output_path(dir, args),
+        })
+        .collect();
+    Workspace { folders }
+}
+
+fn vscode_quickpick(dirs: &[(&Path, PathBuf)], args: &Args) -> Vec<QuickPickItem> {
+    dirs.iter()
+        .map(|(root, dir)| QuickPickItem {
+            label: dir
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+            description: dir.strip_prefix(root).unwrap_or(dir).display().to_string(),
+            detail: output_path(dir, args),
+        })
+        .collect()
+}
+
+// Prints how many folders match the query, or how many repositories
+// there are without one, and with stats, how many of them each root has
+// first. Nothing else is printed, so scripts can compare the number.
+fn count(
+    query: Option<&str>,
+    stats: bool,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    let roots = search_roots(config, &args.roots)?;
+    let dirs = find_projects(&roots, query, args, config, log_enabled)?;
+    if stats {
+        for (root, count) in count_by_root(&roots, &dirs) {
+            println!("{}\t{}", count, root.display());
+        }
+    }
+    println!("{}", dirs.len());
+    Ok(())
+}
+
+// Returns how many of the folders are in each root, in the order of the
+// roots, including the ones with none.
+fn count_by_root<'a>(roots: &'a [Root], dirs: &[(&Path, PathBuf)]) -> Vec<(&'a Path, usize)> {
+    roots
+        .iter()
+        .map(|root| {
+            let count = dirs
+                .iter()
+                .filter(|(dir_root, _)| *dir_root == root.path)
+                .count();
+            (root.path.as_path(), count)
+        })
+        .collect()
+}
+
+// Prints the matching folders of every root as a tree under it, skipping
+// the roots without any.
+fn tree(
+    query: Option<&str>,
+    depth: usize,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    let roots = search_roots(config, &args.roots)?;
+    let dirs = find_projects(&roots, query, args, config, log_enabled)?;
+    let mut printed = false;
+    for root in &roots {
+        let matches: Vec<&Path> = dirs
+            .iter()
+            .filter(|(dir_root, _)| *dir_root == root.path)
+            .map(|(_, dir)| dir.as_path())
+            .collect();
+        if matches.is_empty() {
+            continue;
+        }
+        if printed {
+            println!();
+        }
+        println!("{}", output_path(&root.path, args));
+        print!("{}", project_tree(&root.path, &matches, depth));
+        printed = true;
+    }
+    Ok(())
+}
+
+// A folder in the tree of a root, and whether it matched. The others lead
+// to a match or are inside one.
+#[derive(Default)]
+struct TreeNode {
+    matched: bool,
+    children: BTreeMap<OsString, TreeNode>,
+}
+
+impl TreeNode {
+    fn insert(&mut self, path: &Path) -> &mut TreeNode {
+        path.iter().fold(self, |node, name| {
+            node.children.entry(name.to_os_string()).or_default()
+        })
+    }
+
+    // Adds the folders inside dir, skipping hidden ones, this many levels
+    // deep.
+    fn insert_children(&mut self, dir: &Path, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name();
+            if name.to_string_lossy().starts_with('.') || !entry.path().is_dir() {
+                continue;
+            }
+            self.children
+                .entry(name)
+                .or_default()
+                .insert_children(&entry.path(), depth - 1);
+        }
+    }
+
+    fn render(&self, prefix: &str, out: &mut String) {
+        let last = self.children.len().saturating_sub(1);
+        for (i, (name, child)) in self.children.iter().enumerate() {
+            let (branch, indent) = if i == last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let slash = if child.matched { "" } else { "/" };
+            out.push_str(&format!(
+                "{}{}{}{}\n",
+                prefix,
+                branch,
+                name.to_string_lossy(),
+                slash
+            ));
+            child.render(&format!("{}{}", prefix, indent), out);
+        }
+    }
+}
+
+// Draws the matching folders of a root as a tree, with the folders inside
+// each of them, depth levels deep. Folders that didn't match end with a
+// slash.
+fn project_tree(root: &Path, matches: &[&Path], depth: usize) -> String {
+    let mut tree = TreeNode::default();
+    for dir in matches {
+        let node = tree.insert(dir.strip_prefix(root).unwrap_or(dir));
+        node.matched = true;
+        node.insert_children(dir, depth);
+    }
+    let mut out = String::new();
+    tree.render("", &mut out);
+    out
+}
+
+// Prints every line matching the regular expression in the files of the
+// matching folders, or of every repository, as "path:line:text" like grep
+// does, exiting with code 1 when there are none.
+fn grep(
+    pattern: &str,
+    query: Option<&str>,
+    ignore_case: bool,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    let roots = search_roots(config, &args.roots)?;
+    let dirs = find_projects(&roots, query, args, config, log_enabled)?;
+    ensure_found(&dirs, query, &roots)?;
+
+    let mut found = false;
+    for dir in outermost(&dirs) {
+        check_interrupted()?;
+        grep::search(
+            dir,
+            pattern,
+            ignore_case,
+            &args.prune,
+            &mut |path, n, line| {
+                check_interrupted()?;
+                println!("{}:{}:{}", output_path(path, args), n, line);
+                found = true;
+                Ok(())
+            },
+        )?;
+    }
+    if !found {
+        eprintln!("No lines match \"{}\" in {} folders", pattern, dirs.len());
+        process::exit(1);
+    }
+    Ok(())
+}
+
+// Returns the folders that aren't inside another one of them, whose files
+// would be searched twice otherwise.
+fn outermost<'a>(dirs: &'a [(&Path, PathBuf)]) -> Vec<&'a Path> {
+    dirs.iter()
+        .map(|(_, dir)| dir.as_path())
+        .filter(|dir| {
+            !dirs
+                .iter()
+                .any(|(_, other)| other != dir && dir.starts_with(other))
+        })
+        .collect()
+}
+
+// Runs a command in every folder matching the query, or in every
+// repository when there's no query, one after the other.
+fn foreach(
+    query: Option<&str>,
+    command: &[String],
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    let roots = search_roots(config, &args.roots)?;
+    let dirs = find_projects(&roots, query, args, config, log_enabled)?;
+    ensure_found(&dirs, query, &roots)?;
+
+    let (program, program_args) = command
+        .split_first()
+        .context("Please specify a command to run")?;
+    let mut failed = Vec::new();
+    for (root, dir) in &dirs {
+        check_interrupted()?;
+        let label = dir.strip_prefix(root).unwrap_or(dir).display().to_string();
+        let status = exec::run_labeled(dir, &label, program, program_args)?;
+        if !status.success() {
+            eprintln!("{}: {}", label, status);
+            failed.push(label);
+        }
+    }
+
+    if !failed.is_empty() {
+        bail!(
+            "The command failed in {} of {} folders: {}",
+            failed.len(),
+            dirs.len(),
+            failed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+// Fetches every git repository in the roots, several at the same time,
+// printing the ones that got new commits as they finish.
+fn fetch_all(
+    jobs: usize,
+    timeout: Duration,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    let mut search_args = args.clone();
+    search_args.markers = vec![".git".to_string()];
+    search_args.stop_at_projects = true;
+    // Worktrees share what's fetched with their main repository.
+    search_args.skip_worktrees = true;
+
+    let roots = search_roots(config, &args.roots)?;
+    let repos = find_all_in_roots(&roots, "", &search_args, config, log_enabled)?;
+
+    let next = AtomicUsize::new(0);
+    let updated = AtomicUsize::new(0);
+    let failed = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, repos.len().max(1)) {
+            scope.spawn(|| {
+                while let Some((root, dir)) = repos
+                    .get(next.fetch_add(1, Ordering::Relaxed))
+                    .filter(|_| !interrupted().load(Ordering::Relaxed))
+                {
+                    let label = dir.strip_prefix(root).unwrap_or(dir).display().to_string();
+                    match git::fetch(dir, timeout) {
+                        Ok(branches) if branches.is_empty() => {}
+                        Ok(branches) => {
+                            updated.fetch_add(1, Ordering::Relaxed);
+                            println!("{}: new commits in {}", label, branches.join(", "));
+                        }
+                        Err(err) => {
+                            eprintln!("{}: {:#}", label, err);
+                            failed.lock().expect("no thread panics").push(label);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    check_interrupted()?;
+    let failed = failed.into_inner().expect("no thread panics");
+    eprintln!(
+        "Fetched {} repositories, {} with new commits",
+        repos.len() - failed.len(),
+        updated.into_inner()
+    );
+    if !failed.is_empty() {
+        bail!(
+            "Unable to fetch {} repositories: {}",
+            failed.len(),
+            failed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+// Prints a summary of the projects in every root: how many there are and
+// how many are under version control, the languages they're written in,
+// the deepest one and how much space they take.
+fn root_stats(measure_size: bool, args: &Args, config: &Config, log_enabled: bool) -> Result<()> {
+    let roots = search_roots(config, &args.roots)?;
+    let projects = find_all_in_roots(&roots, "", args, config, log_enabled)?;
+
+    for (i, root) in roots.iter().enumerate() {
+        let dirs: Vec<&PathBuf> = projects
+            .iter()
+            .filter(|(project_root, _)| *project_root == root.path)
+            .map(|(_, dir)| dir)
+            .collect();
+        let repositories = dirs.iter().filter(|dir| vcs::detect(dir).is_some()).count();
+
+        let mut languages: BTreeMap<&str, usize> = BTreeMap::new();
+        for dir in &dirs {
+            for language in stats::languages(dir) {
+                *languages.entry(language).or_default() += 1;
+            }
+        }
+        // Most used first, alphabetically when tied, since the sort is
+        // stable.
+        let mut languages: Vec<_> = languages.into_iter().collect();
+        languages.sort_by_key(|(_, count)| Reverse(*count));
+
+        let deepest = dirs
+            .iter()
+            .filter_map(|dir| dir.strip_prefix(&root.path).ok())
+            .max_by_key(|rel| rel.components().count());
+
+        if i > 0 {
+            println!();
+        }
+        println!("{}", output_path(&root.path, args));
+        println!(
+            "  Projects:   {} ({} repositories, {} plain folders)",
+            dirs.len(),
+            repositories,
+            dirs.len() - repositories
+        );
+        if !languages.is_empty() {
+            let languages: Vec<_> = languages
+                .iter()
+                .map(|(language, count)| format!("{} {}", language, count))
+                .collect();
+            println!("  Languages:  {}", languages.join(", "));
+        }
+        if let Some(deepest) = deepest {
+            println!(
+                "  Deepest:    {} ({} levels)",
+                deepest.display(),
+                deepest.components().count()
+            );
+        }
+        if measure_size {
+            // Projects inside other projects are already counted with them.
+            let mut size = 0;
+            for dir in dirs.iter().filter(|dir| {
+                !dirs
+                    .iter()
+                    .any(|other| other != *dir && dir.starts_with(other))
+            }) {
+                check_interrupted()?;
+                size += stats::disk_usage(dir);
+            }
+            println!("  Size:       {}", stats::format_size(size));
+        }
+    }
+    Ok(())
+}
+
+// Prints how much space every folder matching the query, or every
+// project, takes, biggest first, measuring several of them at the same
+// time since most of the time goes into waiting for the disk.
+fn disk_usage(
+    query: Option<&str>,
+    jobs: usize,
+    bytes: bool,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    let roots = search_roots(config, &args.roots)?;
+    let name = query.unwrap_or_default();
+    let dirs = find_all_in_roots(&roots, name, args, config, log_enabled)?;
+    if dirs.is_empty() {
+        match query {
+            Some(name) => bail!(
+                "Folder \"{}\" not found inside {}",
+                name,
+                display_roots(&roots)
+            ),
+            None => bail!("No projects found inside {}", display_roots(&roots)),
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let sizes = Mutex::new(Vec::with_capacity(dirs.len()));
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, dirs.len()) {
+            scope.spawn(|| {
+                while let Some((_, dir)) = dirs
+                    .get(next.fetch_add(1, Ordering::Relaxed))
+                    .filter(|_| !interrupted().load(Ordering::Relaxed))
+                {
+                    let size = stats::disk_usage(dir);
+                    sizes.lock().expect("no thread panics").push((size, dir));
+                }
+            });
+        }
+    });
+    check_interrupted()?;
+
+    let mut sizes = sizes.into_inner().expect("no thread panics");
+    sizes.sort_by_key(|(size, _)| Reverse(*size));
+    let format = |size: u64| {
+        if bytes {
+            size.to_string()
+        } else {
+            stats::format_size(size)
+        }
+    };
+    for (size, dir) in &sizes {
+        println!("{:>10}  {}", format(*size), output_path(dir, args));
+    }
+    // Folders inside other folders listed are already counted with them.
+    let total: u64 = sizes
+        .iter()
+        .filter(|(_, dir)| {
+            !dirs
+                .iter()
+                .any(|(_, other)| other != *dir && dir.starts_with(other))
+        })
+        .map(|(size, _)| size)
+        .sum();
+    println!("{:>10}  total", format(total));
+    Ok(())
+}
+
+// Prints the projects sharing a folder name or a repository, in groups
+// named after what they share.
+fn dedupe(by: DuplicateKey, args: &Args, config: &Config, log_enabled: bool) -> Result<()> {
+    let mut search_args = args.clone();
+    // Worktrees are meant to be another copy of their repository.
+    search_args.skip_worktrees = true;
+
+    let roots = search_roots(config, &args.roots)?;
+    let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for (_, dir) in find_all_in_roots(&roots, "", &search_args, config, log_enabled)? {
+        if let Some(key) = duplicate_key(&dir, by, args.ignores_case()) {
+            groups.entry(key).or_default().push(dir);
+        }
+    }
+
+    let duplicates: Vec<_> = groups.iter().filter(|(_, dirs)| dirs.len() > 1).collect();
+    if duplicates.is_empty() {
+        eprintln!(
+            "No duplicate projects found inside {}",
+            display_roots(&roots)
+        );
+        return Ok(());
+    }
+    for (i, (key, dirs)) in duplicates.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}", key);
+        for dir in dirs.iter() {
+            println!("  {}", output_path(dir, args));
+        }
+    }
+    Ok(())
+}
+
+// Returns what two projects share when they're duplicates, or None when
+// there's nothing to compare, like a folder without an origin remote.
+fn duplicate_key(dir: &Path, by: DuplicateKey, ignore_case: bool) -> Option<String> {
+    match by {
+        DuplicateKey::Name => {
+            let name = dir.file_name()?.to_string_lossy();
+            Some(if ignore_case {
+                name.to_lowercase()
+            } else {
+                name.into_owned()
+            })
+        }
+        // Hosts and owners ignore case, see Remote::same_repository.
+        DuplicateKey::Remote => {
+            let url = git::origin_url(dir)?;
+            let remote = clone::Remote::parse(&url, clone::DEFAULT_HOST).ok()?;
+            Some(format!("{}/{}/{}", remote.host, remote.org, remote.repo).to_lowercase())
+        }
+    }
+}
+
+// What to do with the projects found by stale.
+#[derive(Clone, Copy)]
+enum StaleAction<'a> {
+    Print,
+    Delete,
+    Archive(&'a Path),
+}
+
+// Prints the projects nothing has changed in for the given number of
+// days, with when they last changed, oldest first. They can be deleted or
+// moved away too, asking about each one before.
+fn stale(
+    days: u64,
+    action: StaleAction,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    if !matches!(action, StaleAction::Print) && !io::stdin().is_terminal() {
+        bail!("--delete and --archive ask about every project first, so they need a terminal");
+    }
+
+    let roots = search_roots(config, &args.roots)?;
+    // Projects whose files can't be read have no time to compare, so
+    // they're skipped instead of looking like they never changed.
+    let mut projects = Vec::new();
+    for (root, dir) in find_all_in_roots(&roots, "", args, config, log_enabled)? {
+        match stats::last_modified(&dir) {
+            Some(time) => projects.push((time, root, dir)),
+            None => eprintln!(
+                "Skipping {}: unable to read when it last changed",
+                dir.display()
+            ),
+        }
+    }
+    let cutoff = frecency::now().saturating_sub(days.saturating_mul(24 * 60 * 60));
+    // A project with a recent one inside isn't stale, even if its own
+    // files haven't changed.
+    let mut stale: Vec<_> = projects
+        .iter()
+        .filter(|(time, _, dir)| {
+            *time < cutoff
+                && !projects
+                    .iter()
+                    .any(|(other_time, _, other)| *other_time >= cutoff && other.starts_with(dir))
+        })
+        .collect();
+    stale.sort_by_key(|(time, _, _)| *time);
+
+    if stale.is_empty() {
+        eprintln!(
+            "No projects unchanged for {} days inside {}",
+            days,
+            display_roots(&roots)
+        );
+        return Ok(());
+    }
+    for (time, root, dir) in stale {
+        let line = format!(
+            "{:<19}  {}",
+            history::format_timestamp(*time),
+            output_path(dir, args)
+        );
+        // Projects inside one deleted or moved before are gone with it.
+        let question = match action {
+            StaleAction::Print => {
+                println!("{}", line);
+                continue;
+            }
+            _ if !dir.exists() => continue,
+            StaleAction::Delete => "Delete it?",
+            StaleAction::Archive(_) => "Move it to the archive?",
+        };
+        if !confirm(&format!("{}\n{} [y/N] ", line, question))? {
+            continue;
+        }
+
+        match action {
+            StaleAction::Delete => {
+                fs::remove_dir_all(dir).with_context(|| format!("Unable to delete {:?}", dir))?
+            }
+            StaleAction::Archive(archive) => {
+                let target = archive.join(dir.strip_prefix(root).unwrap_or(dir));
+                if target.exists() {
+                    bail!(
+                        "Unable to move {:?} to {:?}, it already exists",
+                        dir,
+                        target
+                    );
+                }
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)
+                        .with_context(|| format!("Unable to create {:?}", parent))?;
+                }
+                fs::rename(dir, &target)
+                    .with_context(|| format!("Unable to move {:?} to {:?}", dir, target))?;
+            }
+            StaleAction::Print => unreachable!("printing doesn't ask"),
+        }
+    }
+    Ok(())
+}
+
+fn prune(delete: bool, args: &Args, config: &Config) -> Result<()> {
+    if delete && !io::stdin().is_terminal() {
+        bail!("--delete asks before removing anything, so it needs a terminal");
+    }
+
+    let roots = search_roots(config, &args.roots)?;
+    let mut empty = Vec::new();
+    for root in &roots {
+        check_interrupted()?;
+        empty_folders(&root.path, &args.markers, &mut empty);
+    }
+    empty.sort();
+
+    // Only projects inside the roots are forgotten, the others may be on a
+    // drive that isn't mounted right now.
+    let missing =
+        |path: &Path| roots.iter().any(|root| path.starts_with(&root.path)) && !path.exists();
+    let mut db = Database::load()?;
+    let visited: Vec<_> = db
+        .entries()
+        .iter()
+        .map(|entry| entry.path.clone())
+        .filter(|path| missing(path))
+        .collect();
+    let mut pins = Pins::load()?;
+    let pinned: Vec<_> = pins
+        .iter()
+        .filter(|path| missing(path))
+        .map(Path::to_path_buf)
+        .collect();
+
+    if empty.is_empty() && visited.is_empty() && pinned.is_empty() {
+        eprintln!("Nothing to prune inside {}", display_roots(&roots));
+        return Ok(());
+    }
+    for (kind, paths) in [
+        ("empty", &empty),
+        ("visited", &visited),
+        ("pinned", &pinned),
+    ] {
+        for path in paths {
+            println!("{:<7}  {}", kind, output_path(path, args));
+        }
+    }
+    if !delete {
+        return Ok(());
+    }
+
+    let question = format!(
+        "Delete {} empty folders and forget {} missing projects? [y/N] ",
+        empty.len(),
+        visited.len() + pinned.len()
+    );
+    if !confirm(&question)? {
+        return Ok(());
+    }
+    for dir in &visited {
+        db.forget(dir);
+    }
+    for dir in &pinned {
+        pins.remove(&dir.to_string_lossy());
+    }
+    db.save()?;
+    pins.save()?;
+    for dir in &empty {
+        remove_empty(dir).with_context(|| format!("Unable to delete {:?}", dir))?;
+    }
+    Ok(())
+}
+
+// Adds the folders inside dir with nothing but empty folders in them to
+// found, only the topmost of every such tree, and returns whether that's
+// everything dir holds. Projects and hidden folders are never empty, and
+// nothing inside them is looked at, since tools expect their empty
+// folders to be there.
+fn empty_folders(dir: &Path, markers: &[String], found: &mut Vec<PathBuf>) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    let mut all_empty = true;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let start = found.len();
+        let empty = entry.file_type().is_ok_and(|t| t.is_dir())
+            && !entry.file_name().to_string_lossy().starts_with('.')
+            && !is_project(&path, markers)
+            && empty_folders(&path, markers, found);
+        if empty {
+            found.truncate(start);
+            found.push(path);
+        } else {
+            all_empty = false;
+        }
+    }
+    all_empty
+}
+
+// Deletes a folder holding only empty folders. Unlike fs::remove_dir_all,
+// it fails rather than delete a file created after it was found empty.
+fn remove_empty(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            remove_empty(&entry.path())?;
+        }
+    }
+    fs::remove_dir(dir)
+}
+
+// Loads what a search keeps between runs. A search still works when
+// there's no data directory, like when $HOME isn't set, it just doesn't
+// rank or record what it finds.
+fn load_state<T>(loaded: find_project::error::Result<T>) -> Result<Option<T>> {
+    match loaded {
+        Err(FindError::NoDataDir { .. }) => Ok(None),
+        loaded => Ok(Some(loaded?)),
+    }
+}
+
+// Sorts the folders found by how they rank among the previously visited
+// ones, the never visited ones last, then by how deep they are inside
+// their root, and then by their path, so the best one comes first.
+fn rank_matches(dirs: &mut [(&Path, PathBuf)], db: Option<&Database>, ranking: &Ranking, now: u64) {
+    let score = |dir: &Path| {
+        db.and_then(|db| db.entry(dir))
+            .map_or(0.0, |entry| ranking.score(entry, now))
+    };
+    let depth =
+        |root: &Path, dir: &Path| dir.strip_prefix(root).unwrap_or(dir).components().count();
+    dirs.sort_by(|(a_root, a), (b_root, b)| {
+        score(b)
+            .total_cmp(&score(a))
+            .then_with(|| depth(a_root, a).cmp(&depth(b_root, b)))
+            .then_with(|| a.cmp(b))
+    });
+}
 
-#[derive(Parser, Default, Debug)]
-#[command(
-    version,
-    about = "Traverse the directory specified by $FP_FOLDER or $GOPATH to find a folder depth-first."
-)]
-struct Args {
-    #[clap(required = true)]
-    folder_name: String,
+// Sorts the folders found by how well their names match the name searched
+// for, best first, keeping the order they had for the same score.
+fn rank_by_fuzzy_score(dirs: &mut [(&Path, PathBuf)], name: &str) {
+    let mut scorer = FuzzyScorer::new(name);
+    dirs.sort_by_cached_key(|(_, dir)| {
+        let folder = dir.file_name().unwrap_or_default().to_string_lossy();
+        Reverse(scorer.score(&folder))
+    });
+}
 
-    #[clap(long, help = "Also search in \"vendor\" folders")]
-    include_vendor: bool,
+// Sorts the folders by the scores the scorer command gives them, highest
+// first, keeping the order they had for the same score.
+fn rank_by_scorer(dirs: &mut Vec<(&Path, PathBuf)>, scorer: &str) -> Result<()> {
+    let paths: Vec<&Path> = dirs.iter().map(|(_, dir)| dir.as_path()).collect();
+    let scores = exec::scores(scorer, &paths)?;
+    let mut scored: Vec<_> = scores.into_iter().zip(dirs.drain(..)).collect();
+    scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    dirs.extend(scored.into_iter().map(|(_, dir)| dir));
+    Ok(())
+}
 
-    #[clap(long, help = "Also search in hidden (dot) folders")]
-    include_hidden: bool,
+// Finds every folder matching the name and, when there's more than one,
+// lists them numbered on stderr and asks which one to use on the
+// terminal, since stdin and stdout are often taken by the shell running
+// the search.
+fn choose(
+    roots: &[Root],
+    name: &str,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<Option<PathBuf>> {
+    let mut dirs = find_all_in_roots(roots, name, args, config, log_enabled)?;
+    if dirs.len() < 2 {
+        return Ok(dirs.pop().map(|(_, dir)| dir));
+    }
 
-    #[clap(long, help = "Sort folders alphabetically")]
-    sort_alphabetically: bool,
+    let mut stderr = io::stderr().lock();
+    for (i, (_, dir)) in dirs.iter().enumerate() {
+        writeln!(stderr, "{:>3}) {}", i + 1, output_path(dir, args))?;
+    }
+    let tty = fs::File::open(TERMINAL).context("Unable to read from the terminal")?;
+    match read_choice(io::BufReader::new(tty), stderr, dirs.len())? {
+        Some(i) => Ok(Some(dirs.swap_remove(i).1)),
+        None => process::exit(EXIT_CANCELLED),
+    }
 }
 
-fn main() -> Result<()> {
-    // Collect command-line arguments
-    let args = Args::parse();
+#[cfg(windows)]
+const TERMINAL: &str = "CONIN$";
+#[cfg(not(windows))]
+const TERMINAL: &str = "/dev/tty";
 
-    // Enable debug logging if the environment variable FP_DEBUG is set
-    // to any non-empty value.
-    let log_enabled = env::var("FP_DEBUG").is_ok();
-
-    // Check if FP_FOLDER or GOPATH are set:
-    // If FP_FOLDER is set, use it as the folder to search,
-    // otherwise, use the GOPATH folder.
-    let location = env::var("GOPATH")
-        .or_else(|_| env::var("FP_FOLDER"))
-        .context(
-            "Please set the $FP_FOLDER environment variable or the $GOPATH \
-            environment variable to a location that find-project can search. \
-            Neither are set.",
-        )?;
+// Asks for the number of one of count choices until a valid one is
+// given, returning its index, or None when nothing is.
+fn read_choice(
+    mut input: impl io::BufRead,
+    mut prompt: impl Write,
+    count: usize,
+) -> Result<Option<usize>> {
+    loop {
+        write!(prompt, "Which one? [1-{}] ", count)?;
+        prompt.flush()?;
+        let mut answer = String::new();
+        input
+            .read_line(&mut answer)
+            .context("Unable to read the answer")?;
+        if answer.trim().is_empty() {
+            return Ok(None);
+        }
+        match answer.trim().parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => return Ok(Some(n - 1)),
+            _ => writeln!(prompt, "Enter a number from 1 to {}", count)?,
+        }
+    }
+}
 
-    // If the path is a $GOPATH, then append "src" to it, otherwise,
-    // use the path as is
-    let full_location = if env::var("GOPATH").is_ok() {
-        Path::new(&location)
-            .join("src")
-            .canonicalize()
-            .context("Unable to get absolute path to $GOPATH/src")?
-    } else {
-        Path::new(&location)
-            .to_path_buf()
-            .canonicalize()
-            .context("Unable to get absolute path to $FP_FOLDER")?
+// Asks a yes or no question on the terminal. Anything but "y" or "yes"
+// is a no.
+fn confirm(question: &str) -> Result<bool> {
+    eprint!("{}", question);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("Unable to read the answer")?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn pin(
+    name_or_path: Option<&str>,
+    list: bool,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    let mut pins = Pins::load()?;
+
+    if list {
+        for pinned in pins.iter() {
+            println!("{}", pinned.display());
+        }
+        return Ok(());
+    }
+
+    let Some(name_or_path) = name_or_path else {
+        bail!("Please specify a folder name or a path to pin");
     };
 
-    // Find the directory
-    let loc = finddir(&full_location, &args, log_enabled)?;
-    if let Some(loc) = loc {
-        println!("{}", loc.display());
-        Ok(())
+    // Anything that looks like a path is pinned as is, otherwise the
+    // name is resolved by searching the root like a regular query.
+    let target = Path::new(name_or_path);
+    let dir = if target.components().count() > 1 || !target.is_relative() || name_or_path == "." {
+        absolute(target).with_context(|| format!("Unable to get absolute path to {:?}", target))?
     } else {
-        eprintln!(
-            "Folder \"{}\" not found inside {}",
-            args.folder_name,
-            full_location.as_os_str().to_string_lossy()
-        );
-        std::process::exit(1);
+        let roots = search_roots(config, &args.roots)?;
+        match find_in_roots(&roots, name_or_path, args, config, log_enabled)? {
+            Some(dir) => dir,
+            None => bail!(
+                "Folder \"{}\" not found inside {}",
+                name_or_path,
+                display_roots(&roots)
+            ),
+        }
+    };
+
+    if !dir.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+
+    if let Some(replaced) = pins.add(dir.clone())? {
+        if replaced != dir {
+            eprintln!("Replacing pinned project {}", replaced.display());
+        }
     }
+    pins.save()?;
+
+    println!("{}", dir.display());
+    Ok(())
 }
 
-fn finddir(p: &Path, args: &Args, log_enabled: bool) -> Result<Option<PathBuf>> {
-    let mut dirs = getalldirs(p, args)?;
-    let name = Path::new(&args.folder_name);
+fn unpin(name_or_path: &str) -> Result<()> {
+    let mut pins = Pins::load()?;
+    let removed = pins.remove(name_or_path);
+    if removed.is_empty() {
+        bail!("No pinned project matches \"{}\"", name_or_path);
+    }
+    pins.save()?;
 
-    let mut i = 0;
-    while i < dirs.len() {
-        let dir = &dirs[i];
-        if log_enabled {
-            eprintln!("Searching in: {}", dir.display());
+    for dir in removed {
+        println!("{}", dir.display());
+    }
+    Ok(())
+}
+
+fn import_database(source: ImportSource, file: Option<&Path>, config: &Config) -> Result<()> {
+    let default_file = match source {
+        ImportSource::Zoxide => import::zoxide_path(),
+        ImportSource::Autojump => import::autojump_path(),
+        ImportSource::Z => import::z_path(),
+    };
+    let Some(file) = file.map(Path::to_path_buf).or(default_file) else {
+        bail!("Unable to find the database to import, please specify it with --file");
+    };
+
+    let read = || fs::read_to_string(&file).with_context(|| format!("Unable to read {:?}", file));
+    let imported = match source {
+        ImportSource::Zoxide => {
+            import::zoxide(&fs::read(&file).with_context(|| format!("Unable to read {:?}", file))?)?
+        }
+        ImportSource::Autojump => {
+            // autojump doesn't track visit times, so the best guess is
+            // the last time its database was written.
+            let modified = fs::metadata(&file)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or_else(frecency::now);
+            import::autojump(&read()?, modified)
         }
+        ImportSource::Z => import::z(&read()?),
+    };
 
-        if let Some(base_name) = dir.file_name() {
-            if base_name == name {
-                return Ok(Some(dir.clone()));
-            }
+    // Only directories that still exist under the roots are useful.
+    let roots = search_roots(config, &[])?;
+    let mut db = Database::load()?;
+    let mut skipped = 0;
+    let mut added = 0;
+    for dir in imported {
+        if roots.iter().any(|root| dir.path.starts_with(&root.path)) && dir.path.is_dir() {
+            db.merge(&dir.path, dir.visits, dir.last_visit);
+            added += 1;
+        } else {
+            skipped += 1;
         }
+    }
+    db.save()?;
 
-        let extras = getalldirs(dir, args)?;
-        for extra in extras {
-            if let Some(extra_name) = extra.file_name() {
-                if extra_name == name {
-                    if log_enabled {
-                        eprintln!("Found: {}", extra.display());
-                    }
-                    return Ok(Some(extra));
-                }
+    eprintln!(
+        "Imported {} directories from {}, skipped {} outside {} or no longer on disk",
+        added,
+        file.display(),
+        skipped,
+        display_roots(&roots)
+    );
+    Ok(())
+}
+
+fn top(count: usize, rank_by: Option<RankBy>, config: &Config) -> Result<()> {
+    let ranking = ranking(rank_by, config);
+    let db = Database::load()?;
+    for (entry, score) in db.top(&ranking, frecency::now(), count) {
+        println!("{:>10.2}  {}", score, entry.path.display());
+    }
+    Ok(())
+}
+
+// Prints the projects in the roots changed or visited most recently,
+// newest first.
+fn recent(
+    count: usize,
+    by: RecentBy,
+    args: &Args,
+    config: &Config,
+    log_enabled: bool,
+) -> Result<()> {
+    let roots = search_roots(config, &args.roots)?;
+    let mut projects: Vec<(u64, PathBuf)> = match by {
+        RecentBy::Modified => find_all_in_roots(&roots, "", args, config, log_enabled)?
+            .into_iter()
+            .filter_map(|(_, dir)| Some((stats::last_modified(&dir)?, dir)))
+            .collect(),
+        // Only projects still in the roots, so the list is the same one
+        // whichever way it's sorted.
+        RecentBy::Visited => Database::load()?
+            .entries()
+            .iter()
+            .filter(|entry| {
+                roots.iter().any(|root| entry.path.starts_with(&root.path)) && entry.path.is_dir()
+            })
+            .map(|entry| (entry.last_visit, entry.path.clone()))
+            .collect(),
+    };
+
+    projects.sort_by_key(|(time, _)| Reverse(*time));
+    for (_, dir) in projects.iter().take(count) {
+        println!("{}", output_path(dir, args));
+    }
+    Ok(())
+}
+
+// Moves every git repository to the folder "clone" would have cloned it
+// into, following its origin remote, or only prints the moves. Visits and
+// pins follow the repositories they're about.
+fn organize(apply: bool, args: &Args, config: &Config, log_enabled: bool) -> Result<()> {
+    let template = config
+        .clone
+        .template
+        .as_deref()
+        .unwrap_or(clone::DEFAULT_TEMPLATE);
+    let mut search_args = args.clone();
+    search_args.markers = vec![".git".to_string()];
+    search_args.stop_at_projects = true;
+    // Worktrees point to their main repository by path.
+    search_args.skip_worktrees = true;
+
+    let roots = search_roots(config, &args.roots)?;
+    let repos = find_all_in_roots(&roots, "", &search_args, config, log_enabled)?;
+
+    let mut moves = Vec::new();
+    let mut targets = HashSet::new();
+    for (root, dir) in &repos {
+        let Some(url) = git::origin_url(dir) else {
+            eprintln!("{}: skipped, there's no origin remote", dir.display());
+            continue;
+        };
+        let remote = clone::Remote::parse(&url, clone::DEFAULT_HOST)
+            .with_context(|| format!("Unable to understand the origin remote of {:?}", dir))?;
+        let target = root.join(remote.path(template)?);
+        if target == *dir {
+            continue;
+        }
+        if target.exists() || !targets.insert(target.clone()) {
+            eprintln!(
+                "{}: skipped, {} is taken by another folder",
+                dir.display(),
+                target.display()
+            );
+            continue;
+        }
+        if target.starts_with(dir) {
+            eprintln!(
+                "{}: skipped, {} is inside it",
+                dir.display(),
+                target.display()
+            );
+            continue;
+        }
+        println!(
+            "{} -> {}",
+            output_path(dir, args),
+            output_path(&target, args)
+        );
+        moves.push((*root, dir, target));
+    }
+
+    if !apply {
+        match moves.len() {
+            0 => eprintln!("Every repository is where it should be"),
+            count => eprintln!(
+                "{} repositories would be moved, run with --apply to move them",
+                count
+            ),
+        }
+        return Ok(());
+    }
+
+    let mut db = Database::load()?;
+    let mut pins = Pins::load()?;
+    let mut moved = 0;
+    let result = moves.iter().try_for_each(|(root, dir, target)| {
+        check_interrupted()?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Unable to create {:?}", parent))?;
+        }
+        fs::rename(dir, target)
+            .with_context(|| format!("Unable to move {:?} to {:?}", dir, target))?;
+        db.rename(dir, target);
+        pins.rename(dir, target);
+        moved += 1;
+
+        // Folders left empty, like the one of an organization that had
+        // a single repository, go away too.
+        for parent in dir.ancestors().skip(1) {
+            if parent == *root || fs::remove_dir(parent).is_err() {
+                break;
             }
-            dirs.push(extra);
+        }
+        Ok(())
+    });
+
+    // Saved even when a move failed, so the ones done before are kept.
+    db.save()?;
+    pins.save()?;
+    eprintln!("Moved {} repositories", moved);
+    result
+}
+
+fn clone_repository(spec: &str, root: Option<&str>, config: &Config) -> Result<()> {
+    let host = config.clone.host.as_deref().unwrap_or(clone::DEFAULT_HOST);
+    let remote = clone::Remote::parse(spec, host)?;
+    let template = config
+        .clone
+        .template
+        .as_deref()
+        .unwrap_or(clone::DEFAULT_TEMPLATE);
+
+    // Without a root name, the repository goes into the first root.
+    let names: Vec<String> = root
+        .or(config.clone.root.as_deref())
+        .map(String::from)
+        .into_iter()
+        .collect();
+    let roots = search_roots(config, &names)?;
+    let Some(root) = roots.first() else {
+        bail!("There's no root to clone {} into", remote.url);
+    };
+    let dir = root.path.join(remote.path(template)?);
+
+    if dir.is_dir() {
+        eprintln!("{} is already cloned", dir.display());
+    } else {
+        if let Some(parent) = dir.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Unable to create directory {:?}", parent))?;
         }
 
-        i += 1;
+        // Only the path goes to stdout, so the output can be used with cd.
+        let status = process::Command::new("git")
+            .arg("clone")
+            .arg("--")
+            .arg(&remote.url)
+            .arg(&dir)
+            .stdout(io::stderr())
+            .status()
+            .context("Unable to run git, is it installed?")?;
+        if !status.success() {
+            bail!("Unable to clone {}: git exited with {}", remote.url, status);
+        }
     }
 
-    Ok(None)
+    let mut db = Database::load()?;
+    db.record(&dir, frecency::now());
+    db.save()?;
+    forget_misses()?;
+
+    println!("{}", dir.display());
+    Ok(())
 }
 
-fn getalldirs(p: &Path, args: &Args) -> Result<Vec<PathBuf>> {
-    let mut dirs = Vec::new();
-    let entries = fs::read_dir(p).with_context(|| format!("Unable to read directory {:?}", p))?;
-    for entry in entries {
-        let entry = entry?;
-        let file_type = entry.file_type()?;
-        if file_type.is_dir() {
-            let name = entry.file_name();
-            let name_str = name.to_string_lossy();
+fn config_command(action: &ConfigAction) -> Result<()> {
+    let path =
+        config::config_path().context("Unable to find the configuration file: $HOME is not set")?;
 
-            if !args.include_hidden && name_str.starts_with('.') {
-                continue;
+    match action {
+        ConfigAction::Path => println!("{}", path.display()),
+        ConfigAction::Get { key } => match config::get(&config::read_raw(&path)?, key)? {
+            Some(value) => println!("{}", value),
+            None => bail!("\"{}\" is not set in {}", key, path.display()),
+        },
+        ConfigAction::Set { key, value } => {
+            let updated = config::set(&config::read_raw(&path)?, key, value)?;
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("Unable to create directory {:?}", dir))?;
             }
-
-            if !args.include_vendor && name_str == "vendor" {
-                continue;
+            fs::write(&path, updated).with_context(|| format!("Unable to write {:?}", path))?;
+        }
+        ConfigAction::List => {
+            for (key, value) in config::list(&config::read_raw(&path)?)? {
+                println!("{} = {}", key, value);
             }
-
-            dirs.push(entry.path());
         }
+        ConfigAction::Edit => edit_config(&path)?,
+        ConfigAction::Check => check_config(&path)?,
     }
+    Ok(())
+}
 
-    if args.sort_alphabetically {
-        dirs.sort();
+fn check_config(path: &Path) -> Result<()> {
+    let config = Config::load_from(path)?;
+    let problems = config.problems();
+    if problems.is_empty() {
+        println!("{} is valid", path.display());
+        return Ok(());
     }
 
-    Ok(dirs)
+    for problem in &problems {
+        eprintln!("{}: {}", path.display(), problem);
+    }
+    bail!("Found {} problems in {}", problems.len(), path.display());
+}
+
+fn edit_config(path: &Path) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+
+    // The editor might come with its own arguments, like "code --wait".
+    let mut words = opts::split(&editor).context("Unable to parse the editor command")?;
+    if words.is_empty() {
+        bail!("The editor command is empty, please set $VISUAL or $EDITOR");
+    }
+    let program = words.remove(0);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Unable to create directory {:?}", dir))?;
+    }
+
+    let status = process::Command::new(&program)
+        .args(&words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Unable to run the editor {:?}", program))?;
+    if !status.success() {
+        bail!("The editor {:?} exited with {}", program, status);
+    }
+
+    let config = Config::load_from(path)
+        .context("The configuration file is invalid, run \"find-project config edit\" to fix it")?;
+    for problem in config.problems() {
+        eprintln!("Warning: {}", problem);
+    }
+    Ok(())
+}
+
+fn history(clear: bool) -> Result<()> {
+    let mut history = History::load()?;
+    if clear {
+        history.clear();
+        history.save()?;
+        return Ok(());
+    }
+
+    for jump in history.jumps() {
+        println!(
+            "{}  {}  {}",
+            history::format_timestamp(jump.time),
+            jump.query,
+            jump.path.display()
+        );
+    }
+    Ok(())
+}
+
+// Returns how many days of history to keep, which can be changed with
+// the FP_HISTORY_DAYS environment variable or the configuration file.
+// Zero disables the history.
+fn history_retention(config: &Config) -> Result<u64> {
+    match env::var("FP_HISTORY_DAYS") {
+        Ok(days) => days
+            .parse()
+            .context("$FP_HISTORY_DAYS must be a number of days"),
+        Err(_) => Ok(config
+            .history
+            .retention_days
+            .unwrap_or(history::DEFAULT_RETENTION_DAYS)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
+    use std::time::SystemTime;
     use tempfile::tempdir;
 
     #[test]
-    fn test_getalldirs() -> Result<()> {
+    fn test_resolve_alias() -> Result<()> {
         let dir = tempdir()?;
         let path = dir.path();
 
-        let args = Args {
-            include_vendor: false,
-            include_hidden: false,
-            ..Default::default()
-        };
+        fs::create_dir_all(path.join("second").join("github.com").join("kubernetes"))?;
+        let roots = vec![
+            Root {
+                path: path.join("first"),
+                ..Default::default()
+            },
+            Root {
+                path: path.join("second"),
+                ..Default::default()
+            },
+        ];
 
-        // Create some directories
-        fs::create_dir(path.join("dir1"))?;
-        fs::create_dir(path.join("dir2"))?;
-        fs::create_dir(path.join("vendor"))?;
-        fs::create_dir(path.join(".hidden"))?;
-        fs::create_dir(path.join("dir1").join("subdir1"))?;
+        assert_eq!(
+            resolve_alias("github.com/kubernetes", &roots),
+            Some(path.join("second").join("github.com").join("kubernetes"))
+        );
+        assert_eq!(
+            resolve_alias(&path.join("second").to_string_lossy(), &roots),
+            Some(path.join("second"))
+        );
+        assert_eq!(resolve_alias("kubernetes", &roots), None);
+
+        Ok(())
+    }
 
-        let dirs = getalldirs(path, &args)?;
+    #[test]
+    fn test_organization() -> Result<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
 
-        assert_eq!(dirs.len(), 2);
-        let mut names = dirs
-            .iter()
-            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
-            .collect::<Vec<_>>();
-        names.sort();
-        assert_eq!(names, vec!["dir1", "dir2"]);
+        let fork = root.join("forks").join("autoscaler");
+        fs::create_dir_all(fork.join(".git"))?;
+        fs::write(
+            fork.join(".git").join("config"),
+            "[remote \"origin\"]\n\turl = https://github.com/kubernetes/autoscaler\n",
+        )?;
+        let api = root.join("gitlab.com").join("acme").join("api");
+        fs::create_dir_all(&api)?;
+        fs::create_dir_all(root.join("notes"))?;
+
+        assert_eq!(organization(root, &fork), "github.com/kubernetes");
+        assert_eq!(organization(root, &api), "gitlab.com/acme");
+        assert_eq!(organization(root, &root.join("notes")), "(no organization)");
 
         Ok(())
     }
 
     #[test]
-    fn test_finddir() -> Result<()> {
-        let dir = tempdir()?;
-        let path = dir.path();
-        let args = Args {
-            include_vendor: false,
-            include_hidden: false,
-            sort_alphabetically: false,
-            folder_name: "target".to_string(),
+    fn test_root_source() -> Result<()> {
+        let mut config = Config {
+            roots: vec![Root {
+                path: PathBuf::from("/home/patrickdap/Projects"),
+                ..Default::default()
+            }],
+            ..Default::default()
         };
+        assert_eq!(root_source(&config)?, RootSource::Config);
 
-        // Create directories
-        fs::create_dir(path.join("dir1"))?;
-        fs::create_dir(path.join("dir2"))?;
-        fs::create_dir(path.join("dir1").join("target"))?;
-        fs::create_dir(path.join("dir2").join("vendor"))?;
-        fs::create_dir(path.join("dir2").join("vendor").join("target"))?;
-        fs::create_dir(path.join(".hidden"))?;
+        config.source = RootSource::Env;
+        assert_eq!(root_source(&config)?, RootSource::Env);
 
-        let found = finddir(path, &args, false)?;
-        assert!(found.is_some());
-        let found_path = found.unwrap();
-        assert_eq!(found_path, path.join("dir1").join("target"));
+        config.source = RootSource::Config;
+        config.roots.clear();
+        assert!(root_source(&config).is_err());
 
         Ok(())
     }
 
     #[test]
-    fn test_finddir_not_found() -> Result<()> {
+    #[cfg(unix)]
+    fn test_root_path() -> Result<()> {
         let dir = tempdir()?;
-        let path = dir.path();
-        let args = Args {
-            include_vendor: false,
-            include_hidden: false,
-            sort_alphabetically: false,
-            folder_name: "target".to_string(),
-        };
+        let storage = dir.path().join("storage").join("projects");
+        fs::create_dir_all(&storage)?;
+        let link = dir.path().join("projects");
+        std::os::unix::fs::symlink(&storage, &link)?;
 
-        // Create directories
-        fs::create_dir(path.join("dir1"))?;
-        fs::create_dir(path.join("dir2"))?;
-        fs::create_dir(path.join("dir1").join("subdir1"))?;
-        fs::create_dir(path.join("dir2").join("vendor"))?;
-        fs::create_dir(path.join(".hidden"))?;
+        let mut config = Config::default();
+        assert_eq!(root_path(&link, &config)?, storage.canonicalize()?);
 
-        let found = finddir(path, &args, false)?;
-        assert!(found.is_none());
+        config.no_follow_root = true;
+        assert_eq!(root_path(&link, &config)?, link);
+        assert!(root_path(&dir.path().join("missing"), &config).is_err());
 
         Ok(())
     }
 
     #[test]
-    fn test_finddir_inside_vendor() -> Result<()> {
+    fn test_empty_folders() -> Result<()> {
         let dir = tempdir()?;
-        let path = dir.path();
-        let args = Args {
-            include_vendor: true,
-            include_hidden: false,
-            sort_alphabetically: false,
-            folder_name: "target".to_string(),
+        let root = dir.path();
+        let markers = vec![".git".to_string()];
+        fs::create_dir_all(root.join("old").join("deep").join("deeper"))?;
+        fs::create_dir_all(root.join("old").join("empty"))?;
+        fs::create_dir_all(root.join("work").join("empty"))?;
+        fs::write(root.join("work").join("notes.txt"), "")?;
+        fs::create_dir_all(root.join("project").join(".git"))?;
+        fs::create_dir_all(root.join("project").join("empty"))?;
+        fs::create_dir_all(root.join(".cache"))?;
+
+        let mut found = Vec::new();
+        assert!(!empty_folders(root, &markers, &mut found));
+        found.sort();
+        assert_eq!(found, [root.join("old"), root.join("work").join("empty")]);
+
+        remove_empty(&root.join("old"))?;
+        assert!(!root.join("old").exists());
+        assert!(remove_empty(&root.join("work")).is_err());
+        assert!(root.join("work").join("notes.txt").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_key() {
+        let key = |path: &str, ignore_case| {
+            duplicate_key(Path::new(path), DuplicateKey::Name, ignore_case)
+        };
+        assert_eq!(
+            key("/home/patrickdap/Projects/forks/Autoscaler", false).as_deref(),
+            Some("Autoscaler")
+        );
+        assert_eq!(
+            key("/home/patrickdap/Projects/forks/Autoscaler", true),
+            key("/home/patrickdap/Projects/kubernetes/autoscaler", true)
+        );
+        assert_eq!(key("/", false), None);
+        assert_eq!(
+            duplicate_key(
+                Path::new("/home/patrickdap/Projects/autoscaler"),
+                DuplicateKey::Remote,
+                false
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reachable_roots() -> Result<()> {
+        let root = |path: &str| Root {
+            path: PathBuf::from(path),
+            ..Default::default()
         };
 
-        // Create directories
-        fs::create_dir(path.join("dir1"))?;
-        fs::create_dir(path.join("dir2"))?;
-        fs::create_dir(path.join("dir1").join("extra"))?;
-        fs::create_dir(path.join("dir2").join("vendor"))?;
-        fs::create_dir(path.join("dir2").join("vendor").join("target"))?;
-        fs::create_dir(path.join(".hidden"))?;
+        let roots = reachable_roots(vec![
+            Ok(root(r"D:\code")),
+            Err(anyhow::anyhow!("drive E: is gone")),
+            Ok(root(r"F:\")),
+        ])?;
+        assert_eq!(root_paths(&roots), [r"D:\code", r"F:\"].map(PathBuf::from));
 
-        let found = finddir(path, &args, false)?;
-        assert!(found.is_some());
-        let found_path = found.unwrap();
-        assert_eq!(found_path, path.join("dir2").join("vendor").join("target"));
+        let err = reachable_roots(vec![Err(anyhow::anyhow!("drive E: is gone"))]).unwrap_err();
+        assert_eq!(err.to_string(), "drive E: is gone");
+        assert!(reachable_roots(Vec::new()).is_err());
 
         Ok(())
     }
 
     #[test]
-    fn test_find_inside_vendor_disabled() -> Result<()> {
+    fn test_enclosing_project() -> Result<()> {
         let dir = tempdir()?;
-        let path = dir.path();
-        let args = Args {
-            include_vendor: false,
-            include_hidden: false,
-            sort_alphabetically: false,
-            folder_name: "target".to_string(),
+        let root = dir.path().join("code");
+        fs::create_dir_all(root.join("github.com/me/app/.git"))?;
+        fs::create_dir_all(root.join("github.com/me/app/src/bin"))?;
+        fs::create_dir_all(root.join("scratch"))?;
+        let roots = [Root {
+            path: root.clone(),
+            ..Default::default()
+        }];
+        let markers = vec![".git".to_string()];
+
+        let app = root.join("github.com/me/app");
+        let expected = Enclosing {
+            name: "app".to_string(),
+            root: "code".to_string(),
+            depth: 3,
+            path: app.clone(),
         };
+        assert_eq!(
+            enclosing_project(&app.join("src/bin"), &roots, &markers),
+            Some(expected)
+        );
+        assert_eq!(
+            enclosing_project(&root.join("scratch"), &roots, &markers),
+            None
+        );
+        assert_eq!(enclosing_project(dir.path(), &roots, &markers), None);
+
+        let named = [Root {
+            name: Some("work".to_string()),
+            ..roots[0].clone()
+        }];
+        assert_eq!(
+            enclosing_project(&app, &named, &markers).map(|e| e.root),
+            Some("work".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_vscode_export() {
+        let root = Path::new("/code");
+        let dirs = [(root, root.join("github.com/me/app"))];
+        let args = Args::default();
+        assert_eq!(
+            vscode_workspace(&dirs, &args),
+            Workspace {
+                folders: vec![WorkspaceFolder {
+                    name: "github.com/me/app".to_string(),
+                    path: "/code/github.com/me/app".to_string(),
+                }]
+            }
+        );
+        assert_eq!(
+            vscode_quickpick(&dirs, &args),
+            [QuickPickItem {
+                label: "app".to_string(),
+                description: "github.com/me/app".to_string(),
+                detail: "/code/github.com/me/app".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_emacs_export() {
+        let root = Path::new("/code");
+        let dirs = [(root, root.join("app")), (root, root.join("say \"hi\""))];
+        let args = Args::default();
+        assert_eq!(
+            project_el_list(&dirs, &args),
+            ";;; -*- lisp-data -*-\n((\"/code/app/\")\n (\"/code/say \\\"hi\\\"/\"))\n"
+        );
+        assert_eq!(
+            projectile_bookmarks(&dirs, &args),
+            "(\"/code/app/\" \"/code/say \\\"hi\\\"/\")\n"
+        );
+        assert_eq!(project_el_list(&[], &args), ";;; -*- lisp-data -*-\n()\n");
+    }
+
+    #[test]
+    fn test_project_tree() -> Result<()> {
+        let root = tempdir()?;
+        for dir in [
+            "work/api/src",
+            "work/api/.git",
+            "work/web",
+            "home/api/docs/en",
+        ] {
+            fs::create_dir_all(root.path().join(dir))?;
+        }
+        let (work_api, work_web, home_api) = (
+            root.path().join("work/api"),
+            root.path().join("work/web"),
+            root.path().join("home/api"),
+        );
+        let matches = [work_api.as_path(), work_web.as_path(), home_api.as_path()];
+
+        assert_eq!(
+            project_tree(root.path(), &matches, 0),
+            "├── home/\n│   └── api\n└── work/\n    ├── api\n    └── web\n"
+        );
+        assert_eq!(
+            project_tree(root.path(), &matches[..1], 1),
+            "└── work/\n    └── api\n        └── src/\n"
+        );
+        assert_eq!(
+            project_tree(root.path(), &matches[2..], 2),
+            "└── home/\n    └── api\n        └── docs/\n            └── en/\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_outermost() {
+        let root = Path::new("/work");
+        let dirs = [
+            (root, root.join("api")),
+            (root, root.join("api/vendor/api")),
+            (root, root.join("web/api")),
+        ];
+        assert_eq!(
+            outermost(&dirs),
+            [Path::new("/work/api"), Path::new("/work/web/api")]
+        );
+    }
 
-        // Create directories
-        fs::create_dir(path.join("dir1"))?;
-        fs::create_dir(path.join("dir2"))?;
-        fs::create_dir(path.join("dir1").join("extra"))?;
-        fs::create_dir(path.join("dir2").join("vendor"))?;
-        fs::create_dir(path.join("dir2").join("vendor").join("target"))?;
-        fs::create_dir(path.join(".hidden"))?;
+    #[test]
+    fn test_read_choice() -> Result<()> {
+        let mut prompt = Vec::new();
+        let choice = read_choice(io::Cursor::new("x\n4\n 2 \n"), &mut prompt, 3)?;
+        assert_eq!(choice, Some(1));
+        assert_eq!(
+            String::from_utf8(prompt)?,
+            "Which one? [1-3] Enter a number from 1 to 3\n\
+             Which one? [1-3] Enter a number from 1 to 3\n\
+             Which one? [1-3] "
+        );
+        assert_eq!(read_choice(io::Cursor::new("\n"), io::sink(), 3)?, None);
+        assert_eq!(read_choice(io::Cursor::new(""), io::sink(), 3)?, None);
+        Ok(())
+    }
 
-        let found = finddir(path, &args, false)?;
-        assert!(found.is_none());
+    #[test]
+    fn test_rank_matches() -> Result<()> {
+        let dir = tempdir()?;
+        let (work, home) = (dir.path().join("work"), dir.path().join("home"));
+        let mut db = Database::load_from(dir.path().join("frecency"))?;
+        db.record(&home.join("old/api"), 100);
 
+        let mut dirs = vec![
+            (work.as_path(), work.join("b/api")),
+            (work.as_path(), work.join("x/y/api")),
+            (home.as_path(), home.join("a/api")),
+            (home.as_path(), home.join("old/api")),
+        ];
+        rank_matches(&mut dirs, Some(&db), &Ranking::default(), 100);
+        let ranked: Vec<&Path> = dirs.iter().map(|(_, dir)| dir.as_path()).collect();
+        assert_eq!(
+            ranked,
+            [
+                home.join("old/api"),
+                home.join("a/api"),
+                work.join("b/api"),
+                work.join("x/y/api"),
+            ]
+        );
         Ok(())
     }
 
     #[test]
-    fn test_find_inside_hidden() -> Result<()> {
+    fn test_rank_by_fuzzy_score() {
+        let root = Path::new("/projects");
+        let mut dirs = ["kube-system-8s", "k8s-control", "acme/k8s-api"]
+            .map(|dir| (root, root.join(dir)))
+            .to_vec();
+        rank_by_fuzzy_score(&mut dirs, "k8s");
+        let ranked: Vec<_> = dirs.into_iter().map(|(_, dir)| dir).collect();
+        assert_eq!(
+            ranked,
+            [
+                root.join("k8s-control"),
+                root.join("acme/k8s-api"),
+                root.join("kube-system-8s"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_by_root() {
+        let roots = [
+            Root {
+                path: PathBuf::from("/work"),
+                ..Default::default()
+            },
+            Root {
+                path: PathBuf::from("/home"),
+                ..Default::default()
+            },
+            Root {
+                path: PathBuf::from("/empty"),
+                ..Default::default()
+            },
+        ];
+        let (work, home) = (Path::new("/work"), Path::new("/home"));
+        let dirs = [
+            (work, work.join("api")),
+            (home, home.join("api")),
+            (work, work.join("old/api")),
+        ];
+        assert_eq!(
+            count_by_root(&roots, &dirs),
+            [(work, 2), (home, 1), (Path::new("/empty"), 0)]
+        );
+    }
+
+    #[test]
+    fn test_direnv_script() {
+        assert_eq!(
+            direnv_script("/home/me/my project", DirenvShell::Bash),
+            "cd -- '/home/me/my project' && eval \"$(direnv export bash)\""
+        );
+        assert_eq!(
+            direnv_script("/home/me/it's", DirenvShell::Zsh),
+            "cd -- '/home/me/it'\\''s' && eval \"$(direnv export zsh)\""
+        );
+        assert_eq!(
+            direnv_script("/home/me/it's", DirenvShell::Fish),
+            "cd '/home/me/it\\'s'; and direnv export fish | source"
+        );
+    }
+
+    #[test]
+    fn test_miss_key() -> Result<()> {
         let dir = tempdir()?;
-        let path = dir.path();
-        let args = Args {
-            include_vendor: false,
+        let roots = [Root {
+            path: dir.path().to_path_buf(),
+            ..Default::default()
+        }];
+        // The root changed a while ago, so adding a folder to it changes
+        // the time, even on file systems that keep it to the second.
+        let past = SystemTime::now() - Duration::from_secs(60 * 60);
+        fs::File::open(dir.path())?.set_modified(past)?;
+        let (args, config) = (Args::default(), Config::default());
+        let key = miss_key(&roots, "api", &args, &config);
+        assert_eq!(miss_key(&roots, "api", &args, &config), key);
+        assert_ne!(miss_key(&roots, "web", &args, &config), key);
+
+        let hidden = Args {
             include_hidden: true,
-            sort_alphabetically: false,
-            folder_name: "target".to_string(),
+            ..Args::default()
+        };
+        assert_ne!(miss_key(&roots, "api", &hidden, &config), key);
+
+        fs::create_dir(dir.path().join("api"))?;
+        assert_ne!(miss_key(&roots, "api", &args, &config), key);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_in_roots() -> Result<()> {
+        let dir = tempdir()?;
+        let (first, second) = (dir.path().join("first"), dir.path().join("second"));
+        fs::create_dir_all(first.join("deep").join("deeper").join("api"))?;
+        fs::create_dir_all(second.join("api"))?;
+        fs::create_dir_all(second.join("web"))?;
+        let roots = [&first, &dir.path().join("missing"), &second].map(|path| Root {
+            path: path.clone(),
+            ..Default::default()
+        });
+        let (args, config) = (Args::default(), Config::default());
+
+        // The first root with a match wins, however deep it is there.
+        assert_eq!(
+            find_in_roots(&roots, "api", &args, &config, false)?,
+            Some(first.join("deep").join("deeper").join("api"))
+        );
+        assert_eq!(
+            find_in_roots(&roots, "web", &args, &config, false)?,
+            Some(second.join("web"))
+        );
+        assert_eq!(find_in_roots(&roots, "docs", &args, &config, false)?, None);
+
+        let all = find_all_in_roots(&roots, "api", &args, &config, false)?;
+        assert_eq!(
+            all.into_iter().map(|(_, dir)| dir).collect::<Vec<_>>(),
+            [
+                first.join("deep").join("deeper").join("api"),
+                second.join("api")
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_found() -> Result<()> {
+        let roots = ["first", "second", "third"].map(|path| Root {
+            path: PathBuf::from(path),
+            ..Default::default()
+        });
+        let missing = || FindError::RootNotAccessible {
+            path: PathBuf::from("first"),
+            source: io::ErrorKind::NotFound.into(),
         };
 
-        // Create directories
-        fs::create_dir(path.join("dir1"))?;
-        fs::create_dir(path.join("dir2"))?;
-        fs::create_dir(path.join("dir1").join("extra"))?;
-        fs::create_dir(path.join("dir2").join("vendor"))?;
-        fs::create_dir(path.join("dir2").join("vendor").join("target"))?;
-        fs::create_dir(path.join(".hidden"))?;
-        fs::create_dir(path.join(".hidden").join("target"))?;
+        // The first root with a match wins, whichever search finishes
+        // first, and a root that can't be read is skipped.
+        let (sender, receiver) = mpsc::channel();
+        sender.send((2, Ok(Some(PathBuf::from("third/api")))))?;
+        sender.send((1, Ok(Some(PathBuf::from("second/api")))))?;
+        sender.send((0, Err(missing())))?;
+        assert_eq!(
+            first_found(&roots, &receiver)?,
+            Some(PathBuf::from("second/api"))
+        );
 
-        let found = finddir(path, &args, false)?;
-        assert!(found.is_some());
-        let found_path = found.unwrap();
-        assert_eq!(found_path, path.join(".hidden").join("target"));
+        // A search that panicked fails the search, unless an earlier root
+        // already had a match.
+        let (sender, receiver) = mpsc::channel();
+        sender.send((
+            1,
+            Err(FindError::Panicked {
+                path: PathBuf::from("second"),
+            }),
+        ))?;
+        sender.send((0, Ok(None)))?;
+        assert!(first_found(&roots, &receiver).is_err());
+        let (sender, receiver) = mpsc::channel();
+        sender.send((
+            1,
+            Err(FindError::Panicked {
+                path: PathBuf::from("second"),
+            }),
+        ))?;
+        sender.send((0, Ok(Some(PathBuf::from("first/api")))))?;
+        assert_eq!(
+            first_found(&roots, &receiver)?,
+            Some(PathBuf::from("first/api"))
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_find_inside_hidden_disabled() -> Result<()> {
+    fn test_max_per_root() -> Result<()> {
         let dir = tempdir()?;
-        let path = dir.path();
-        let args = Args {
-            include_vendor: false,
-            include_hidden: false,
-            sort_alphabetically: false,
-            folder_name: "target".to_string(),
-        };
+        let (large, small) = (dir.path().join("large"), dir.path().join("small"));
+        for project in ["a", "b", "c"] {
+            fs::create_dir_all(large.join(project).join("api"))?;
+        }
+        fs::create_dir_all(small.join("api"))?;
+        let roots = [&large, &small].map(|path| Root {
+            path: path.clone(),
+            ..Default::default()
+        });
+        let mut args = Args::default();
+        let config = Config::default();
+        assert_eq!(
+            find_all_in_roots(&roots, "api", &args, &config, false)?.len(),
+            4
+        );
+
+        args.list.max_per_root = Some(2);
+        let all = find_all_in_roots(&roots, "api", &args, &config, false)?;
+        assert_eq!(
+            count_by_root(&roots, &all),
+            [(large.as_path(), 2), (small.as_path(), 1)]
+        );
+
+        args.list.max_per_root = Some(0);
+        assert!(find_all_in_roots(&roots, "api", &args, &config, false)?.is_empty());
+        Ok(())
+    }
 
-        // Create directories
-        fs::create_dir(path.join("dir1"))?;
-        fs::create_dir(path.join("dir2"))?;
-        fs::create_dir(path.join("dir1").join("extra"))?;
-        fs::create_dir(path.join("dir2").join("vendor"))?;
-        fs::create_dir(path.join("dir2").join("vendor").join("target"))?;
-        fs::create_dir(path.join(".hidden"))?;
-        fs::create_dir(path.join(".hidden").join("target"))?;
+    #[test]
+    fn test_macos_exclusions() -> Result<()> {
+        let dir = tempdir()?;
+        let home = dir.path().join("Users").join("patrickdap");
+        fs::create_dir_all(home.join("Library").join("api"))?;
+        fs::create_dir_all(home.join("Applications").join("Editor.app").join("api"))?;
+        fs::create_dir_all(home.join("Projects").join("api"))?;
+
+        assert!(macos_exclusions(&home.join("Projects"), &home).is_empty());
+        for root in [home.clone(), dir.path().join("Users")] {
+            let exclude = macos_exclusions(&root, &home);
+            let finder = Finder::new(FinderOptions {
+                root,
+                exclude,
+                ..Default::default()
+            });
+            assert_eq!(
+                finder.find_all("api")?,
+                vec![home.join("Projects").join("api")]
+            );
+        }
+        Ok(())
+    }
 
-        let found = finddir(path, &args, false)?;
-        assert!(found.is_none());
+    #[test]
+    fn test_plugin_arguments() -> Result<()> {
+        // A name is always a folder to search for, even when a plugin
+        // has it, and plugins only run through "run".
+        let args = Args::try_parse_from(["find-project", "sync"])?;
+        assert_eq!(args.folder_name.as_deref(), Some("sync"));
+        assert!(args.command.is_none());
 
+        let args = Args::try_parse_from(["find-project", "run", "sync", "--dry-run", "api"])?;
+        let Some(Command::Run { plugin, args }) = args.command else {
+            panic!("\"run\" is a command");
+        };
+        assert_eq!(plugin, "sync");
+        assert_eq!(args, ["--dry-run", "api"]);
         Ok(())
     }
 }
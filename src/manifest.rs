@@ -0,0 +1,285 @@
+//! Names projects declare for themselves in their manifest files, which
+//! often differ from the name of their folder after a rename, and what
+//! their README says they are.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::str::Chars;
+
+/// A manifest file declaring the name of the project in its folder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Manifest {
+    /// The module path in `go.mod`, like "github.com/org/mod".
+    GoModule,
+    /// The package name in `package.json`, like "@org/pkg".
+    NpmPackage,
+    /// The package name in `Cargo.toml`. Dashes and underscores are the
+    /// same in crate names, like Cargo treats them.
+    Crate,
+    /// The title of the README and the line after it, which matches when
+    /// it has every word searched for, ignoring case.
+    Readme,
+}
+
+impl Manifest {
+    /// Returns the name the manifest in a folder declares, or what its
+    /// README says it is, or None when the folder has none, or it
+    /// declares no name.
+    pub fn name(self, dir: &Path) -> Option<String> {
+        match self {
+            Manifest::GoModule => go_module(&fs::read_to_string(dir.join("go.mod")).ok()?),
+            Manifest::NpmPackage => {
+                npm_package(&fs::read_to_string(dir.join("package.json")).ok()?)
+            }
+            Manifest::Crate => crate_name(&fs::read_to_string(dir.join("Cargo.toml")).ok()?),
+            Manifest::Readme => readme_description(&read_readme(dir)?),
+        }
+    }
+
+    /// Returns whether the manifest in a folder declares this name, or
+    /// for a README, whether it has every word of it.
+    pub fn declares(self, dir: &Path, name: &str) -> bool {
+        self.name(dir).is_some_and(|declared| match self {
+            Manifest::Crate => declared.replace('-', "_") == name.replace('-', "_"),
+            Manifest::Readme => {
+                let declared = declared.to_lowercase();
+                name.to_lowercase()
+                    .split_whitespace()
+                    .all(|word| declared.contains(word))
+            }
+            _ => declared == name,
+        })
+    }
+}
+
+// How much of a README is read: its title is at the top.
+const README_BYTES: u64 = 8 * 1024;
+
+// Reads the start of the README in a folder, whatever its case and
+// extension, like README.md, readme.rst or README.
+fn read_readme(dir: &Path) -> Option<String> {
+    let path = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_stem()
+                .is_some_and(|stem| stem.eq_ignore_ascii_case("readme"))
+                && path.is_file()
+        })?;
+    let mut contents = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(README_BYTES)
+        .read_to_end(&mut contents)
+        .ok()?;
+    Some(String::from_utf8_lossy(&contents).into_owned())
+}
+
+// Returns the title of a README and the line after it, skipping badges,
+// HTML and the lines underlining titles.
+fn readme_description(contents: &str) -> Option<String> {
+    let lines: Vec<&str> = contents
+        .lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .filter(|line| {
+            !line.is_empty()
+                && !line.starts_with("[![")
+                && !line.starts_with("![")
+                && !line.starts_with('<')
+                && !line.chars().all(|c| matches!(c, '=' | '-' | '~' | '*'))
+        })
+        .take(2)
+        .collect();
+    Some(lines.join(" ")).filter(|description| !description.is_empty())
+}
+
+// Returns the module path a go.mod declares: the first "module" directive,
+// quoted or not, ignoring comments.
+fn go_module(contents: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let line = line.split("//").next()?.trim();
+        let path = line.strip_prefix("module")?;
+        if !path.starts_with([' ', '\t', '"', '`']) {
+            return None;
+        }
+        let path = path.trim().trim_matches(['"', '`']);
+        Some(path.to_string()).filter(|path| !path.is_empty())
+    })
+}
+
+// Returns the name in the [package] table of a Cargo.toml. Workspace
+// roots without a package of their own have none.
+fn crate_name(contents: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line.split('#').next()?.trim() == "[package]";
+            continue;
+        }
+        if !in_package {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim() == "name" {
+            let value = value.trim();
+            let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+            return value[1..].split(quote).next().map(str::to_string);
+        }
+    }
+    None
+}
+
+// Returns the "name" of the object a package.json holds. Only the keys of
+// that object are looked at, not the ones of the objects inside it, like
+// the dependencies, so JSON is scanned rather than parsed.
+fn npm_package(contents: &str) -> Option<String> {
+    let mut chars = contents.chars();
+    let mut depth = 0;
+    // The last string read in the object, which is a key if a colon
+    // follows it, and whether the value being read is the name's.
+    let mut key = None;
+    let mut in_name = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            '"' => {
+                let string = json_string(&mut chars)?;
+                if depth == 1 {
+                    if in_name {
+                        return Some(string);
+                    }
+                    key = Some(string);
+                }
+            }
+            ':' if depth == 1 => in_name = key.take().as_deref() == Some("name"),
+            ',' if depth == 1 => in_name = false,
+            _ => {}
+        }
+    }
+    None
+}
+
+// Reads a JSON string up to its closing quote, decoding its escapes.
+fn json_string(chars: &mut Chars) -> Option<String> {
+    let mut string = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(string),
+            '\\' => string.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+                }
+                c => c,
+            }),
+            c => string.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_go_module() -> Result<()> {
+        assert_eq!(
+            go_module(
+                "// Renamed from old-name\nmodule github.com/org/mod // v2 soon\n\ngo 1.22\n"
+            ),
+            Some("github.com/org/mod".to_string())
+        );
+        assert_eq!(
+            go_module("module \"example.com/quoted\"\n"),
+            Some("example.com/quoted".to_string())
+        );
+        assert_eq!(go_module("modules are elsewhere\ngo 1.22\n"), None);
+
+        let dir = tempdir()?;
+        assert_eq!(Manifest::GoModule.name(dir.path()), None);
+        fs::write(dir.path().join("go.mod"), "module example.com/tool\n")?;
+        assert_eq!(
+            Manifest::GoModule.name(dir.path()),
+            Some("example.com/tool".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_npm_package() {
+        let package = r#"{
+  "description": "Says \"name\": \"nope\"",
+  "dependencies": {"name": "not-this-one"},
+  "keywords": ["name", "x"],
+  "name": "@org/pkg",
+  "version": "1.0.0"
+}"#;
+        assert_eq!(npm_package(package), Some("@org/pkg".to_string()));
+        assert_eq!(
+            npm_package(r#"{"name":"caf\u00e9"}"#),
+            Some("café".to_string())
+        );
+        assert_eq!(npm_package(r#"{"private": true}"#), None);
+        assert_eq!(npm_package(r#"{"config": {"name": "x"}}"#), None);
+    }
+
+    #[test]
+    fn test_crate_name() -> Result<()> {
+        let manifest = r#"
+[workspace]
+members = ["crates/*"]
+
+[package] # the root crate
+version = "0.1.0"
+name = 'find-project'
+
+[dependencies]
+name = "not-this-one"
+"#;
+        assert_eq!(crate_name(manifest), Some("find-project".to_string()));
+        assert_eq!(crate_name("[workspace]\nmembers = [\"a\"]\n"), None);
+
+        let dir = tempdir()?;
+        fs::write(dir.path().join("Cargo.toml"), manifest)?;
+        assert!(Manifest::Crate.declares(dir.path(), "find_project"));
+        assert!(Manifest::Crate.declares(dir.path(), "find-project"));
+        assert!(!Manifest::Crate.declares(dir.path(), "find"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_readme_description() -> Result<()> {
+        let readme = "[![CI](https://example.com/badge.svg)](https://example.com)\n\n\
+            # hooky\n\n\
+            Delivers webhooks, with retries and backoff.\n\n\
+            ## Installation\n";
+        assert_eq!(
+            readme_description(readme),
+            Some("hooky Delivers webhooks, with retries and backoff.".to_string())
+        );
+        assert_eq!(
+            readme_description("hooky\n=====\n\nA webhook relay.\n"),
+            Some("hooky A webhook relay.".to_string())
+        );
+        assert_eq!(readme_description("\n<p align=\"center\">\n"), None);
+
+        let dir = tempdir()?;
+        fs::write(dir.path().join("Readme.markdown"), readme)?;
+        assert!(Manifest::Readme.declares(dir.path(), "Webhook retries"));
+        assert!(!Manifest::Readme.declares(dir.path(), "webhook queue"));
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use crate::error::Result;
+use crate::store;
+use std::path::PathBuf;
+
+/// A search that found nothing, identified by a key covering the query
+/// and everything else that decides what it finds.
+#[derive(Debug, PartialEq)]
+pub struct Miss {
+    pub time: u64,
+    pub key: String,
+}
+
+/// Searches that recently found nothing, so repeating them, like a typo
+/// typed twice or a prompt checking for a project that isn't there, can
+/// fail without walking the roots again.
+pub struct Misses {
+    path: PathBuf,
+    misses: Vec<Miss>,
+}
+
+impl Misses {
+    /// Loads the misses stored in the data directory.
+    pub fn load() -> Result<Self> {
+        Self::load_from(store::data_dir()?.join("misses"))
+    }
+
+    pub fn load_from(path: PathBuf) -> Result<Self> {
+        let misses = store::read_lines(&path)?
+            .into_iter()
+            .filter_map(|line| {
+                let (time, key) = line.split_once('\t')?;
+                Some(Miss {
+                    time: time.parse().ok()?,
+                    key: key.to_string(),
+                })
+            })
+            .collect();
+        Ok(Misses { path, misses })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        store::write_lines(
+            &self.path,
+            self.misses.iter().map(|m| format!("{}\t{}", m.time, m.key)),
+        )
+    }
+
+    /// Returns when the search with this key last found nothing, if it
+    /// was less than ttl seconds ago.
+    pub fn get(&self, key: &str, now: u64, ttl: u64) -> Option<u64> {
+        self.misses
+            .iter()
+            .find(|m| m.key == key && now.saturating_sub(m.time) < ttl)
+            .map(|m| m.time)
+    }
+
+    /// Forgets every miss, so every search walks the roots again.
+    pub fn clear(&mut self) {
+        self.misses.clear();
+    }
+
+    /// Records that the search with this key found nothing, and forgets
+    /// the misses older than ttl seconds.
+    pub fn record(&mut self, key: &str, now: u64, ttl: u64) {
+        self.misses
+            .retain(|m| m.key != key && now.saturating_sub(m.time) < ttl);
+        self.misses.push(Miss {
+            time: now,
+            key: key.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_misses() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("misses");
+
+        let mut misses = Misses::load_from(path.clone())?;
+        assert_eq!(misses.get("typo", 100, 30), None);
+        misses.record("typo", 100, 30);
+        misses.record("old", 60, 30);
+        misses.save()?;
+
+        let mut misses = Misses::load_from(path)?;
+        assert_eq!(misses.get("typo", 120, 30), Some(100));
+        assert_eq!(misses.get("typo", 130, 30), None);
+        assert_eq!(misses.get("other", 120, 30), None);
+
+        // Recording again replaces the miss and forgets expired ones.
+        misses.record("typo", 140, 30);
+        assert_eq!(
+            misses.misses,
+            [Miss {
+                time: 140,
+                key: "typo".to_string()
+            }]
+        );
+        Ok(())
+    }
+}
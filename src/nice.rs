@@ -0,0 +1,114 @@
+use std::io;
+
+/// Lowers the I/O priority of the whole process, so reading thousands of
+/// folders only uses the disk when nothing else needs it: the idle class
+/// on Linux, throttled I/O on macOS, and background mode on Windows.
+pub fn lower_io_priority() -> io::Result<()> {
+    imp::lower_io_priority()
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod imp {
+    use std::ffi::{c_int, c_long};
+    use std::io;
+
+    #[cfg(target_arch = "x86_64")]
+    const SYS_IOPRIO_SET: c_long = 251;
+    #[cfg(target_arch = "aarch64")]
+    const SYS_IOPRIO_SET: c_long = 30;
+    const IOPRIO_WHO_PROCESS: c_int = 1;
+    const IOPRIO_CLASS_IDLE: c_int = 3;
+    const IOPRIO_CLASS_SHIFT: c_int = 13;
+
+    extern "C" {
+        fn syscall(number: c_long, ...) -> c_long;
+    }
+
+    pub fn lower_io_priority() -> io::Result<()> {
+        // Process 0 is the calling one, and the idle class has no levels.
+        let priority = IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT;
+        match unsafe { syscall(SYS_IOPRIO_SET, IOPRIO_WHO_PROCESS, 0 as c_int, priority) } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use std::ffi::c_int;
+    use std::io;
+
+    const IOPOL_TYPE_DISK: c_int = 0;
+    const IOPOL_SCOPE_PROCESS: c_int = 0;
+    const IOPOL_THROTTLE: c_int = 3;
+
+    extern "C" {
+        fn setiopolicy_np(io_type: c_int, scope: c_int, policy: c_int) -> c_int;
+    }
+
+    pub fn lower_io_priority() -> io::Result<()> {
+        match unsafe { setiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_PROCESS, IOPOL_THROTTLE) } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::ffi::c_void;
+    use std::io;
+
+    // Lowers the I/O and memory priority of the process, not only its CPU
+    // priority, which is what the other priority classes change.
+    const PROCESS_MODE_BACKGROUND_BEGIN: u32 = 0x0010_0000;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentProcess() -> *mut c_void;
+        fn SetPriorityClass(process: *mut c_void, priority_class: u32) -> i32;
+    }
+
+    pub fn lower_io_priority() -> io::Result<()> {
+        match unsafe { SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) } {
+            0 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(not(any(
+    all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ),
+    target_os = "macos",
+    windows
+)))]
+mod imp {
+    use std::io;
+
+    pub fn lower_io_priority() -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "not supported on this platform",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_lower_io_priority() -> io::Result<()> {
+        // Lowering it is always allowed, and only makes this test's own
+        // I/O wait for other processes.
+        lower_io_priority()
+    }
+}
@@ -0,0 +1,123 @@
+use anyhow::{bail, Result};
+use std::mem;
+
+/// Splits a string into arguments the way a POSIX shell would, honoring
+/// single quotes, double quotes and backslash escapes, so `$FP_OPTS` can
+/// hold values with spaces in them.
+pub fn split(s: &str) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => bail!("Unterminated single quote in {:?}", s),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => bail!("Unterminated double quote in {:?}", s),
+                        },
+                        Some(c) => current.push(c),
+                        None => bail!("Unterminated double quote in {:?}", s),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => current.push(c),
+                    None => bail!("Trailing backslash in {:?}", s),
+                }
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    args.push(mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+
+    if in_word {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+/// Quotes a value so a POSIX shell, and [`split`], read it back as a
+/// single argument. Values made only of safe characters are left alone.
+pub fn quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:,=+@%".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Quotes every value and joins them with spaces, so [`split`] returns
+/// the same values.
+pub fn join(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| quote(value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() -> Result<()> {
+        assert_eq!(
+            split("  --include-hidden   --prune node_modules ")?,
+            vec!["--include-hidden", "--prune", "node_modules"]
+        );
+        assert_eq!(
+            split(r#"--prune 'My Stuff' --prune "old \"code\"" a\ b ''"#)?,
+            vec!["--prune", "My Stuff", "--prune", "old \"code\"", "a b", ""]
+        );
+        assert!(split("--prune 'oops").is_err());
+        assert!(split("--prune \"oops").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote() -> Result<()> {
+        assert_eq!(quote("node_modules"), "node_modules");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("My Stuff"), "'My Stuff'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+
+        let values = vec![
+            "--prune".to_string(),
+            "it's mine".to_string(),
+            String::new(),
+        ];
+        assert_eq!(split(&join(&values))?, values);
+        Ok(())
+    }
+}
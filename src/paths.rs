@@ -0,0 +1,275 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Whether the default file system of the platform ignores case, so
+/// "MyProj" and "myproj" are the same folder.
+pub const FOLDS_CASE: bool = cfg!(any(target_os = "macos", windows));
+
+/// Returns whether the last component of a path is the given name.
+pub fn same_name(path: &Path, name: &str, ignore_case: bool) -> bool {
+    match path.file_name() {
+        Some(file_name) if ignore_case => {
+            file_name.to_string_lossy().to_lowercase() == name.to_lowercase()
+        }
+        file_name => file_name == Some(Path::new(name).as_os_str()),
+    }
+}
+
+/// Makes a path absolute and resolves its symlinks, like
+/// [`Path::canonicalize`], without the `\\?\` prefix it adds on Windows
+/// whenever the path works without it. Many programs reading the paths
+/// printed can't handle the prefix, and the standard library already
+/// adds it back when opening paths longer than 260 characters.
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    path.canonicalize().map(simplified)
+}
+
+/// Returns where a path ends up when the folder `from`, which it's in or
+/// is, moves to `to`. Returns None for paths somewhere else.
+pub fn moved(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix(from).ok()?;
+    // Joining an empty path would add a trailing separator.
+    if rest.as_os_str().is_empty() {
+        Some(to.to_path_buf())
+    } else {
+        Some(to.join(rest))
+    }
+}
+
+/// What a folder is, as opposed to how it was reached: two paths leading
+/// to the same folder through symlinks or bind mounts have the same ID.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileId {
+    /// The device and inode numbers, where the platform has them.
+    Inode(u64, u64),
+    /// The canonical path everywhere else.
+    Path(PathBuf),
+}
+
+/// Returns the ID of the file or folder a path leads to.
+pub fn file_id(path: &Path) -> io::Result<FileId> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = path.metadata()?;
+        Ok(FileId::Inode(metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        canonicalize(path).map(FileId::Path)
+    }
+}
+
+/// Removes the `\\?\` prefix from an extended-length Windows path when
+/// it means the same without it. Other paths are returned as they are.
+pub fn simplified(path: PathBuf) -> PathBuf {
+    if !cfg!(windows) {
+        return path;
+    }
+    match path.to_str().and_then(strip_verbatim) {
+        Some(plain) => PathBuf::from(plain),
+        None => path,
+    }
+}
+
+/// Returns whether this is Linux running under the Windows Subsystem for
+/// Linux.
+pub fn is_wsl() -> bool {
+    cfg!(target_os = "linux")
+        && (std::env::var_os("WSL_DISTRO_NAME").is_some()
+            || std::fs::read_to_string("/proc/sys/kernel/osrelease")
+                .is_ok_and(|release| release.to_lowercase().contains("microsoft")))
+}
+
+/// Translates a WSL path into the path Windows uses for it: drives mounted
+/// like "/mnt/c/Users" become "C:\Users", and anything else is reached
+/// through the `\\wsl$` share of the distribution. Returns None for
+/// relative paths, or when the distribution is needed but unknown.
+pub fn wsl_to_windows(path: &str, distro: Option<&str>) -> Option<String> {
+    if !path.starts_with('/') {
+        return None;
+    }
+    if let Some(rest) = path.strip_prefix("/mnt/") {
+        let (drive, rest) = rest.split_once('/').unwrap_or((rest, ""));
+        if drive.len() == 1 && drive.as_bytes()[0].is_ascii_alphabetic() {
+            return Some(format!(
+                "{}:\\{}",
+                drive.to_ascii_uppercase(),
+                rest.replace('/', "\\")
+            ));
+        }
+    }
+    Some(format!(r"\\wsl$\{}{}", distro?, path.replace('/', "\\")))
+}
+
+/// Translates a Windows path into the path WSL uses for it: "C:\Users"
+/// becomes "/mnt/c/Users", and paths on the `\\wsl$` or
+/// `\\wsl.localhost` shares become the path inside the distribution.
+/// Returns None for other paths.
+pub fn windows_to_wsl(path: &str) -> Option<String> {
+    for share in [r"\\wsl$\", r"\\wsl.localhost\"] {
+        if let Some(rest) = path
+            .get(..share.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(share))
+            .map(|_| &path[share.len()..])
+        {
+            let rest = rest.split_once('\\').map_or("", |(_, rest)| rest);
+            return Some(format!("/{}", rest.replace('\\', "/")));
+        }
+    }
+
+    let bytes = path.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        let rest = path[2..].trim_start_matches(['\\', '/']).replace('\\', "/");
+        let drive = (bytes[0] as char).to_ascii_lowercase();
+        return Some(if rest.is_empty() {
+            format!("/mnt/{}", drive)
+        } else {
+            format!("/mnt/{}/{}", drive, rest)
+        });
+    }
+    None
+}
+
+// Returns the path without its verbatim prefix, turning "\\?\C:\dir" into
+// "C:\dir" and "\\?\UNC\server\share" into "\\server\share", unless it
+// has parts Windows would change when parsing it without the prefix.
+fn strip_verbatim(path: &str) -> Option<String> {
+    let (plain, rest) = if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        (format!(r"\\{}", rest), rest)
+    } else {
+        let rest = path.strip_prefix(r"\\?\")?;
+        let bytes = rest.as_bytes();
+        if bytes.len() < 3 || !bytes[0].is_ascii_alphabetic() || &bytes[1..3] != b":\\" {
+            return None;
+        }
+        (rest.to_string(), &rest[3..])
+    };
+
+    let valid = rest
+        .split('\\')
+        .filter(|part| !part.is_empty())
+        .all(|part| {
+            let stem = part.split('.').next().unwrap_or_default().trim_end();
+            part != "."
+                && part != ".."
+                && !part.ends_with(['.', ' '])
+                && !part.contains('/')
+                && !is_reserved(stem)
+        });
+    valid.then_some(plain)
+}
+
+// Device names that refer to the device, rather than a file, without the
+// prefix, no matter the extension.
+fn is_reserved(stem: &str) -> bool {
+    let stem = stem.to_ascii_uppercase();
+    matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.as_bytes()[3].is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wsl_to_windows() {
+        assert_eq!(
+            wsl_to_windows("/mnt/c/Users/patrickdap/Projects", None).as_deref(),
+            Some(r"C:\Users\patrickdap\Projects")
+        );
+        assert_eq!(wsl_to_windows("/mnt/d", None).as_deref(), Some(r"D:\"));
+        assert_eq!(
+            wsl_to_windows("/home/patrickdap/api", Some("Ubuntu")).as_deref(),
+            Some(r"\\wsl$\Ubuntu\home\patrickdap\api")
+        );
+        assert_eq!(wsl_to_windows("/home/patrickdap/api", None), None);
+        assert_eq!(wsl_to_windows("api", Some("Ubuntu")), None);
+    }
+
+    #[test]
+    fn test_windows_to_wsl() {
+        assert_eq!(
+            windows_to_wsl(r"C:\Users\patrickdap\Projects").as_deref(),
+            Some("/mnt/c/Users/patrickdap/Projects")
+        );
+        assert_eq!(windows_to_wsl(r"D:\").as_deref(), Some("/mnt/d"));
+        assert_eq!(
+            windows_to_wsl(r"\\wsl$\Ubuntu\home\patrickdap\api").as_deref(),
+            Some("/home/patrickdap/api")
+        );
+        assert_eq!(
+            windows_to_wsl(r"\\WSL.localhost\Ubuntu\home").as_deref(),
+            Some("/home")
+        );
+        assert_eq!(windows_to_wsl("/home/patrickdap"), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_file_id() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let releases = dir.path().join("releases/2024");
+        std::fs::create_dir_all(&releases)?;
+        std::os::unix::fs::symlink(&releases, dir.path().join("current"))?;
+
+        assert_eq!(file_id(&dir.path().join("current"))?, file_id(&releases)?);
+        assert_ne!(
+            file_id(dir.path().join("releases").as_path())?,
+            file_id(&releases)?
+        );
+        assert!(file_id(&dir.path().join("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_moved() {
+        let from = Path::new("/home/patrickdap/Projects/autoscaler");
+        let to = Path::new("/home/patrickdap/Projects/github.com/kubernetes/autoscaler");
+        assert_eq!(moved(from, from, to).as_deref(), Some(to));
+        assert_eq!(
+            moved(&from.join("charts"), from, to),
+            Some(to.join("charts"))
+        );
+        assert_eq!(
+            moved(
+                Path::new("/home/patrickdap/Projects/autoscaler-old"),
+                from,
+                to
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_same_name() {
+        let path = Path::new("/home/patrickdap/Projects/MyProj");
+        assert!(same_name(path, "MyProj", false));
+        assert!(!same_name(path, "myproj", false));
+        assert!(same_name(path, "myproj", true));
+        assert!(!same_name(path, "Projects", true));
+    }
+
+    #[test]
+    fn test_strip_verbatim() {
+        assert_eq!(
+            strip_verbatim(r"\\?\C:\Users\patrickdap\Projects").as_deref(),
+            Some(r"C:\Users\patrickdap\Projects")
+        );
+        assert_eq!(
+            strip_verbatim(r"\\?\UNC\server\share\api").as_deref(),
+            Some(r"\\server\share\api")
+        );
+        assert_eq!(strip_verbatim(r"C:\Users"), None);
+        assert_eq!(strip_verbatim(r"\\?\Volume{1234}\api"), None);
+        assert_eq!(strip_verbatim(r"\\?\C:\Projects\api."), None);
+        assert_eq!(strip_verbatim(r"\\?\C:\Projects\con.txt"), None);
+        assert_eq!(strip_verbatim(r"\\?\C:\Projects\com1"), None);
+        assert_eq!(
+            strip_verbatim(r"\\?\C:\Projects\console").as_deref(),
+            Some(r"C:\Projects\console")
+        );
+    }
+}
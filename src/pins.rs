@@ -0,0 +1,150 @@
+use crate::error::{FindError, Result};
+use crate::{paths, store};
+use std::path::{Path, PathBuf};
+
+/// Projects pinned by the user. A pinned project is returned for its
+/// folder name before any directory is walked, so it always wins over
+/// other folders sharing the same name.
+pub struct Pins {
+    path: PathBuf,
+    entries: Vec<PathBuf>,
+}
+
+impl Pins {
+    /// Loads the pins stored in the data directory.
+    pub fn load() -> Result<Self> {
+        Self::load_from(store::data_dir()?.join("pins"))
+    }
+
+    pub fn load_from(path: PathBuf) -> Result<Self> {
+        let entries = store::read_lines(&path)?
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        Ok(Pins { path, entries })
+    }
+
+    /// Writes the pins back. A pin moved by `rename` to a path that isn't
+    /// valid UTF-8 can't be stored as text, so it's dropped.
+    pub fn save(&self) -> Result<()> {
+        store::write_lines(&self.path, self.entries.iter().filter_map(|p| p.to_str()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Path> {
+        self.entries.iter().map(PathBuf::as_path)
+    }
+
+    /// Pins a project. Only one project can be pinned per folder name, so
+    /// any previous pin with the same name is replaced and returned. Pins
+    /// are stored as text, so paths that aren't valid UTF-8 can't be
+    /// pinned.
+    pub fn add(&mut self, dir: PathBuf) -> Result<Option<PathBuf>> {
+        if dir.to_str().is_none() {
+            return Err(FindError::Invalid(format!(
+                "Unable to pin {:?}: the path isn't valid UTF-8",
+                dir
+            )));
+        }
+        let replaced = self
+            .entries
+            .iter()
+            .position(|p| p.file_name() == dir.file_name())
+            .map(|i| self.entries.remove(i));
+        self.entries.push(dir);
+        Ok(replaced)
+    }
+
+    /// Removes the pins matching either a folder name or a full path and
+    /// returns them.
+    pub fn remove(&mut self, name_or_path: &str) -> Vec<PathBuf> {
+        let target = Path::new(name_or_path);
+        let (removed, kept) = self
+            .entries
+            .drain(..)
+            .partition(|p| p.as_path() == target || p.file_name() == Some(target.as_os_str()));
+        self.entries = kept;
+        removed
+    }
+
+    /// Moves the pins of a directory, and of the ones inside it, to where
+    /// the directory was moved.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        for entry in &mut self.entries {
+            if let Some(path) = paths::moved(entry, from, to) {
+                *entry = path;
+            }
+        }
+    }
+
+    /// Returns the pinned project for a folder name, as long as it still
+    /// exists on disk.
+    pub fn lookup(&self, name: &str, ignore_case: bool) -> Option<&Path> {
+        self.iter()
+            .find(|p| paths::same_name(p, name, ignore_case) && p.is_dir())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_pins_roundtrip() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+        fs::create_dir_all(path.join("a").join("api"))?;
+        fs::create_dir_all(path.join("b").join("api"))?;
+
+        let mut pins = Pins::load_from(path.join("pins"))?;
+        assert!(pins.lookup("api", false).is_none());
+
+        pins.add(path.join("a").join("api"))?;
+        let replaced = pins.add(path.join("b").join("api"))?;
+        assert_eq!(replaced, Some(path.join("a").join("api")));
+        pins.save()?;
+
+        let mut pins = Pins::load_from(path.join("pins"))?;
+        assert_eq!(
+            pins.lookup("api", false),
+            Some(path.join("b").join("api").as_path())
+        );
+
+        let removed = pins.remove("api");
+        assert_eq!(removed, vec![path.join("b").join("api")]);
+        assert!(pins.lookup("api", false).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pins_skip_missing_directories() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        let mut pins = Pins::load_from(path.join("pins"))?;
+        pins.add(path.join("gone"))?;
+        assert!(pins.lookup("gone", false).is_none());
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pins_reject_invalid_utf8() -> Result<()> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        let mut pins = Pins::load_from(path.join("pins"))?;
+        let invalid = path.join(OsStr::from_bytes(b"caf\xe9"));
+        assert!(pins.add(invalid).is_err());
+        assert_eq!(pins.iter().count(), 0);
+
+        Ok(())
+    }
+}
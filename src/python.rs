@@ -0,0 +1,209 @@
+//! Python bindings, built with maturin:
+//!
+//! ```python
+//! import find_project
+//!
+//! find_project.find("autoscaler", roots=["~/Projects"], max_depth=4)
+//! for path in find_project.iter("api", matcher="fuzzy"):
+//!     print(path)
+//! ```
+
+// #[pyfunction] converts the PyErr returned by find and iter into a PyErr,
+// in wrappers it adds next to them without their attributes, so only an
+// allow on the module reaches them.
+#![allow(clippy::useless_conversion)]
+
+use crate::{Collation, FindError, Finder, FinderOptions, Kind, Matcher};
+use pyo3::exceptions::{PyOSError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::env;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+
+/// Returns the first folder matching the name in any of the roots, or
+/// None.
+#[pyfunction]
+#[pyo3(signature = (name, roots=None, **options))]
+fn find(
+    py: Python<'_>,
+    name: &str,
+    roots: Option<Vec<PathBuf>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Option<PathBuf>> {
+    let finders = finders(roots, options)?;
+    py.allow_threads(|| {
+        for finder in finders {
+            if let Some(dir) = finder.find(name)? {
+                return Ok(Some(dir));
+            }
+        }
+        Ok(None)
+    })
+    .map_err(error)
+}
+
+/// Returns an iterator over every folder matching the name, which
+/// searches as it goes.
+#[pyfunction]
+#[pyo3(signature = (name, roots=None, **options))]
+fn iter(
+    name: String,
+    roots: Option<Vec<PathBuf>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Matches> {
+    let finders = finders(roots, options)?;
+
+    // Finder::iter borrows the finder, so the search runs on its own
+    // thread, one match ahead of Python at most. It stops as soon as the
+    // iterator is dropped.
+    let (sender, receiver) = mpsc::sync_channel(0);
+    thread::spawn(move || {
+        for finder in finders {
+            for found in finder.iter(&name) {
+                if sender.send(found).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(Matches {
+        receiver: Mutex::new(receiver),
+    })
+}
+
+/// Iterator returned by `iter`.
+#[pyclass]
+struct Matches {
+    // Receivers can't be shared between threads, which Python may do.
+    receiver: Mutex<Receiver<Result<PathBuf, FindError>>>,
+}
+
+#[pymethods]
+impl Matches {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<PathBuf>> {
+        let next = py.allow_threads(|| {
+            let receiver = self.receiver.lock().expect("no thread panics holding it");
+            receiver.recv()
+        });
+        match next {
+            Ok(found) => found.map(Some).map_err(error),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+// Returns a finder for every root, defaulting to $FP_FOLDER or
+// $GOPATH/src like the command does.
+fn finders(
+    roots: Option<Vec<PathBuf>>,
+    options: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Vec<Finder>> {
+    let roots = match roots {
+        Some(roots) => roots,
+        None => match env::var("FP_FOLDER") {
+            Ok(root) => vec![root.into()],
+            Err(_) => match env::var("GOPATH") {
+                Ok(gopath) => vec![PathBuf::from(gopath).join("src")],
+                Err(_) => return Err(error(FindError::NoRootConfigured)),
+            },
+        },
+    };
+    roots
+        .into_iter()
+        .map(|root| Ok(Finder::new(finder_options(root, options)?)))
+        .collect()
+}
+
+// Reads the keyword arguments into the options to search a root.
+fn finder_options(root: PathBuf, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<FinderOptions> {
+    let mut options = FinderOptions::new(root);
+    let Some(kwargs) = kwargs else {
+        return Ok(options);
+    };
+    for (key, value) in kwargs.iter() {
+        let key: String = key.extract()?;
+        match key.as_str() {
+            "include_vendor" => options.include_vendor = value.extract()?,
+            "include_hidden" => options.include_hidden = value.extract()?,
+            "sort_alphabetically" => options.sort_alphabetically = value.extract()?,
+            "collation" => {
+                options.collation = match value.extract::<String>()?.as_str() {
+                    "lexical" => Collation::Lexical,
+                    "natural" => Collation::Natural,
+                    "locale" => Collation::Locale,
+                    other => {
+                        return Err(PyValueError::new_err(format!(
+                        "Unknown collation {:?}, expected \"lexical\", \"natural\" or \"locale\"",
+                        other
+                    )))
+                    }
+                }
+            }
+            "max_depth" => options.max_depth = value.extract()?,
+            "prune" => options.prune = value.extract()?,
+            "exclude" => options.exclude = value.extract()?,
+            "markers" => options.markers = value.extract()?,
+            "follow_links" => options.follow_links = value.extract()?,
+            "ignore_case" => options.ignore_case = value.extract()?,
+            "projects_only" => options.projects_only = value.extract()?,
+            "stop_at_projects" => options.stop_at_projects = value.extract()?,
+            "skip_worktrees" => options.skip_worktrees = value.extract()?,
+            "branch" => options.branch = value.extract()?,
+            "matcher" => {
+                options.matcher = match value.extract::<String>()?.as_str() {
+                    "exact" => Matcher::Exact,
+                    "glob" => Matcher::Glob,
+                    "fuzzy" => Matcher::Fuzzy,
+                    other => {
+                        return Err(PyValueError::new_err(format!(
+                            "Unknown matcher {:?}, expected \"exact\", \"glob\" or \"fuzzy\"",
+                            other
+                        )))
+                    }
+                }
+            }
+            "kind" => {
+                options.kind = match value.extract::<String>()?.as_str() {
+                    "dir" => Kind::Dir,
+                    "file" => Kind::File,
+                    "any" => Kind::Any,
+                    other => {
+                        return Err(PyValueError::new_err(format!(
+                            "Unknown kind {:?}, expected \"dir\", \"file\" or \"any\"",
+                            other
+                        )))
+                    }
+                }
+            }
+            other => {
+                return Err(PyTypeError::new_err(format!(
+                    "Unexpected keyword argument {:?}",
+                    other
+                )))
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn error(err: FindError) -> PyErr {
+    match err {
+        FindError::NoRootConfigured => PyValueError::new_err(err.to_string()),
+        err => PyOSError::new_err(err.to_string()),
+    }
+}
+
+#[pymodule]
+fn find_project(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(find, m)?)?;
+    m.add_function(wrap_pyfunction!(iter, m)?)?;
+    m.add_class::<Matches>()?;
+    Ok(())
+}
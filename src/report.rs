@@ -0,0 +1,91 @@
+use crate::error::Result;
+use crate::{is_project, Finder};
+use std::path::PathBuf;
+
+/// A folder found by a search.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Match {
+    pub path: PathBuf,
+    /// How many folders below the root it is.
+    pub depth: usize,
+    /// Whether it contains any of the project markers.
+    pub project: bool,
+}
+
+/// Everything a search found, in the order it was found.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchReport {
+    pub root: PathBuf,
+    pub query: String,
+    pub matches: Vec<Match>,
+}
+
+impl Finder {
+    /// Searches for every folder matching the name, like
+    /// [`Finder::find_all`], and describes each of them.
+    pub fn report(&self, name: &str) -> Result<SearchReport> {
+        let options = self.options();
+        let matches = self
+            .iter(name)
+            .map(|dir| {
+                let dir = dir?;
+                Ok(Match {
+                    depth: dir
+                        .strip_prefix(&options.root)
+                        .map_or(0, |rel| rel.components().count()),
+                    project: is_project(&dir, &options.markers),
+                    path: dir,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(SearchReport {
+            root: options.root.clone(),
+            query: name.to_string(),
+            matches,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FinderOptions;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_report() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path();
+
+        fs::create_dir_all(path.join("api"))?;
+        fs::create_dir_all(path.join("acme").join("api").join(".git"))?;
+
+        let finder = Finder::new(
+            FinderOptions::new(path)
+                .marker(".git")
+                .sort_alphabetically(true),
+        );
+        let report = finder.report("api")?;
+        assert_eq!(report.root, path);
+        assert_eq!(report.query, "api");
+        assert_eq!(
+            report.matches,
+            vec![
+                Match {
+                    path: path.join("acme").join("api"),
+                    depth: 2,
+                    project: true,
+                },
+                Match {
+                    path: path.join("api"),
+                    depth: 1,
+                    project: false,
+                },
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,593 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// A request to the HTTP server, with its path and query decoded.
+#[derive(Debug, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Returns the first value of a query parameter.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// What the HTTP server answers with.
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json(status: u16, body: String) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    /// Returns a response in the Prometheus text format.
+    pub fn metrics(body: String) -> Self {
+        Response {
+            status: 200,
+            content_type: "text/plain; version=0.0.4",
+            body,
+        }
+    }
+
+    /// Returns a JSON response like {"error": "..."}.
+    pub fn error(status: u16, message: &str) -> Self {
+        let body = BTreeMap::from([("error", message)]);
+        Response::json(status, serde_json::to_string(&body).unwrap_or_default())
+    }
+}
+
+/// Answers requests on the listener one at a time, until stop is set.
+/// Clients get a few seconds to send their request, so a stuck one can't
+/// hold the server.
+pub fn http(
+    listener: &TcpListener,
+    stop: &AtomicBool,
+    mut handle: impl FnMut(&Request) -> Response,
+) -> io::Result<()> {
+    // Accepting without blocking lets Ctrl-C stop the server between
+    // requests.
+    listener.set_nonblocking(true)?;
+    while !stop.load(Ordering::Relaxed) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(50));
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        // A client going away is its own problem, not the server's.
+        let _ = answer(stream, &mut handle);
+    }
+    Ok(())
+}
+
+fn answer(stream: TcpStream, handle: &mut impl FnMut(&Request) -> Response) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream);
+    let response = match read_request(&mut reader)? {
+        Some(request) => handle(&request),
+        None => Response::error(400, "Malformed request"),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+// Reads the request line and skips the headers, since no request has a
+// body. Returns None when it isn't HTTP.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Ok(None);
+    };
+    let request = parse_target(method, target);
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+    Ok(Some(request))
+}
+
+fn parse_target(method: &str, target: &str) -> Request {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    Request {
+        method: method.to_string(),
+        path: decode(path),
+        query: query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode(key), decode(value))
+            })
+            .collect(),
+    }
+}
+
+// Decodes %XX escapes, and "+" as a space, which is how browsers write
+// them in queries.
+fn decode(s: &str) -> String {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => match rest
+                .get(..2)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(decoded) => {
+                    bytes.push(decoded);
+                    rest = &rest[2..];
+                }
+                None => bytes.push(b'%'),
+            },
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// Upper bounds of the latency histogram buckets, in seconds, the same as
+// the Prometheus clients use by default.
+const BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// What the HTTP server has done since it started, served at /metrics.
+#[derive(Default)]
+pub struct Metrics {
+    searches: u64,
+    errors: BTreeMap<u16, u64>,
+    matches: u64,
+    // How many searches took up to each of BUCKETS, and all of them.
+    buckets: [u64; BUCKETS.len()],
+    seconds: f64,
+}
+
+impl Metrics {
+    /// Counts a search that took the given time and found the given
+    /// number of folders.
+    pub fn search(&mut self, seconds: f64, matches: usize) {
+        self.searches += 1;
+        self.matches += matches as u64;
+        self.seconds += seconds;
+        for (bucket, le) in self.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= le {
+                *bucket += 1;
+            }
+        }
+    }
+
+    /// Counts a request answered with an error status.
+    pub fn error(&mut self, status: u16) {
+        *self.errors.entry(status).or_default() += 1;
+    }
+
+    /// Returns the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, String)]| {
+            out.push_str(&format!("# HELP find_project_{} {}\n", name, help));
+            out.push_str(&format!("# TYPE find_project_{} {}\n", name, kind));
+            for (labels, value) in samples {
+                out.push_str(&format!("find_project_{}{} {}\n", name, labels, value));
+            }
+        };
+
+        metric(
+            "searches_total",
+            "counter",
+            "Searches answered.",
+            &[(String::new(), self.searches.to_string())],
+        );
+        metric(
+            "matches_total",
+            "counter",
+            "Folders returned by every search.",
+            &[(String::new(), self.matches.to_string())],
+        );
+        let errors: Vec<_> = self
+            .errors
+            .iter()
+            .map(|(status, count)| (format!("{{status=\"{}\"}}", status), count.to_string()))
+            .collect();
+        metric(
+            "errors_total",
+            "counter",
+            "Requests answered with an error, by status code.",
+            &errors,
+        );
+
+        let mut latency: Vec<_> = BUCKETS
+            .iter()
+            .zip(self.buckets)
+            .map(|(le, count)| (format!("_bucket{{le=\"{}\"}}", le), count.to_string()))
+            .collect();
+        latency.push((
+            "_bucket{le=\"+Inf\"}".to_string(),
+            self.searches.to_string(),
+        ));
+        latency.push(("_sum".to_string(), self.seconds.to_string()));
+        latency.push(("_count".to_string(), self.searches.to_string()));
+        metric(
+            "search_duration_seconds",
+            "histogram",
+            "How long searches took.",
+            &latency,
+        );
+        out
+    }
+}
+
+/// JSON-RPC error code for calls with missing or wrong parameters.
+pub const INVALID_PARAMS: i64 = -32602;
+/// JSON-RPC error code for unknown methods.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+const PARSE_ERROR: i64 = -32700;
+const SERVER_ERROR: i64 = -32000;
+
+/// A JSON-RPC 2.0 call, one per line on stdin.
+#[derive(serde::Deserialize)]
+pub struct Call {
+    #[serde(default)]
+    jsonrpc: String,
+    /// None in notifications, which get no answer at all, not even an
+    /// error. A null id is still an id, and is answered.
+    #[serde(default, deserialize_with = "present")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Params,
+}
+
+// Tells a null id apart from a missing one, which serde would otherwise
+// both read as None.
+fn present<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    serde::Deserialize::deserialize(deserializer).map(Some)
+}
+
+/// The parameters of every method, each of which reads the ones it needs.
+#[derive(serde::Deserialize, Default)]
+#[serde(default)]
+pub struct Params {
+    pub name: Option<String>,
+    pub path: Option<PathBuf>,
+    pub roots: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+struct Reply<'a, T> {
+    jsonrpc: &'static str,
+    id: &'a Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ReplyError<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct ReplyError<'a> {
+    code: i64,
+    message: &'a str,
+}
+
+#[derive(serde::Serialize)]
+struct Notification<'a, T> {
+    jsonrpc: &'static str,
+    method: &'a str,
+    params: T,
+}
+
+// The params of a notification sent while answering a call, with the id
+// of the call.
+#[derive(serde::Serialize)]
+struct Found<'a, T> {
+    id: &'a Value,
+    #[serde(rename = "match")]
+    found: T,
+}
+
+/// Writes the answer and notifications of the call being handled to
+/// stdout, one per line, flushing each of them so editors see them right
+/// away. Nothing is written for a notification.
+pub struct Replies<W> {
+    out: W,
+    id: Option<Value>,
+}
+
+impl<W: Write> Replies<W> {
+    pub fn result(&mut self, result: &impl serde::Serialize) -> anyhow::Result<()> {
+        let Some(id) = &self.id else {
+            return Ok(());
+        };
+        send(
+            &mut self.out,
+            &Reply {
+                jsonrpc: "2.0",
+                id,
+                result: Some(result),
+                error: None,
+            },
+        )
+    }
+
+    pub fn error(&mut self, code: i64, message: &str) -> anyhow::Result<()> {
+        let Some(id) = &self.id else {
+            return Ok(());
+        };
+        reply_error(&mut self.out, id, code, message)
+    }
+
+    /// Sends a notification with something found while answering the
+    /// call, like a match, with the id of the call.
+    pub fn notify(&mut self, method: &str, found: &impl serde::Serialize) -> anyhow::Result<()> {
+        let Some(id) = &self.id else {
+            return Ok(());
+        };
+        send(
+            &mut self.out,
+            &Notification {
+                jsonrpc: "2.0",
+                method,
+                params: Found { id, found },
+            },
+        )
+    }
+}
+
+fn reply_error(out: &mut impl Write, id: &Value, code: i64, message: &str) -> anyhow::Result<()> {
+    send(
+        out,
+        &Reply::<()> {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(ReplyError { code, message }),
+        },
+    )
+}
+
+fn send(out: &mut impl Write, message: &impl serde::Serialize) -> anyhow::Result<()> {
+    writeln!(out, "{}", serde_json::to_string(message)?)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Answers the JSON-RPC calls read from input, one per line, until it's
+/// closed. Calls are answered in order, and a call failing is answered
+/// with its error instead of stopping the others. Lines that aren't JSON,
+/// or aren't a JSON-RPC 2.0 call, are answered with an error and a null
+/// id, since there's no telling which call they were.
+pub fn stdio<W: Write>(
+    input: impl BufRead,
+    output: W,
+    mut handle: impl FnMut(&Call, &mut Replies<W>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut replies = Replies {
+        out: output,
+        id: None,
+    };
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let call = match serde_json::from_str(&line) {
+            Ok(value) => serde_json::from_value::<Call>(value),
+            Err(err) => {
+                reply_error(
+                    &mut replies.out,
+                    &Value::Null,
+                    PARSE_ERROR,
+                    &err.to_string(),
+                )?;
+                continue;
+            }
+        };
+        let call = match call {
+            Ok(call) if call.jsonrpc == "2.0" => call,
+            Ok(_) => {
+                let message = "Only JSON-RPC 2.0 calls are answered";
+                reply_error(&mut replies.out, &Value::Null, INVALID_REQUEST, message)?;
+                continue;
+            }
+            Err(err) => {
+                reply_error(
+                    &mut replies.out,
+                    &Value::Null,
+                    INVALID_REQUEST,
+                    &err.to_string(),
+                )?;
+                continue;
+            }
+        };
+        replies.id = call.id.clone();
+        if let Err(err) = handle(&call, &mut replies) {
+            replies.error(SERVER_ERROR, &format!("{:#}", err))?;
+        }
+    }
+    Ok(())
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Answers the lines given with a handler echoing the name searched,
+    // returning what was written back.
+    fn answer_stdio(input: &str) -> anyhow::Result<Vec<Value>> {
+        let mut output = Vec::new();
+        stdio(
+            Cursor::new(input),
+            &mut output,
+            |call, replies| match call.method.as_str() {
+                "search" => {
+                    let name = call.params.name.as_deref().unwrap_or_default();
+                    replies.notify("match", &name)?;
+                    replies.result(&[name])
+                }
+                "fail" => anyhow::bail!("failed"),
+                _ => replies.error(METHOD_NOT_FOUND, "Unknown method"),
+            },
+        )?;
+        String::from_utf8(output)?
+            .lines()
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    #[test]
+    fn test_stdio() -> anyhow::Result<()> {
+        let replies = answer_stdio(concat!(
+            "{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"search\", \"params\": {\"name\": \"api\"}}\n",
+            "\n",
+            "{\"jsonrpc\": \"2.0\", \"id\": null, \"method\": \"other\"}\n",
+        ))?;
+        assert_eq!(
+            replies,
+            [
+                serde_json::json!({"jsonrpc": "2.0", "method": "match", "params": {"id": 1, "match": "api"}}),
+                serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": ["api"]}),
+                serde_json::json!({"jsonrpc": "2.0", "id": null, "error": {"code": METHOD_NOT_FOUND, "message": "Unknown method"}}),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdio_notifications() -> anyhow::Result<()> {
+        // Notifications get no answer, not even when they fail.
+        let replies = answer_stdio(concat!(
+            "{\"jsonrpc\": \"2.0\", \"method\": \"search\", \"params\": {\"name\": \"api\"}}\n",
+            "{\"jsonrpc\": \"2.0\", \"method\": \"fail\"}\n",
+            "{\"jsonrpc\": \"2.0\", \"method\": \"other\"}\n",
+        ))?;
+        assert_eq!(replies, Vec::<Value>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdio_invalid() -> anyhow::Result<()> {
+        let replies = answer_stdio(concat!(
+            "not json\n",
+            "{\"jsonrpc\": \"1.0\", \"id\": 1, \"method\": \"search\"}\n",
+            "{\"id\": 2, \"method\": \"search\"}\n",
+            "{\"jsonrpc\": \"2.0\", \"id\": 3}\n",
+            "{\"jsonrpc\": \"2.0\", \"id\": 4, \"method\": \"fail\"}\n",
+        ))?;
+        let answers: Vec<_> = replies
+            .iter()
+            .map(|reply| (reply["id"].clone(), reply["error"]["code"].clone()))
+            .collect();
+        assert_eq!(
+            answers,
+            [
+                (Value::Null, PARSE_ERROR.into()),
+                (Value::Null, INVALID_REQUEST.into()),
+                (Value::Null, INVALID_REQUEST.into()),
+                (Value::Null, INVALID_REQUEST.into()),
+                (4.into(), SERVER_ERROR.into()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_target() {
+        let request = parse_target("GET", "/search?q=k8s%2Dcontrol+app&all");
+        assert_eq!(request.path, "/search");
+        assert_eq!(request.param("q"), Some("k8s-control app"));
+        assert_eq!(request.param("all"), Some(""));
+        assert_eq!(request.param("missing"), None);
+        assert_eq!(parse_target("GET", "/projects").query, []);
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("caf%C3%A9"), "café");
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut metrics = Metrics::default();
+        metrics.search(0.02, 3);
+        metrics.search(3.0, 0);
+        metrics.error(404);
+        let rendered = metrics.render();
+        for line in [
+            "# TYPE find_project_searches_total counter",
+            "find_project_searches_total 2",
+            "find_project_matches_total 3",
+            "find_project_errors_total{status=\"404\"} 1",
+            "# TYPE find_project_search_duration_seconds histogram",
+            "find_project_search_duration_seconds_bucket{le=\"0.01\"} 0",
+            "find_project_search_duration_seconds_bucket{le=\"0.025\"} 1",
+            "find_project_search_duration_seconds_bucket{le=\"5\"} 2",
+            "find_project_search_duration_seconds_bucket{le=\"+Inf\"} 2",
+            "find_project_search_duration_seconds_sum 3.02",
+            "find_project_search_duration_seconds_count 2",
+        ] {
+            assert!(rendered.lines().any(|l| l == line), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_read_request() -> io::Result<()> {
+        let mut raw = "GET /projects HTTP/1.1\r\nHost: localhost\r\n\r\n".as_bytes();
+        let request = read_request(&mut raw)?.expect("it's HTTP");
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/projects");
+        assert!(read_request(&mut "hello\r\n".as_bytes())?.is_none());
+        Ok(())
+    }
+}
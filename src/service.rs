@@ -0,0 +1,321 @@
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Name of the service, as systemd and the Task Scheduler know it.
+const NAME: &str = "find-project";
+
+/// Label of the launchd agent on macOS.
+const LABEL: &str = "io.github.patrickdappollonio.find-project";
+
+/// Environment variables copied into the service, since services don't
+/// start from a shell that sets them.
+const VARIABLES: &[&str] = &[
+    "FP_FOLDER",
+    "GOPATH",
+    "FP_CONFIG",
+    "FP_PROFILE",
+    "FP_DATA_DIR",
+];
+
+/// Longest command schtasks takes for a task.
+const MAX_TASK_COMMAND: usize = 261;
+
+/// Sets up "find-project serve --http" to start at login as a user
+/// service, and starts it: a systemd user unit on Linux, a launchd agent
+/// on macOS and a Task Scheduler task on Windows.
+pub fn install(address: &str) -> Result<()> {
+    let exe = env::current_exe()
+        .and_then(fs::canonicalize)
+        .context("Unable to find the running binary")?;
+    let args = ["serve", "--http", address];
+    let variables: Vec<(&str, String)> = VARIABLES
+        .iter()
+        .filter_map(|&name| Some((name, env::var(name).ok()?)))
+        .collect();
+
+    if cfg!(windows) {
+        let command = schtasks_command(&exe, &args, &variables);
+        // schtasks rejects longer commands, like with many roots in
+        // $FP_FOLDER.
+        if command.len() > MAX_TASK_COMMAND {
+            bail!(
+                "The task's command is {} characters long, and the Task Scheduler allows at most {}",
+                command.len(),
+                MAX_TASK_COMMAND
+            );
+        }
+        run(
+            "schtasks",
+            &[
+                "/Create", "/F", "/SC", "ONLOGON", "/TN", NAME, "/TR", &command,
+            ],
+        )?;
+        run("schtasks", &["/Run", "/TN", NAME])?;
+        eprintln!("Installed the {} task, started at every login", NAME);
+        return Ok(());
+    }
+
+    let path = definition_path()?;
+    let definition = if cfg!(target_os = "macos") {
+        launchd_plist(&exe, &args, &variables)
+    } else {
+        systemd_unit(&exe, &args, &variables)
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Unable to create {:?}", dir))?;
+    }
+    fs::write(&path, definition).with_context(|| format!("Unable to write {:?}", path))?;
+
+    if cfg!(target_os = "macos") {
+        // Loading it again picks up the new definition.
+        let _ = run_quiet("launchctl", &["unload", &path.to_string_lossy()]);
+        run("launchctl", &["load", "-w", &path.to_string_lossy()])?;
+    } else {
+        run("systemctl", &["--user", "daemon-reload"])?;
+        run("systemctl", &["--user", "enable", "--now", NAME])?;
+        run("systemctl", &["--user", "restart", NAME])?;
+    }
+    eprintln!("Installed {}, started at every login", path.display());
+    Ok(())
+}
+
+/// Stops the service and removes it.
+pub fn uninstall() -> Result<()> {
+    if cfg!(windows) {
+        let _ = run_quiet("schtasks", &["/End", "/TN", NAME]);
+        run("schtasks", &["/Delete", "/F", "/TN", NAME])?;
+        eprintln!("Removed the {} task", NAME);
+        return Ok(());
+    }
+
+    let path = definition_path()?;
+    if !path.exists() {
+        bail!("The service isn't installed, there's no {}", path.display());
+    }
+    if cfg!(target_os = "macos") {
+        run("launchctl", &["unload", "-w", &path.to_string_lossy()])?;
+    } else {
+        run("systemctl", &["--user", "disable", "--now", NAME])?;
+    }
+    fs::remove_file(&path).with_context(|| format!("Unable to remove {:?}", path))?;
+    if !cfg!(target_os = "macos") {
+        run("systemctl", &["--user", "daemon-reload"])?;
+    }
+    eprintln!("Removed {}", path.display());
+    Ok(())
+}
+
+/// Prints whether the service is installed, and what the service manager
+/// says about it.
+pub fn status() -> Result<()> {
+    if cfg!(windows) {
+        return run("schtasks", &["/Query", "/TN", NAME, "/V", "/FO", "LIST"]);
+    }
+
+    let path = definition_path()?;
+    if !path.exists() {
+        println!("Not installed, \"find-project daemon install\" sets it up");
+        return Ok(());
+    }
+    println!("Installed: {}", path.display());
+    if cfg!(target_os = "macos") {
+        run("launchctl", &["list", LABEL])
+    } else {
+        // systemctl exits with an error when the service isn't running,
+        // which is a status like any other here.
+        let _ = run_quiet("systemctl", &["--user", "--no-pager", "status", NAME]);
+        Ok(())
+    }
+}
+
+// Returns where the service definition is written.
+fn definition_path() -> Result<PathBuf> {
+    let home = || {
+        env::var_os("HOME")
+            .map(PathBuf::from)
+            .context("Unable to find the service folder: $HOME is not set")
+    };
+    if cfg!(target_os = "macos") {
+        return Ok(home()?
+            .join("Library")
+            .join("LaunchAgents")
+            .join(format!("{}.plist", LABEL)));
+    }
+    if !cfg!(target_os = "linux") {
+        bail!("There are no user services to install on this platform");
+    }
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => home()?.join(".config"),
+    };
+    Ok(config
+        .join("systemd")
+        .join("user")
+        .join(format!("{}.service", NAME)))
+}
+
+fn systemd_unit(exe: &Path, args: &[&str], variables: &[(&str, String)]) -> String {
+    // Every argument is quoted, since systemd splits them on spaces.
+    let command: Vec<String> = iter_args(exe, args)
+        .map(|arg| format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    let mut unit = format!(
+        "[Unit]\nDescription=find-project search server\n\n[Service]\nExecStart={}\nRestart=on-failure\n",
+        command.join(" ")
+    );
+    for (name, value) in variables {
+        unit.push_str(&format!(
+            "Environment=\"{}={}\"\n",
+            name,
+            value.replace('"', "\\\"")
+        ));
+    }
+    unit.push_str("\n[Install]\nWantedBy=default.target\n");
+    unit
+}
+
+fn launchd_plist(exe: &Path, args: &[&str], variables: &[(&str, String)]) -> String {
+    let mut plist = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+        <plist version=\"1.0\">\n<dict>\n",
+    );
+    plist.push_str(&format!(
+        "  <key>Label</key>\n  <string>{}</string>\n",
+        LABEL
+    ));
+    plist.push_str("  <key>ProgramArguments</key>\n  <array>\n");
+    for arg in iter_args(exe, args) {
+        plist.push_str(&format!("    <string>{}</string>\n", escape_xml(&arg)));
+    }
+    plist.push_str("  </array>\n");
+    if !variables.is_empty() {
+        plist.push_str("  <key>EnvironmentVariables</key>\n  <dict>\n");
+        for (name, value) in variables {
+            plist.push_str(&format!(
+                "    <key>{}</key>\n    <string>{}</string>\n",
+                name,
+                escape_xml(value)
+            ));
+        }
+        plist.push_str("  </dict>\n");
+    }
+    plist.push_str("  <key>RunAtLoad</key>\n  <true/>\n");
+    plist.push_str("  <key>KeepAlive</key>\n  <dict>\n    <key>SuccessfulExit</key>\n    <false/>\n  </dict>\n");
+    plist.push_str("</dict>\n</plist>\n");
+    plist
+}
+
+// Tasks can't have their own environment, so the command sets the
+// variables in cmd before running the binary. Quoting the whole
+// assignment keeps spaces and "&" in the values.
+fn schtasks_command(exe: &Path, args: &[&str], variables: &[(&str, String)]) -> String {
+    let command = format!("\"{}\" {}", exe.display(), args.join(" "));
+    if variables.is_empty() {
+        return command;
+    }
+    let mut line = String::from("cmd /c");
+    for (name, value) in variables {
+        line.push_str(&format!(" set \"{}={}\" &&", name, value));
+    }
+    format!("{} {}", line, command)
+}
+
+fn iter_args<'a>(exe: &Path, args: &'a [&str]) -> impl Iterator<Item = String> + 'a {
+    let exe = exe.to_string_lossy().into_owned();
+    std::iter::once(exe).chain(args.iter().map(|arg| arg.to_string()))
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Runs a service manager command, failing when it does.
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .with_context(|| format!("Unable to run {}", program))?;
+    if !status.success() {
+        bail!("\"{} {}\" failed with {}", program, args.join(" "), status);
+    }
+    Ok(())
+}
+
+// Like run, but without printing anything.
+fn run_quiet(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Unable to run {}", program))?;
+    if !output.status.success() {
+        bail!(
+            "\"{} {}\" failed with {}",
+            program,
+            args.join(" "),
+            output.status
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_systemd_unit() {
+        let unit = systemd_unit(
+            Path::new("/opt/my tools/find-project"),
+            &["serve", "--http", "127.0.0.1:7433"],
+            &[("FP_FOLDER", "/home/me/Projects".to_string())],
+        );
+        assert!(unit.contains(
+            "ExecStart=\"/opt/my tools/find-project\" \"serve\" \"--http\" \"127.0.0.1:7433\"\n"
+        ));
+        assert!(unit.contains("Environment=\"FP_FOLDER=/home/me/Projects\"\n"));
+        assert!(unit.ends_with("[Install]\nWantedBy=default.target\n"));
+    }
+
+    #[test]
+    fn test_schtasks_command() {
+        let exe = Path::new(r"C:\Program Files\find-project.exe");
+        let args = ["serve", "--http", "127.0.0.1:7433"];
+        assert_eq!(
+            schtasks_command(exe, &args, &[]),
+            r#""C:\Program Files\find-project.exe" serve --http 127.0.0.1:7433"#
+        );
+        assert_eq!(
+            schtasks_command(
+                exe,
+                &args,
+                &[
+                    ("FP_FOLDER", r"D:\R&D;E:\work".to_string()),
+                    ("FP_PROFILE", "work".to_string()),
+                ]
+            ),
+            r#"cmd /c set "FP_FOLDER=D:\R&D;E:\work" && set "FP_PROFILE=work" && "C:\Program Files\find-project.exe" serve --http 127.0.0.1:7433"#
+        );
+    }
+
+    #[test]
+    fn test_launchd_plist() {
+        let plist = launchd_plist(
+            Path::new("/usr/local/bin/find-project"),
+            &["serve", "--http", "127.0.0.1:7433"],
+            &[("FP_FOLDER", "/Users/me/R&D".to_string())],
+        );
+        assert!(plist.contains(&format!("<string>{}</string>", LABEL)));
+        assert!(plist.contains(
+            "    <string>/usr/local/bin/find-project</string>\n    <string>serve</string>\n"
+        ));
+        assert!(
+            plist.contains("    <key>FP_FOLDER</key>\n    <string>/Users/me/R&amp;D</string>\n")
+        );
+    }
+}
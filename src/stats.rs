@@ -0,0 +1,251 @@
+//! Facts about project folders, used to summarize what the roots hold:
+//! the languages projects are written in, how much space they take and
+//! when they last changed.
+
+use crate::git;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Files at the top of a project that tell a language it's written in.
+const LANGUAGE_FILES: &[(&str, &str)] = &[
+    ("Cargo.toml", "Rust"),
+    ("go.mod", "Go"),
+    ("package.json", "JavaScript"),
+    ("tsconfig.json", "TypeScript"),
+    ("deno.json", "TypeScript"),
+    ("pyproject.toml", "Python"),
+    ("setup.py", "Python"),
+    ("requirements.txt", "Python"),
+    ("Gemfile", "Ruby"),
+    ("pom.xml", "Java"),
+    ("build.gradle", "Java"),
+    ("build.gradle.kts", "Kotlin"),
+    ("composer.json", "PHP"),
+    ("mix.exs", "Elixir"),
+    ("pubspec.yaml", "Dart"),
+    ("Package.swift", "Swift"),
+    ("stack.yaml", "Haskell"),
+    ("CMakeLists.txt", "C/C++"),
+];
+
+/// Extensions of files at the top of a project that tell a language it's
+/// written in, for build files named after the project.
+const LANGUAGE_EXTENSIONS: &[(&str, &str)] = &[("csproj", "C#"), ("sln", "C#"), ("fsproj", "F#")];
+
+/// Returns the languages a project is written in, guessed from the build
+/// files at its top, in no particular order. Projects without any known
+/// build file return none.
+pub fn languages(dir: &Path) -> Vec<&'static str> {
+    let mut languages = Vec::new();
+    let mut add = |language| {
+        if !languages.contains(&language) {
+            languages.push(language);
+        }
+    };
+
+    for (file, language) in LANGUAGE_FILES {
+        if dir.join(file).exists() {
+            add(*language);
+        }
+    }
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let Some(extension) = path.extension() else {
+            continue;
+        };
+        for (known, language) in LANGUAGE_EXTENSIONS {
+            if extension == *known {
+                add(*language);
+            }
+        }
+    }
+    languages
+}
+
+/// Returns roughly how many bytes a folder takes on disk, adding up every
+/// file inside it. Symlinks aren't followed, files with several hard
+/// links are counted every time, and folders that can't be read count as
+/// empty, so it's an estimate rather than what deleting it would free.
+pub fn disk_usage(dir: &Path) -> u64 {
+    let mut total = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            // Unlike fs::metadata, this doesn't follow symlinks.
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                total += allocated(&metadata);
+            }
+        }
+    }
+    total
+}
+
+// Returns the space a file takes, which for sparse or small files on Unix
+// differs from its length.
+#[cfg(unix)]
+fn allocated(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Returns when a project last changed, in seconds since the Unix epoch:
+/// the newest modification time of the folder, of what's right inside it
+/// and, for git repositories, of the index and the log of HEAD, which
+/// change with every commit, checkout and staged file. Files deeper inside
+/// aren't looked at, so it's quick enough to run on every project. Returns
+/// None when none of them can be read.
+pub fn last_modified(dir: &Path) -> Option<u64> {
+    let mut paths = vec![dir.to_path_buf()];
+    paths.extend(
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path()),
+    );
+    if let Some(git_dir) = git::git_dir(dir) {
+        paths.push(git_dir.join("index"));
+        paths.push(git_dir.join("logs").join("HEAD"));
+    }
+
+    paths
+        .iter()
+        .filter_map(|path| path.symlink_metadata().and_then(|m| m.modified()).ok())
+        .filter_map(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| time.as_secs())
+        .max()
+}
+
+/// Formats a number of bytes with binary units, like "1.5 GiB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Parses a number of bytes written with an optional binary unit, like
+/// "512M", "1.5GiB" or "2048".
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("{:?} isn't a size, like \"512M\" or \"1G\"", size))?;
+    let exponent = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 1,
+        "m" | "mb" | "mib" => 2,
+        "g" | "gb" | "gib" => 3,
+        "t" | "tb" | "tib" => 4,
+        _ => return Err(format!("Unknown unit {:?} in {:?}", unit, size)),
+    };
+    Ok((number * 1024f64.powi(exponent)) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_languages() -> Result<()> {
+        let dir = tempdir()?;
+        assert!(languages(dir.path()).is_empty());
+
+        fs::write(dir.path().join("Cargo.toml"), "")?;
+        fs::write(dir.path().join("pyproject.toml"), "")?;
+        fs::write(dir.path().join("setup.py"), "")?;
+        fs::write(dir.path().join("Tools.csproj"), "")?;
+        let mut found = languages(dir.path());
+        found.sort();
+        assert_eq!(found, ["C#", "Python", "Rust"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disk_usage() -> Result<()> {
+        let dir = tempdir()?;
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src"))?;
+        assert_eq!(disk_usage(&project), 0);
+
+        // Not all zeros, so file systems that compress don't shrink it.
+        let contents: Vec<u8> = (0..64 * 1024u32).map(|i| (i * 7919 % 251) as u8).collect();
+        fs::write(project.join("src").join("main.rs"), &contents)?;
+        assert!(disk_usage(&project) >= 32 * 1024);
+        assert_eq!(disk_usage(&dir.path().join("missing")), 0);
+
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link");
+            fs::create_dir(&link)?;
+            std::os::unix::fs::symlink(&project, link.join("project"))?;
+            assert!(disk_usage(&link) < disk_usage(&project));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_modified() -> Result<()> {
+        let dir = tempdir()?;
+        let project = dir.path().join("project");
+        fs::create_dir_all(project.join("src"))?;
+        fs::write(project.join("README.md"), "")?;
+        let readme = project.join("README.md").metadata()?.modified()?;
+        let readme = readme.duration_since(UNIX_EPOCH)?.as_secs();
+
+        let time = last_modified(&project).expect("the project exists");
+        assert!(time >= readme);
+        assert_eq!(last_modified(&dir.path().join("missing")), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("2048"), Ok(2048));
+        assert_eq!(parse_size("1.5K"), Ok(1536));
+        assert_eq!(parse_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_size("1 GiB"), Ok(1 << 30));
+        assert_eq!(parse_size("2gb"), Ok(2 << 30));
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("12X").is_err());
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+}
@@ -0,0 +1,110 @@
+use crate::error::{FindError, Result};
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Returns the directory where find-project keeps state between runs,
+/// which may not exist yet: it's only created when something is written
+/// to it. `$FP_DATA_DIR` takes precedence, then `$XDG_DATA_HOME`, then
+/// the platform default.
+pub fn data_dir() -> Result<PathBuf> {
+    let dir = if let Ok(dir) = env::var("FP_DATA_DIR") {
+        PathBuf::from(dir)
+    } else if let Ok(dir) = env::var("XDG_DATA_HOME") {
+        PathBuf::from(dir).join("find-project")
+    } else if cfg!(windows) {
+        env::var("LOCALAPPDATA")
+            .map(PathBuf::from)
+            .map_err(|_| FindError::NoDataDir {
+                variable: "LOCALAPPDATA",
+            })?
+            .join("find-project")
+    } else {
+        env::var("HOME")
+            .map(PathBuf::from)
+            .map_err(|_| FindError::NoDataDir { variable: "HOME" })?
+            .join(".local")
+            .join("share")
+            .join("find-project")
+    };
+    Ok(dir)
+}
+
+/// Reads a file line by line, skipping empty lines. A missing file is
+/// treated as empty.
+pub fn read_lines(path: &Path) -> Result<Vec<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from)
+            .collect()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(source) => Err(FindError::Read {
+            path: path.to_path_buf(),
+            source,
+        }),
+    }
+}
+
+/// Replaces the contents of a file with the given lines. The new contents
+/// are written to a temporary file first and renamed into place so an
+/// interrupted write never leaves a truncated file behind. The directory
+/// it's in is created first if it doesn't exist yet.
+pub fn write_lines<I, S>(path: &Path, lines: I) -> Result<()>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut contents = String::new();
+    for line in lines {
+        contents.push_str(line.as_ref());
+        contents.push('\n');
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|source| FindError::Write {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, contents).map_err(|source| FindError::Write {
+        path: tmp.clone(),
+        source,
+    })?;
+    fs::rename(&tmp, path).map_err(|source| FindError::Write {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_read_lines_missing_file() -> Result<()> {
+        let dir = tempdir()?;
+        let lines = read_lines(&dir.path().join("missing"))?;
+        assert!(lines.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_then_read_lines() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("find-project").join("lines");
+
+        write_lines(&path, ["one", "", "two"])?;
+        assert_eq!(read_lines(&path)?, vec!["one", "two"]);
+
+        write_lines(&path, ["three"])?;
+        assert_eq!(read_lines(&path)?, vec!["three"]);
+        Ok(())
+    }
+}
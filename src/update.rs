@@ -0,0 +1,216 @@
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+/// Repository the releases are published in.
+const REPOSITORY: &str = "patrickdappollonio/find-project";
+
+/// A release on GitHub, with only the fields used to update.
+#[derive(serde::Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(serde::Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Replaces the running binary with the one from the latest release on
+/// GitHub, after checking the archive against the checksum published with
+/// it. With `check`, only prints whether there's a newer version.
+pub fn run(check: bool) -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let url = format!(
+        "https://api.github.com/repos/{}/releases/latest",
+        REPOSITORY
+    );
+    let release: Release = serde_json::from_slice(&download(&url)?)
+        .context("Unable to read the latest release from GitHub")?;
+    let latest = release.tag_name.trim_start_matches('v');
+
+    if !is_newer(latest, current) {
+        eprintln!("find-project {} is the latest version", current);
+        return Ok(());
+    }
+    if check {
+        println!("find-project {} is available, this is {}", latest, current);
+        return Ok(());
+    }
+
+    let exe = env::current_exe()
+        .and_then(fs::canonicalize)
+        .context("Unable to find the running binary")?;
+    if exe.components().any(|c| c.as_os_str() == "Cellar") {
+        bail!("find-project was installed with Homebrew, update it with \"brew upgrade find-project\"");
+    }
+
+    let Some(platform) = platform() else {
+        bail!(
+            "There are no release binaries for {} on {}",
+            env::consts::ARCH,
+            env::consts::OS
+        );
+    };
+    // Archives are named like find-project-v1.2.0-linux-x86_64.tar.gz,
+    // next to their checksum, find-project-v1.2.0-linux-x86_64.sha256.
+    let name = format!("find-project-{}-{}", release.tag_name, platform);
+    let extension = if cfg!(windows) { ".zip" } else { ".tar.gz" };
+    let asset = |suffix: &str| {
+        release
+            .assets
+            .iter()
+            .find(|asset| asset.name.starts_with(&name) && asset.name.ends_with(suffix))
+    };
+    let Some(archive) = asset(extension) else {
+        bail!("Release {} has no {} archive", release.tag_name, platform);
+    };
+    let Some(checksum) = asset(".sha256") else {
+        bail!(
+            "Release {} has no checksum for {}, so it can't be verified",
+            release.tag_name,
+            archive.name
+        );
+    };
+
+    let dir = env::temp_dir().join(format!("find-project-update-{}", process::id()));
+    fs::create_dir_all(&dir).with_context(|| format!("Unable to create {:?}", dir))?;
+    let result = install(archive, checksum, &dir, &exe);
+    fs::remove_dir_all(&dir).with_context(|| format!("Unable to remove {:?}", dir))?;
+    result?;
+
+    eprintln!("Updated find-project from {} to {}", current, latest);
+    Ok(())
+}
+
+// Downloads, verifies and unpacks the archive in dir, then moves the
+// binary inside it over the running one.
+fn install(archive: &Asset, checksum: &Asset, dir: &Path, exe: &Path) -> Result<()> {
+    eprintln!("Downloading {}", archive.name);
+    let contents = download(&archive.browser_download_url)?;
+    let expected = String::from_utf8_lossy(&download(&checksum.browser_download_url)?)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let actual = format!("{:x}", Sha256::digest(&contents));
+    if actual != expected {
+        bail!(
+            "The checksum of {} doesn't match the published one, not updating",
+            archive.name
+        );
+    }
+
+    let path = dir.join(&archive.name);
+    fs::write(&path, &contents).with_context(|| format!("Unable to write {:?}", path))?;
+    // tar ships with every supported system, and on Windows it reads
+    // zip files too.
+    let status = Command::new("tar")
+        .arg("-xf")
+        .arg(&path)
+        .arg("-C")
+        .arg(dir)
+        .status()
+        .context("Unable to run tar, is it installed?")?;
+    if !status.success() {
+        bail!(
+            "Unable to unpack {}: tar exited with {}",
+            archive.name,
+            status
+        );
+    }
+
+    let binary = dir.join(format!("find-project{}", env::consts::EXE_SUFFIX));
+    if !binary.is_file() {
+        bail!("There's no find-project binary in {}", archive.name);
+    }
+    replace(exe, &binary)
+}
+
+// Downloads a URL with curl, which every supported system ships.
+fn download(url: &str) -> Result<Vec<u8>> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location"])
+        .arg(url)
+        .output()
+        .context("Unable to run curl, is it installed?")?;
+    if !output.status.success() {
+        bail!(
+            "Unable to download {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+// Moves the new binary next to the running one first, so the final rename
+// happens inside a single folder and either fully happens or doesn't.
+fn replace(exe: &Path, binary: &Path) -> Result<()> {
+    let staged = sibling(exe, "new");
+    fs::copy(binary, &staged).with_context(|| format!("Unable to write {:?}", staged))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))
+            .with_context(|| format!("Unable to make {:?} executable", staged))?;
+    }
+
+    // Windows doesn't replace a running binary, but it does rename it.
+    #[cfg(windows)]
+    {
+        let old = sibling(exe, "old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).with_context(|| format!("Unable to move {:?} away", exe))?;
+    }
+    fs::rename(&staged, exe).with_context(|| format!("Unable to replace {:?}", exe))
+}
+
+// Returns a hidden file next to the binary, like .find-project.new.
+fn sibling(exe: &Path, suffix: &str) -> PathBuf {
+    let name = exe.file_name().unwrap_or_default().to_string_lossy();
+    exe.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+// Returns the platform in the name of the release archives, as built by
+// the release workflow.
+fn platform() -> Option<&'static str> {
+    match (env::consts::OS, env::consts::ARCH) {
+        ("linux", "x86_64") => Some("linux-x86_64"),
+        ("linux", "aarch64") => Some("linux-arm64"),
+        ("macos", "x86_64") => Some("darwin-x86_64"),
+        ("macos", "aarch64") => Some("darwin-arm64"),
+        ("windows", "x86_64") => Some("windows-x86_64"),
+        _ => None,
+    }
+}
+
+// Compares two versions like "1.10.0" number by number. Anything after a
+// dash, as in "1.2.0-rc.1", is ignored.
+fn is_newer(latest: &str, current: &str) -> bool {
+    let numbers = |version: &str| -> Vec<u64> {
+        let version = version.split('-').next().unwrap_or_default();
+        version.split('.').map(|n| n.parse().unwrap_or(0)).collect()
+    };
+    numbers(latest) > numbers(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.0"));
+        assert!(is_newer("1.10.0", "1.9.3"));
+        assert!(is_newer("1.0.1", "1.0"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1.0", "0.2.0"));
+        assert!(!is_newer("1.0.0-rc.1", "1.0.0"));
+    }
+}
@@ -0,0 +1,424 @@
+use crate::finder::{Entry, EntryType};
+use io_uring::{opcode, squeue, types, IoUring};
+use std::ffi::{CString, OsStr, OsString};
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+// How many folders are read at the same time, which is also how many
+// submissions the ring holds.
+pub(crate) const BATCH: usize = 256;
+
+// Size of the buffer getdents64 fills with entries, like glibc's readdir
+// uses.
+const DIRENT_BUFFER: usize = 32 * 1024;
+
+// How many times waiting for a batch is retried after it failed, and how
+// long to pause between tries, before giving up on the operations still
+// running.
+const DRAIN_TRIES: usize = 100;
+const DRAIN_PAUSE: Duration = Duration::from_millis(10);
+
+// Reads folders with io_uring, for FinderOptions::io_uring. Opening a
+// batch of folders and looking up the type of the entries the file
+// system doesn't report, like some network file systems do, are each
+// submitted at once, so on file systems where every lookup waits on a
+// disk or a server, they wait together instead of one after another.
+// io_uring can't list folders, so each one is still listed with its own
+// getdents64 calls.
+pub(crate) struct Reader {
+    ring: IoUring,
+    buffer: Vec<u8>,
+    // Operations of a failed batch that never completed. The kernel may
+    // still write to what they point to, so that's leaked, and the ring
+    // isn't used again.
+    running: usize,
+    // Makes waiting for the next batches fail this many times, to test
+    // what happens then.
+    #[cfg(test)]
+    fail_waits: usize,
+}
+
+// An entry of a folder read by Reader.
+pub(crate) struct Dirent {
+    name: OsString,
+    // The errno of looking up its type, if that failed.
+    entry_type: Result<EntryType, i32>,
+}
+
+impl Entry for Dirent {
+    fn name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    fn entry_type(&self) -> io::Result<EntryType> {
+        self.entry_type.map_err(io::Error::from_raw_os_error)
+    }
+
+    fn has_hidden_attribute(&self) -> bool {
+        false
+    }
+}
+
+impl Reader {
+    // Fails on kernels older than 5.6 and where io_uring is disabled,
+    // like in containers whose seccomp profile blocks it.
+    pub(crate) fn new() -> io::Result<Self> {
+        Ok(Reader {
+            ring: IoUring::new(BATCH as u32)?,
+            buffer: vec![0; DIRENT_BUFFER],
+            running: 0,
+            #[cfg(test)]
+            fail_waits: 0,
+        })
+    }
+
+    // Returns the entries of every folder, at most BATCH of them, in the
+    // same order, or the error reading it. Fails as a whole only when the
+    // ring itself does. Every folder opened is closed either way, but
+    // when a batch fails with folders still being opened, the kernel may
+    // open them after this returns, and those stay open.
+    pub(crate) fn read_dirs(
+        &mut self,
+        dirs: &[PathBuf],
+    ) -> io::Result<Vec<io::Result<Vec<Dirent>>>> {
+        // Paths can't have NUL bytes, so those that do can't be opened.
+        let paths: Vec<_> = dirs
+            .iter()
+            .map(|dir| CString::new(dir.as_os_str().as_bytes()).ok())
+            .collect();
+        let opens = paths
+            .iter()
+            .map(|path| {
+                path.as_ref().map(|path| {
+                    opcode::OpenAt::new(types::Fd(libc::AT_FDCWD), path.as_ptr())
+                        .flags(libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC)
+                        .build()
+                })
+            })
+            .collect();
+        let mut fds = vec![None; dirs.len()];
+        let read = match self.submit(opens, &mut fds) {
+            Ok(()) => self.list(&fds),
+            Err(err) => Err(err),
+        };
+
+        for fd in fds.into_iter().flatten().filter(|fd| *fd >= 0) {
+            // SAFETY: the folder was opened by this batch, and nothing
+            // else closes it.
+            unsafe { libc::close(fd) };
+        }
+        if self.running > 0 {
+            mem::forget(paths);
+        }
+        read
+    }
+
+    // Lists the folders opened, with the result of opening each of them,
+    // and looks up the type of the entries that need it.
+    fn list(&mut self, fds: &[Option<i32>]) -> io::Result<Vec<io::Result<Vec<Dirent>>>> {
+        let mut read = Vec::with_capacity(fds.len());
+        // Entries whose type the file system didn't report, as the folder
+        // they're in and their position in it.
+        let mut unknown = Vec::new();
+        for (i, fd) in fds.iter().enumerate() {
+            read.push(match *fd {
+                Some(fd) if fd >= 0 => getdents(fd, &mut self.buffer).map(|(entries, untyped)| {
+                    unknown.extend(untyped.into_iter().map(|j| (i, j)));
+                    entries
+                }),
+                Some(errno) => Err(io::Error::from_raw_os_error(-errno)),
+                None => Err(io::Error::from(io::ErrorKind::InvalidInput)),
+            });
+        }
+        self.look_up(fds, &mut read, &unknown)?;
+        Ok(read)
+    }
+
+    // Looks up the type of the entries at the given positions with statx,
+    // BATCH at a time so every buffer stays in place while the kernel
+    // fills it.
+    fn look_up(
+        &mut self,
+        fds: &[Option<i32>],
+        read: &mut [io::Result<Vec<Dirent>>],
+        unknown: &[(usize, usize)],
+    ) -> io::Result<()> {
+        for chunk in unknown.chunks(BATCH) {
+            let names: Vec<_> = chunk
+                .iter()
+                .map(|(i, j)| match &read[*i] {
+                    Ok(entries) => CString::new(entries[*j].name.as_bytes()).ok(),
+                    Err(_) => None,
+                })
+                .collect();
+            // SAFETY: statx is plain data, for which all zeroes is valid.
+            let mut stats: Vec<libc::statx> = vec![unsafe { mem::zeroed() }; chunk.len()];
+            let lookups = chunk
+                .iter()
+                .zip(&names)
+                .zip(stats.iter_mut())
+                .map(|(((i, _), name), stat)| {
+                    let name = name.as_ref()?;
+                    Some(
+                        opcode::Statx::new(
+                            types::Fd(fds[*i].expect("the folder was opened")),
+                            name.as_ptr(),
+                            (stat as *mut libc::statx).cast(),
+                        )
+                        .flags(libc::AT_SYMLINK_NOFOLLOW)
+                        .mask(libc::STATX_TYPE)
+                        .build(),
+                    )
+                })
+                .collect();
+            let mut results = vec![None; chunk.len()];
+            if let Err(err) = self.submit(lookups, &mut results) {
+                if self.running > 0 {
+                    mem::forget(names);
+                    mem::forget(stats);
+                }
+                return Err(err);
+            }
+            for (((i, j), result), stat) in chunk.iter().zip(results).zip(&stats) {
+                if let (Ok(entries), Some(result)) = (&mut read[*i], result) {
+                    entries[*j].entry_type = if result < 0 {
+                        Err(-result)
+                    } else {
+                        Ok(mode_type(stat.stx_mode as u32))
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Submits the operations given, at most BATCH of them, and waits for
+    // all of them, writing their results to the same positions. None
+    // leaves a position without an operation. When waiting fails, the
+    // operations already running are still waited for, since they point
+    // to buffers the caller drops next, and the results of those that
+    // complete are written too.
+    fn submit(
+        &mut self,
+        ops: Vec<Option<squeue::Entry>>,
+        results: &mut [Option<i32>],
+    ) -> io::Result<()> {
+        assert!(ops.len() <= BATCH, "at most {} operations at a time", BATCH);
+        if self.running > 0 {
+            return Err(io::Error::other(
+                "operations of a failed batch are still running",
+            ));
+        }
+        let mut pending = 0;
+        for (i, op) in ops.into_iter().enumerate() {
+            let Some(op) = op else {
+                continue;
+            };
+            // SAFETY: what the operations point to, paths, names and statx
+            // buffers, outlives them: this waits for all of them, and when
+            // it can't, the caller leaks it.
+            unsafe {
+                self.ring
+                    .submission()
+                    .push(&op.user_data(i as u64))
+                    .expect("the ring has room for a batch");
+            }
+            pending += 1;
+        }
+        while pending > 0 {
+            match self.wait(pending) {
+                Ok(_) => {}
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.running = self.drain(pending, results);
+                    return Err(err);
+                }
+            }
+            pending -= self.complete(results);
+        }
+        Ok(())
+    }
+
+    // Waits for the operations still running after waiting for them
+    // failed, trying again for a while. Returns how many never completed.
+    fn drain(&mut self, mut pending: usize, results: &mut [Option<i32>]) -> usize {
+        for _ in 0..DRAIN_TRIES {
+            if self.wait(pending).is_err() {
+                thread::sleep(DRAIN_PAUSE);
+            }
+            pending -= self.complete(results);
+            if pending == 0 {
+                break;
+            }
+        }
+        pending
+    }
+
+    fn wait(&mut self, pending: usize) -> io::Result<usize> {
+        #[cfg(test)]
+        if self.fail_waits > 0 {
+            self.fail_waits -= 1;
+            return Err(io::Error::other("waiting failed"));
+        }
+        self.ring.submit_and_wait(pending)
+    }
+
+    // Writes the results of the operations that completed, returning how
+    // many did.
+    fn complete(&mut self, results: &mut [Option<i32>]) -> usize {
+        let mut completed = 0;
+        for completion in self.ring.completion() {
+            results[completion.user_data() as usize] = Some(completion.result());
+            completed += 1;
+        }
+        completed
+    }
+}
+
+// Lists an open folder with getdents64, leaving out "." and "..", along
+// with the positions of the entries whose type the file system didn't
+// report, which are returned as Other.
+fn getdents(fd: i32, buffer: &mut [u8]) -> io::Result<(Vec<Dirent>, Vec<usize>)> {
+    let mut entries = Vec::new();
+    let mut unknown = Vec::new();
+    loop {
+        // SAFETY: the kernel writes at most buffer.len() bytes to it.
+        let read =
+            unsafe { libc::syscall(libc::SYS_getdents64, fd, buffer.as_mut_ptr(), buffer.len()) };
+        if read < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+        if read == 0 {
+            return Ok((entries, unknown));
+        }
+
+        // Each entry is a struct linux_dirent64: an 8 byte inode number, an
+        // 8 byte offset, its 2 byte length, a 1 byte type and its name,
+        // ending with a NUL byte.
+        let mut rest = &buffer[..read as usize];
+        while !rest.is_empty() {
+            let len = u16::from_ne_bytes([rest[16], rest[17]]) as usize;
+            let d_type = rest[18];
+            let name = &rest[19..len];
+            let name = &name[..name.iter().position(|b| *b == 0).unwrap_or(name.len())];
+            if name != b"." && name != b".." {
+                if d_type == libc::DT_UNKNOWN {
+                    unknown.push(entries.len());
+                }
+                entries.push(Dirent {
+                    name: OsStr::from_bytes(name).to_os_string(),
+                    entry_type: Ok(match d_type {
+                        libc::DT_DIR => EntryType::Dir,
+                        libc::DT_REG => EntryType::File,
+                        libc::DT_LNK => EntryType::Symlink,
+                        _ => EntryType::Other,
+                    }),
+                });
+            }
+            rest = &rest[len..];
+        }
+    }
+}
+
+fn mode_type(mode: u32) -> EntryType {
+    match mode & libc::S_IFMT {
+        libc::S_IFDIR => EntryType::Dir,
+        libc::S_IFREG => EntryType::File,
+        libc::S_IFLNK => EntryType::Symlink,
+        _ => EntryType::Other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_read_dirs() -> Result<()> {
+        // Skipped where io_uring is disabled.
+        let Ok(mut reader) = Reader::new() else {
+            return Ok(());
+        };
+        let dir = tempdir()?;
+        let path = dir.path();
+        fs::create_dir(path.join("api"))?;
+        fs::write(path.join("README.md"), "")?;
+        std::os::unix::fs::symlink(path.join("api"), path.join("link"))?;
+
+        let mut read = reader.read_dirs(&[path.to_path_buf(), path.join("missing")])?;
+        let missing = read.pop().expect("two folders were read");
+        assert_eq!(
+            missing.err().map(|err| err.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+        let mut entries: Vec<_> = read
+            .pop()
+            .expect("two folders were read")?
+            .iter()
+            .map(|entry| Ok((entry.name(), entry.entry_type()?)))
+            .collect::<io::Result<_>>()?;
+        entries.sort();
+        assert_eq!(
+            entries,
+            vec![
+                ("README.md".into(), EntryType::File),
+                ("api".into(), EntryType::Dir),
+                ("link".into(), EntryType::Symlink),
+            ]
+        );
+        Ok(())
+    }
+
+    // Returns the open file descriptors of this process pointing into dir.
+    fn open_in(dir: &std::path::Path) -> Result<Vec<PathBuf>> {
+        let mut open = Vec::new();
+        for fd in fs::read_dir("/proc/self/fd")? {
+            if let Ok(target) = fs::read_link(fd?.path()) {
+                if target.starts_with(dir) {
+                    open.push(target);
+                }
+            }
+        }
+        Ok(open)
+    }
+
+    #[test]
+    fn test_read_dirs_failed_wait() -> Result<()> {
+        let Ok(mut reader) = Reader::new() else {
+            return Ok(());
+        };
+        let dir = tempdir()?;
+        let path = dir.path().canonicalize()?;
+        let dirs: Vec<_> = (0..8).map(|i| path.join(i.to_string())).collect();
+        for dir in &dirs {
+            fs::create_dir(dir)?;
+        }
+
+        // The folders opened before waiting failed are still closed, and
+        // the ring works again for the next batch.
+        reader.fail_waits = 1;
+        assert!(reader.read_dirs(&dirs).is_err());
+        assert_eq!(reader.running, 0);
+        assert_eq!(open_in(&path)?, Vec::<PathBuf>::new());
+        assert_eq!(reader.read_dirs(&dirs)?.len(), dirs.len());
+        assert_eq!(open_in(&path)?, Vec::<PathBuf>::new());
+
+        // Once operations never complete, the ring isn't used again.
+        reader.fail_waits = DRAIN_TRIES + 1;
+        assert!(reader.read_dirs(&dirs).is_err());
+        assert!(reader.running > 0);
+        assert!(reader.read_dirs(&dirs).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use crate::error::{FindError, Result};
+use crate::git::{self, Status};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// The version control systems whose repositories are recognized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Git,
+    Mercurial,
+    Subversion,
+}
+
+/// Returns which version control system a repository root uses, or None
+/// when the folder isn't one.
+pub fn detect(repo: &Path) -> Option<Kind> {
+    if git::git_dir(repo).is_some() {
+        Some(Kind::Git)
+    } else if repo.join(".hg").is_dir() {
+        Some(Kind::Mercurial)
+    } else if repo.join(".svn").is_dir() {
+        Some(Kind::Subversion)
+    } else {
+        None
+    }
+}
+
+/// Returns the branch checked out in a repository root without running
+/// any command, or None when it's unknown. Subversion working copies
+/// don't record it anywhere cheap to read.
+pub fn branch(repo: &Path) -> Option<String> {
+    match detect(repo)? {
+        Kind::Git => git::head_branch(repo),
+        Kind::Mercurial => Some(hg_branch(repo)),
+        Kind::Subversion => None,
+    }
+}
+
+// The branch file is only written for branches other than the default
+// one.
+fn hg_branch(repo: &Path) -> String {
+    fs::read_to_string(repo.join(".hg").join("branch"))
+        .map(|branch| branch.trim().to_string())
+        .unwrap_or_else(|_| "default".to_string())
+}
+
+/// Returns the status of a repository root, or None when the folder isn't
+/// one. Only git knows how far ahead or behind its upstream a branch is,
+/// without going through the network.
+pub fn status(repo: &Path) -> Result<Option<Status>> {
+    match detect(repo) {
+        Some(Kind::Git) => git::status(repo),
+        Some(Kind::Mercurial) => Ok(Some(Status {
+            branch: Some(hg_branch(repo)),
+            dirty: !run(repo, "hg", &["status"])?.trim().is_empty(),
+            ..Default::default()
+        })),
+        Some(Kind::Subversion) => Ok(Some(Status {
+            branch: svn_branch(&run(repo, "svn", &["info", "--show-item", "relative-url"])?),
+            dirty: !run(repo, "svn", &["status"])?.trim().is_empty(),
+            ..Default::default()
+        })),
+        None => Ok(None),
+    }
+}
+
+/// Returns when the revision checked out in a repository root was made,
+/// in seconds since the Unix epoch, or None when it's unknown.
+pub fn last_commit_time(repo: &Path) -> Result<Option<u64>> {
+    match detect(repo) {
+        Some(Kind::Git) => git::last_commit_time(repo),
+        Some(Kind::Mercurial) => {
+            // Prints the time followed by the timezone offset.
+            let output = run(
+                repo,
+                "hg",
+                &["log", "-r", ".", "--template", "{date|hgdate}"],
+            )?;
+            Ok(output
+                .split_whitespace()
+                .next()
+                .and_then(|time| time.parse().ok()))
+        }
+        // Reading the date of a Subversion revision needs the server.
+        Some(Kind::Subversion) | None => Ok(None),
+    }
+}
+
+// Runs a version control command inside a repository and returns what it
+// printed.
+fn run(repo: &Path, program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .map_err(|source| FindError::Command {
+            program: program.to_string(),
+            source,
+        })?;
+    if !output.status.success() {
+        return Err(FindError::CommandFailed {
+            command: format!("{} {}", program, args.join(" ")),
+            dir: repo.to_path_buf(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Returns the branch a Subversion working copy is on from its URL
+// relative to the repository root, following the usual layout of
+// "trunk", "branches/<name>" and "tags/<name>".
+fn svn_branch(relative_url: &str) -> Option<String> {
+    let path = relative_url.trim().trim_start_matches("^/");
+    let mut parts = path.split('/');
+    match parts.next()? {
+        "" => None,
+        "branches" | "tags" => parts.next().map(String::from),
+        first => Some(first.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_detect() -> Result<()> {
+        let dir = tempdir()?;
+        for (name, kind) in [
+            ("api", Kind::Git),
+            ("legacy", Kind::Mercurial),
+            ("ancient", Kind::Subversion),
+        ] {
+            let marker = match kind {
+                Kind::Git => ".git",
+                Kind::Mercurial => ".hg",
+                Kind::Subversion => ".svn",
+            };
+            fs::create_dir_all(dir.path().join(name).join(marker))?;
+            assert_eq!(detect(&dir.path().join(name)), Some(kind));
+        }
+        assert_eq!(detect(dir.path()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_svn_branch() {
+        assert_eq!(svn_branch("^/trunk\n").as_deref(), Some("trunk"));
+        assert_eq!(
+            svn_branch("^/branches/release-2/src").as_deref(),
+            Some("release-2")
+        );
+        assert_eq!(svn_branch("^/tags/v1.0").as_deref(), Some("v1.0"));
+        assert_eq!(svn_branch("^/"), None);
+    }
+}